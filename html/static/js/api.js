@@ -0,0 +1,304 @@
+//接口文档, 内容全部来自 /openapi.json
+const METHOD_CLASS = {
+    get: 'label-primary',
+    post: 'label-success',
+    put: 'label-warning',
+    delete: 'label-danger',
+};
+
+let spec = null;
+
+function escapeHtml(value) {
+    return $('<div>').text(value === undefined || value === null ? '' : String(value)).html();
+}
+
+function schemaName(ref) {
+    return ref.split('/').pop();
+}
+
+function resolveSchema(schema) {
+    if (schema && schema.$ref) {
+        return spec.components.schemas[schemaName(schema.$ref)];
+    }
+    return schema || {};
+}
+
+//按 schema 生成示例请求体
+function exampleOf(schema, depth) {
+    schema = resolveSchema(schema);
+    if (depth > 8) {
+        return null;
+    }
+    if (schema.example !== undefined) {
+        return schema.example;
+    }
+    if (schema.enum) {
+        return schema.enum[0];
+    }
+    if (schema.oneOf) {
+        return exampleOf(schema.oneOf[0], depth + 1);
+    }
+    if (schema.allOf) {
+        let merged = {};
+        schema.allOf.forEach(function (item) {
+            Object.assign(merged, exampleOf(item, depth + 1));
+        });
+        return merged;
+    }
+    let type = Array.isArray(schema.type) ? schema.type[0] : schema.type;
+    switch (type) {
+        case 'object': {
+            let value = {};
+            Object.keys(schema.properties || {}).forEach(function (key) {
+                value[key] = exampleOf(schema.properties[key], depth + 1);
+            });
+            return value;
+        }
+        case 'array':
+            return [exampleOf(schema.items, depth + 1)];
+        case 'integer':
+        case 'number':
+            return 0;
+        case 'boolean':
+            return false;
+        case 'string':
+            return '';
+        default:
+            return null;
+    }
+}
+
+function schemaLink(schema) {
+    if (schema && schema.$ref) {
+        let name = schemaName(schema.$ref);
+        return `<a href="#schema_${name}">${name}</a>`;
+    }
+    return escapeHtml((schema && schema.type) || '');
+}
+
+function renderParams(op, id) {
+    let params = op.parameters || [];
+    if (params.length === 0) {
+        return '';
+    }
+    let rows = params.map(function (param) {
+        return `<tr>
+            <td>${escapeHtml(param.name)}${param.required ? ' <b style="color: red">*</b>' : ''}</td>
+            <td>${escapeHtml(param.in)}</td>
+            <td>${schemaLink(param.schema)}</td>
+            <td><input type="text" class="form-control input-sm" data-op="${id}" data-in="${param.in}" data-name="${escapeHtml(param.name)}"></td>
+        </tr>`;
+    }).join('');
+    return `<table class="table table-condensed"><thead><tr><th>参数</th><th>位置</th><th>类型</th><th>值</th></tr></thead><tbody>${rows}</tbody></table>`;
+}
+
+function renderBody(op, id) {
+    if (!op.requestBody) {
+        return '';
+    }
+    let types = Object.keys(op.requestBody.content);
+    let options = types.map(function (type) {
+        return `<option>${escapeHtml(type)}</option>`;
+    }).join('');
+    let example = JSON.stringify(exampleOf(op.requestBody.content[types[0]].schema, 0), null, 2);
+    let schemas = types.map(function (type) {
+        return `${escapeHtml(type)}: ${schemaLink(op.requestBody.content[type].schema)}`;
+    }).join('<br>');
+    return `<p>请求体<br>${schemas}</p>
+        <select class="form-control input-sm" id="body_type_${id}" style="width: auto; margin-bottom: 5px">${options}</select>
+        <textarea class="form-control" id="body_${id}" rows="6" style="font-family: monospace">${escapeHtml(example)}</textarea>`;
+}
+
+function renderResponses(op) {
+    let rows = Object.keys(op.responses).map(function (status) {
+        let response = op.responses[status];
+        let content = Object.keys(response.content || {}).map(function (type) {
+            return `${escapeHtml(type)}: ${schemaLink(response.content[type].schema)}`;
+        }).join('<br>');
+        return `<tr><td>${status}</td><td>${escapeHtml(response.description)}</td><td>${content}</td></tr>`;
+    }).join('');
+    return `<table class="table table-condensed"><thead><tr><th>状态码</th><th>说明</th><th>内容</th></tr></thead><tbody>${rows}</tbody></table>`;
+}
+
+function isStream(op) {
+    let ok = op.responses['200'];
+    return ok && ok.content && ok.content['text/event-stream'] !== undefined;
+}
+
+function renderOperation(path, method, op, id) {
+    return `<div class="panel panel-default api_op">
+        <div class="panel-heading" data-toggle="collapse" data-target="#op_${id}" style="cursor: pointer">
+            <span class="label ${METHOD_CLASS[method] || 'label-default'} api_method">${method}</span>
+            <code>${escapeHtml(path)}</code> ${escapeHtml(op.summary)}
+        </div>
+        <div class="panel-collapse collapse" id="op_${id}">
+            <div class="panel-body">
+                <p>${escapeHtml(op.description)}</p>
+                ${renderParams(op, id)}
+                ${renderBody(op, id)}
+                <button type="button" class="btn btn-primary btn-sm api_send" style="margin-top: 10px"
+                        data-op="${id}" data-path="${escapeHtml(path)}" data-method="${method}">发送</button>
+                <pre id="result_${id}" style="margin-top: 10px; display: none"></pre>
+                ${renderResponses(op)}
+            </div>
+        </div>
+    </div>`;
+}
+
+function requestUrl(id, path) {
+    let query = {};
+    $(`input[data-op="${id}"]`).each(function () {
+        let value = $(this).val();
+        let name = $(this).data('name');
+        if (isBlank(value)) {
+            return;
+        }
+        if ($(this).data('in') === 'path') {
+            path = path.replace(`{${name}}`, encodeURIComponent(value));
+        } else {
+            query[name] = value;
+        }
+    });
+    let params = $.param(query);
+    return params ? `${path}?${params}` : path;
+}
+
+function isBlank(value) {
+    return value === undefined || value === null || !/\S/.test(value);
+}
+
+//text/event-stream 接口逐条显示推送的事件
+function sendStream(url, result) {
+    let source = new EventSource(url);
+    let lines = [];
+    let append = function (event) {
+        lines.push(`event: ${event.type}\ndata: ${event.data}`);
+        result.text(lines.join('\n\n'));
+    };
+    ['result', 'progress', 'done'].forEach(function (name) {
+        source.addEventListener(name, function (event) {
+            append(event);
+            if (name === 'done') {
+                source.close();
+            }
+        });
+    });
+    source.onerror = function () {
+        source.close();
+        //事件流开始前的错误 (problem+json), EventSource 拿不到响应内容, 重新请求一次显示
+        if (lines.length === 0) {
+            $.ajax({url: url, dataType: 'text'}).always(function (data, status, xhr) {
+                if (data && data.getAllResponseHeaders) {
+                    xhr = data;
+                }
+                result.text(`HTTP ${xhr.status}\n${xhr.getResponseHeader('Content-Type') || ''}\n\n${xhr.responseText || ''}`);
+            });
+        }
+    };
+}
+
+function send(button) {
+    let id = button.data('op');
+    let method = button.data('method');
+    let url = requestUrl(id, button.data('path'));
+    let result = $(`#result_${id}`).show().text('...');
+    let op = spec.paths[button.data('path')][method];
+    if (isStream(op)) {
+        sendStream(url, result);
+        return;
+    }
+    let options = {url: url, type: method.toUpperCase(), dataType: 'text'};
+    if (op.requestBody) {
+        options.contentType = $(`#body_type_${id}`).val();
+        options.data = $(`#body_${id}`).val();
+    }
+    $.ajax(options).always(function (data, status, xhr) {
+        //失败时参数顺序为 xhr, status, error
+        if (data && data.getAllResponseHeaders) {
+            xhr = data;
+        }
+        let body = xhr.responseText || '';
+        try {
+            body = JSON.stringify(JSON.parse(body), null, 2);
+        } catch (e) {
+            //非 json 原样显示
+        }
+        result.text(`HTTP ${xhr.status}\n${xhr.getResponseHeader('Content-Type') || ''}\n\n${body}`);
+    });
+}
+
+function renderErrors() {
+    let codes = spec.components.schemas.ErrorCode.enum || [];
+    let rows = [];
+    Object.values(spec.paths).forEach(function (item) {
+        Object.values(item).forEach(function (op) {
+            Object.keys(op.responses).forEach(function (status) {
+                if (status >= 400 && rows.every(function (row) {
+                    return row.status !== status;
+                })) {
+                    rows.push({status: status, description: op.responses[status].description});
+                }
+            });
+        });
+    });
+    rows.sort(function (a, b) {
+        return a.status - b.status;
+    });
+    $('#apiErrors').html(rows.map(function (row) {
+        let list = row.description.split(', ').filter(function (code) {
+            return codes.indexOf(code) >= 0;
+        });
+        return `<tr><td>${row.status}</td><td>${list.map(escapeHtml).join('<br>')}</td></tr>`;
+    }).join(''));
+}
+
+function renderSchemas() {
+    let schemas = spec.components.schemas;
+    $('#apiSchemas').html(Object.keys(schemas).sort().map(function (name) {
+        return `<div id="schema_${name}">
+            <h4>${escapeHtml(name)}</h4>
+            <pre>${escapeHtml(JSON.stringify(schemas[name], null, 2))}</pre>
+        </div>`;
+    }).join(''));
+}
+
+function render() {
+    $('#apiTitle').text(`${spec.info.title} ${spec.info.version}`);
+    $('#apiDescription').text(spec.info.description || '');
+    let tags = (spec.tags || []).map(function (tag) {
+        return {name: tag.name, description: tag.description, html: ''};
+    });
+    let index = 0;
+    Object.keys(spec.paths).sort().forEach(function (path) {
+        Object.keys(spec.paths[path]).forEach(function (method) {
+            let op = spec.paths[path][method];
+            let name = (op.tags || [])[0];
+            let tag = tags.find(function (tag) {
+                return tag.name === name;
+            });
+            if (!tag) {
+                tag = {name: name || 'default', description: '', html: ''};
+                tags.push(tag);
+            }
+            tag.html += renderOperation(path, method, op, index++);
+        });
+    });
+    $('#apiOperations').html(tags.map(function (tag) {
+        return `<h3>${escapeHtml(tag.name)} <small>${escapeHtml(tag.description)}</small></h3>${tag.html}`;
+    }).join(''));
+    renderErrors();
+    renderSchemas();
+}
+
+$(function () {
+    $.getJSON('/openapi.json', function (data) {
+        spec = data;
+        render();
+    }).fail(function (xhr, status) {
+        $('#apiOperations').html(`<p class="text-danger">openapi.json 加载失败 ${escapeHtml(status)}</p>`);
+    });
+
+    $('#apiOperations').on('click', '.api_send', function () {
+        send($(this));
+    });
+});
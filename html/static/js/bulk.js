@@ -0,0 +1,115 @@
+$(document).ready(function () {
+
+    //选择文件后读入文本框, 提交前还可以修改
+    $('#inputBulkFile').change(function () {
+        let file = this.files[0];
+        if (!file) {
+            return;
+        }
+        let reader = new FileReader();
+        reader.onload = function () {
+            $('#inputBulkDomains').val(reader.result);
+        };
+        reader.readAsText(file);
+    });
+
+    $('#btn_bulk_start').click(function () {
+        var text = $('#inputBulkDomains').val();
+        if (isEmpty(text)) {
+            Swal.fire({
+                icon: "error",
+                text: t("BULK_EMPTY"),
+            });
+            return;
+        }
+        $(this).prop('disabled', true);
+        $.ajax({
+            url: '/v1/bulk?' + $.param({
+                recordType: $('#inputBulkTypes').val(),
+                dnsServer: $('#inputBulkResolvers').val()
+            }),
+            type: 'POST',
+            contentType: 'text/plain; charset=utf-8',
+            data: text,
+            success: function (response) {
+                if (response.code !== "1") {
+                    $('#btn_bulk_start').prop('disabled', false);
+                    Swal.fire({
+                        icon: "error",
+                        text: response.msg,
+                    });
+                    return;
+                }
+                watch(response.data.id);
+            },
+            error: function (xhr, status, error) {
+                $('#btn_bulk_start').prop('disabled', false);
+                Swal.fire({
+                    icon: "error",
+                    text: errorText(xhr, status),
+                });
+            }
+        });
+    });
+
+    //订阅进度, 完成后加载结果
+    function watch(id) {
+        $('#btn_bulk_csv').attr('href', `/v1/bulk/${id}/report?format=csv`);
+        $('#btn_bulk_json').attr('href', `/v1/bulk/${id}/report?format=json`);
+        $('#bulk_status').show();
+        $('#bulk_table tbody').html('');
+        let source = new EventSource(`/v1/bulk/${id}/events`);
+        source.addEventListener('progress', function (e) {
+            render_progress(JSON.parse(e.data));
+        });
+        source.addEventListener('done', function (e) {
+            source.close();
+            render_progress(JSON.parse(e.data));
+            load(id);
+        });
+        source.onerror = function () {
+            source.close();
+            load(id);
+        };
+    }
+
+    function render_progress(progress) {
+        let percent = progress.total === 0 ? 100 : Math.floor(progress.done * 100 / progress.total);
+        $('#bulk_progress').css('width', percent + '%').text(percent + '%');
+        $('#bulk_progress_text').text(`已完成 ${progress.done} / ${progress.total}, 失败 ${progress.failed}`);
+    }
+
+    function load(id) {
+        $.get(`/v1/bulk/${id}`, function (response) {
+            $('#btn_bulk_start').prop('disabled', false);
+            if (response.code !== "1") {
+                Swal.fire({
+                    icon: "error",
+                    text: response.msg,
+                });
+                return;
+            }
+            render_progress(response.data);
+            let rows = response.data.items.map(function (item) {
+                let answer;
+                if (item.code !== "1") {
+                    answer = `<b style='color: red'>${item.msg}</b>`;
+                } else if (item.record.length === 0) {
+                    answer = '无记录';
+                } else {
+                    answer = item.record.map(function (record) {
+                        return `${record.value} (${record.ttl})`;
+                    }).join('<br>');
+                }
+                return `<tr><td>${item.domain}</td><td>${item.recordType}</td><td>${item.dnsServer}</td><td>${answer}</td><td>${item.durationMs}</td></tr>`;
+            });
+            $('#bulk_table tbody').html(rows.join(''));
+        }).fail(function (xhr, status) {
+            $('#btn_bulk_start').prop('disabled', false);
+            Swal.fire({
+                icon: "error",
+                text: errorText(xhr, status),
+            });
+        });
+    }
+});
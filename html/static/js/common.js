@@ -1,4 +1,6 @@
-const domainRegex = /^(?:(?:[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z][a-zA-Z0-9-]{0,61}[a-zA-Z0-9])(?::\d{1,5})?$/;
+//支持国际化域名, 服务端会按 UTS-46 转换为 punycode 并校验
+const domainRegex = /^(?:(?:[\p{L}\p{N}](?:[\p{L}\p{N}\p{M}-]{0,61}[\p{L}\p{N}\p{M}])?\.)+[\p{L}\p{N}\p{M}-]{2,})(?::\d{1,5})?$/u;
+const subnetRegex = /^[0-9a-fA-F:.]+\/\d{1,3}$/;
 const ipv4Regex = /^(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.(25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$/;
 
 const DNS_MAP = new Map([
@@ -9,6 +11,33 @@ const DNS_MAP = new Map([
     ['114', '114 Dns'],
 ]);
 
+//文案, 与服务端共用 html/i18n/messages.json, 由页面模板注入 I18N
+function t(code, params) {
+    let text = (typeof I18N !== 'undefined' && I18N[code]) || code;
+    Object.keys(params || {}).forEach(function (key) {
+        text = text.split(`{${key}}`).join(params[key]);
+    });
+    return text;
+}
+
+//接口错误, 优先使用服务端返回的 msg (ApiResponse) 或 detail (problem+json)
+function errorText(xhr, status) {
+    let body = xhr.responseJSON;
+    if (body && (body.msg || body.detail)) {
+        return body.msg || body.detail;
+    }
+    return t("UI_REQUEST_FAILED") + " " + status;
+}
+
+//事件流接口的参数错误在事件流开始前以 problem+json 返回, EventSource 拿不到响应内容, 重新请求一次取错误信息
+function streamError(url, callback) {
+    $.ajax({url: url, dataType: 'json'}).always(function (data, status, xhr) {
+        //失败时参数顺序为 xhr, status, error
+        let error = data && data.getAllResponseHeaders ? data : xhr;
+        callback(errorText(error, error.status));
+    });
+}
+
 // 定义 get 方法
 function getDnsDesc(key) {
     return DNS_MAP.get(key) || null;
@@ -16,6 +45,110 @@ function getDnsDesc(key) {
 
 
 
+//毫秒时间戳 => yyyy-MM-dd HH:mm:ss
+function formatTime(ts) {
+    let d = new Date(Number(ts));
+    let pad = function (n) {
+        return n < 10 ? '0' + n : '' + n;
+    };
+    return `${d.getFullYear()}-${pad(d.getMonth() + 1)}-${pad(d.getDate())} ${pad(d.getHours())}:${pad(d.getMinutes())}:${pad(d.getSeconds())}`;
+}
+
+//分享链接
+function shareLink(shareId) {
+    return isEmpty(shareId) ? '' : ` <a href="/r/${shareId}" target="_blank">分享</a>`;
+}
+
+//按分享链接 id 下载保存的结果, format: csv / json / zone / dig
+function exportShareIds(shareIds, format) {
+    let ids = shareIds.filter(function (id) {
+        return !isEmpty(id);
+    });
+    if (ids.length === 0) {
+        return;
+    }
+    window.location.href = '/v1/export?' + $.param({shareIds: ids.join(','), format: format});
+}
+
+//国际化域名的两种形式与仿冒提示
+function idnHtml(idn) {
+    if (!idn) {
+        return '';
+    }
+    let html = `${idn.unicode} (${idn.ascii})`;
+    (idn.warnings || []).forEach(function (warning) {
+        html += `<br><b style='color: red'>${t(warning.code, warning.args)}</b>`;
+    });
+    return html;
+}
+
+//解析器组各成员的结果, 应答的成员加粗
+function groupHtml(group) {
+    if (!group) {
+        return '';
+    }
+    let strategy = group.quorum ? `${group.strategy}/${group.quorum}` : group.strategy;
+    let html = `<br><small>${strategy}`;
+    group.members.forEach(function (member) {
+        let ms = member.durationMs === undefined || member.durationMs === null ? '' : ` ${member.durationMs}ms`;
+        let line = `${member.name} ${member.status}${ms}`;
+        if (member.reason) {
+            line += ` (${member.reason})`;
+        }
+        html += member.status === 'ANSWERED'
+            ? `<br><b>${line}</b>`
+            : `<br>${line}`;
+    });
+    return html + '</small>';
+}
+
+//每次发送的结果, 只有一次且成功时不显示
+function attemptsHtml(attempts) {
+    if (!attempts || (attempts.length === 1 && attempts[0].outcome === 'ok')) {
+        return '';
+    }
+    let html = '<br><small>';
+    attempts.forEach(function (item, index) {
+        let line = `#${item.attempt} ${item.dnsServer} ${item.transport} ${item.outcome} ${item.durationMs}/${item.timeoutMs}ms`;
+        html += index > 0 ? '<br>' : '';
+        html += item.outcome === 'ok' || item.outcome === 'truncated'
+            ? line
+            : `<span style="color: red">${line}</span>`;
+    });
+    return html + '</small>';
+}
+
+//CNAME / DNAME 链, 每一跳后是 TTL, 循环、超长与违反 RFC 的 CNAME 标红
+function chainHtml(chain) {
+    if (!chain) {
+        return '';
+    }
+    let html = '<small>';
+    chain.hops.forEach(function (hop) {
+        let owner = hop.recordType === 'DNAME' ? ` (${hop.owner})` : '';
+        html += `${hop.name} ${hop.recordType}${owner} ${hop.ttl}<br>→ `;
+    });
+    html += chain.canonical;
+    if (chain.loopDetected) {
+        html += `<br><b style='color: red'>别名循环</b>`;
+    }
+    if (chain.tooLong) {
+        html += `<br><b style='color: red'>别名链过长</b>`;
+    }
+    (chain.cnameAtApex || []).forEach(function (name) {
+        html += `<br><b style='color: red'>CNAME 在 zone 顶点: ${name}</b>`;
+    });
+    (chain.coexistence || []).forEach(function (item) {
+        html += `<br><b style='color: red'>与 CNAME 共存: ${item}</b>`;
+    });
+    return html + '</small><br>';
+}
+
+//ECS scope prefix, 解析器没有返回时显示 -
+function formatScope(scopePrefix) {
+    return scopePrefix === undefined || scopePrefix === null ? '-' : '/' + scopePrefix;
+}
+
 function isEmpty(str) {
     if (str === null || str === undefined || str === '') {
         return true;
@@ -0,0 +1,155 @@
+$(document).ready(function () {
+
+    const pageSize = 20;
+    let page = 1;
+    let total = 0;
+
+    let live = null;
+
+    $('#tab_history_link').on('shown.bs.tab', function () {
+        page = 1;
+        v1_history();
+        v1_resolver();
+    });
+
+    //实时显示新保存的历史, 包括转发解析器的查询, 只在第一页追加
+    $('#inputHistoryLive').change(function () {
+        if (live) {
+            live.close();
+            live = null;
+        }
+        if (!this.checked) {
+            return;
+        }
+        live = new EventSource('/v1/history/stream');
+        live.addEventListener('history', function (event) {
+            if (page !== 1) {
+                return;
+            }
+            let tbody = $('#history_table tbody');
+            tbody.find('td[colspan]').closest('tr').remove();
+            tbody.prepend(historyRow(JSON.parse(event.data)));
+            tbody.children('tr').slice(pageSize).remove();
+            total++;
+            $('#history_page_info').text(`${page} / ${Math.max(1, Math.ceil(total / pageSize))} (共 ${total} 条)`);
+        });
+    });
+
+    $('#btn_history_query').click(function () {
+        page = 1;
+        v1_history();
+    });
+
+    $('#btn_history_prev').click(function () {
+        if (page > 1) {
+            page--;
+            v1_history();
+        }
+    });
+
+    $('#btn_history_next').click(function () {
+        if (page * pageSize < total) {
+            page++;
+            v1_history();
+        }
+    });
+
+    //datetime-local => 毫秒时间戳
+    function toMillis(value) {
+        if (isEmpty(value)) {
+            return null;
+        }
+        let ms = new Date(value).getTime();
+        return isNaN(ms) ? null : ms;
+    }
+
+    function historyRow(item) {
+        let outcome;
+        if (item.code !== "1") {
+            outcome = `<b style='color: red'>${item.code}: ${item.msg}</b>`;
+        } else if (item.result && item.result.record && item.result.record.length > 0) {
+            outcome = item.result.record.map(function (record) {
+                return `${record.value} (${record.ttl})`;
+            }).join('<br>');
+        } else {
+            outcome = '无记录';
+        }
+        outcome += shareLink(item.shareId);
+        let server = item.clientSubnet ? `${item.dnsServer}<br>ECS ${item.clientSubnet}` : item.dnsServer;
+        return `<tr>
+                    <td>${formatTime(item.createdAt)}</td>
+                    <td>${item.requester}</td>
+                    <td>${item.kind}</td>
+                    <td>${item.domain}</td>
+                    <td>${item.recordType}</td>
+                    <td>${server}</td>
+                    <td>${item.durationMs}</td>
+                    <td>${outcome}</td>
+                 </tr>`;
+    }
+
+    //转发解析器的监听地址与各上游统计
+    function v1_resolver() {
+        $.getJSON('/v1/resolver', function (response) {
+            let status = response.data;
+            if (response.code !== "1" || !status.enabled) {
+                $('#history_resolver').text('');
+                return;
+            }
+            let upstreams = status.upstreams.map(function (upstream) {
+                let error = upstream.lastError ? `, 最近错误: ${upstream.lastError}` : '';
+                return `${upstream.name} 查询 ${upstream.queries} 失败 ${upstream.failures} 平均 ${upstream.avgMs}ms${error}`;
+            }).join('; ');
+            $('#history_resolver').text(`转发解析器 ${status.listen || '未监听'}, 缓存 ${status.cacheSize} 条 (命中 ${status.cacheHits} / 未命中 ${status.cacheMisses}), SERVFAIL ${status.servFail}; ${upstreams}`);
+        });
+    }
+
+    function v1_history() {
+        let params = {
+            domain: $('#inputHistoryDomain').val(),
+            recordType: $('#inputHistoryType').val(),
+            dnsServer: $('#inputHistoryServer').val(),
+            code: $('#inputHistoryCode').val(),
+            start: toMillis($('#inputHistoryStart').val()),
+            end: toMillis($('#inputHistoryEnd').val()),
+            page: page,
+            size: pageSize
+        };
+        //去掉空条件
+        Object.keys(params).forEach(function (key) {
+            if (params[key] === null || params[key] === '') {
+                delete params[key];
+            }
+        });
+
+        $.ajax({
+            url: '/v1/history',
+            type: 'GET',
+            data: params,
+            success: function (response) {
+                let tbody = $('#history_table tbody');
+                tbody.empty();
+                if (response.code !== "1") {
+                    tbody.append(`<tr><td colspan="8"><b style='color: red'>${response.msg}</b></td></tr>`);
+                    return;
+                }
+                total = response.total || 0;
+                let pages = Math.max(1, Math.ceil(total / pageSize));
+                $('#history_page_info').text(`${page} / ${pages} (共 ${total} 条)`);
+                if (response.data.length === 0) {
+                    tbody.append(`<tr><td colspan="8">暂无记录</td></tr>`);
+                    return;
+                }
+                response.data.forEach(function (item) {
+                    tbody.append(historyRow(item));
+                });
+            },
+            error: function (xhr, status, error) {
+                Swal.fire({
+                    icon: "error",
+                    text: errorText(xhr, status),
+                });
+            }
+        });
+    }
+});
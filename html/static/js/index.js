@@ -9,6 +9,15 @@ $(document).ready(function () {
     });
 
 
+    $('#btn_query_subnets').click(function () {
+        $(this).prop('disabled', true);
+        query_subnets();
+        setTimeout(function() {
+            $('#btn_query_subnets').prop('disabled', false);
+        }, 2000);
+    });
+
+
     $('#btn_query_sec').click(function () {
         $(this).prop('disabled', true);
         query_sec();
@@ -18,6 +27,32 @@ $(document).ready(function () {
     });
 
 
+    //最近一次查询结果的分享链接 id, 按 dns 服务器顺序, 用于导出
+    let queryShareIds = [];
+    let secShareIds = [];
+
+    $('#query_export .btn-export').click(function () {
+        exportShareIds(queryShareIds, $(this).data('format'));
+    });
+
+    $('#sec_export .btn-export').click(function () {
+        exportShareIds(secShareIds, $(this).data('format'));
+    });
+
+
+    //解析器组, 组名作为 dns服务器查询
+    $.getJSON('/v1/resolver_groups', function (response) {
+        if (response.code !== "1") {
+            return;
+        }
+        response.data.groups.forEach(function (group) {
+            $('#dns_groups').append(`<label class="checkbox-inline">
+            <input type="checkbox" class="dns-checkbox" value="${group.name}">${group.name} (${group.strategy})
+        </label>`);
+        });
+    });
+
+
     $(document).ready(function () {
         $('.dns-checkbox_sec').on('change', function () {
             $('.dns-checkbox_sec').not(this).prop('checked', false);
@@ -39,7 +74,7 @@ $(document).ready(function () {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "域名不能为空!",
+                text: t("DOMAIN_EMPTY"),
             });
         }
 
@@ -48,14 +83,14 @@ $(document).ready(function () {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "记录类型不能为空!",
+                text: t("RECORD_TYPE_EMPTY"),
             });
         }
         if (dnsServerSecArr.length === 0) {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "请选择一个dns服务商!",
+                text: t("UI_SELECT_DNS_SERVER"),
             });
         }
         if (!isEmpty(domainSec)) {
@@ -63,7 +98,7 @@ $(document).ready(function () {
                 is_valid = false;
                 Swal.fire({
                     icon: "error",
-                    text: "无效的域名格式!",
+                    text: t("DOMAIN_INVALID", {domain: domainSec}),
                 });
             }
         }
@@ -79,6 +114,11 @@ $(document).ready(function () {
         var domain = $('#inputDomain').val();
         //自定义dns服务器
         var cusDns = $('#inputCusDns').val();
+        //客户端子网
+        var clientSubnet = $('#inputClientSubnet').val();
+        //每次尝试的超时与重试次数
+        var timeoutMs = $('#inputTimeoutMs').val();
+        var retries = $('#inputRetries').val();
         //记录类型
         var recordType = $('input[name="record_type"]:checked').val();
         //dns服务商列表
@@ -92,7 +132,7 @@ $(document).ready(function () {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "域名不能为空!",
+                text: t("DOMAIN_EMPTY"),
             });
         }
 
@@ -101,7 +141,7 @@ $(document).ready(function () {
                 is_valid = false;
                 Swal.fire({
                     icon: "error",
-                    text: "无效的dns服务器地址!",
+                    text: t("DNS_SERVER_INVALID", {dnsServer: cusDns}),
                 });
             }
         }
@@ -110,14 +150,14 @@ $(document).ready(function () {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "记录类型不能为空!",
+                text: t("RECORD_TYPE_EMPTY"),
             });
         }
         if (dnsServerArr.length === 0 && isEmpty(cusDns)) {
             is_valid = false;
             Swal.fire({
                 icon: "error",
-                text: "请选择一个dns服务商或者输入自定义dns服务器!",
+                text: t("UI_SELECT_OR_INPUT_DNS_SERVER"),
             });
         }
         if (!isEmpty(domain)) {
@@ -125,16 +165,136 @@ $(document).ready(function () {
                 is_valid = false;
                 Swal.fire({
                     icon: "error",
-                    text: "无效的域名格式!",
+                    text: t("DOMAIN_INVALID", {domain: domain}),
+                });
+            }
+        }
+        if (!isEmpty(clientSubnet)) {
+            if (!subnetRegex.test(clientSubnet.trim())) {
+                is_valid = false;
+                Swal.fire({
+                    icon: "error",
+                    text: t("CLIENT_SUBNET_INVALID", {clientSubnet: clientSubnet}),
                 });
             }
         }
         if (is_valid) {
-            v1_query(domain, cusDns, recordType, dnsServerArr);
+            v1_query(domain, cusDns, recordType, dnsServerArr, clientSubnet, timeoutMs, retries);
         }
     }
 
 
+    function query_subnets() {
+        var domain = $('#inputDomain').val();
+        var cusDns = $('#inputCusDns').val();
+        var recordType = $('input[name="record_type"]:checked').val();
+        //对比只用一个dns服务器, 自定义优先
+        var dnsServer = !isEmpty(cusDns) ? cusDns : $('.dns-checkbox:checked').first().val();
+        var subnets = $('#inputCompareSubnets').val().split('\n').filter(function (item) {
+            return !isEmpty(item);
+        });
+
+        if (isEmpty(domain) || !domainRegex.test(domain)) {
+            Swal.fire({
+                icon: "error",
+                text: t("DOMAIN_INVALID", {domain: domain}),
+            });
+            return;
+        }
+        if (isEmpty(dnsServer)) {
+            Swal.fire({
+                icon: "error",
+                text: t("UI_SELECT_OR_INPUT_DNS_SERVER"),
+            });
+            return;
+        }
+        if (subnets.length === 0) {
+            Swal.fire({
+                icon: "error",
+                text: t("CLIENT_SUBNET_EMPTY"),
+            });
+            return;
+        }
+        v1_query_subnets(domain, recordType, dnsServer, subnets);
+    }
+
+    function v1_query_subnets(domain, recordType, dnsServer, subnets) {
+        let table = $('#subnet_compare_table');
+        //每个子网一列, 结果返回后填入对应的列
+        let head = '<td>客户端子网</td>';
+        let scopeRow = '<td>ECS作用域</td>';
+        let recordRow = '<td>响应IP / TTL</td>';
+        subnets.forEach(function (subnet, i) {
+            head += `<td>${subnet}</td>`;
+            scopeRow += `<td id="subnet_scope_${i}">...</td>`;
+            recordRow += `<td id="subnet_record_${i}">...</td>`;
+        });
+        table.find('thead tr').html(head);
+        table.find('tbody').html(`<tr>${scopeRow}</tr><tr>${recordRow}</tr>`);
+
+        let url = '/v1/query_subnets/stream?' + $.param({
+            domain: domain,
+            recordType: recordType,
+            dnsServer: dnsServer,
+            clientSubnets: subnets.join(',')
+        });
+        let source = new EventSource(url);
+        let received = false;
+        source.addEventListener('result', function (e) {
+            received = true;
+            let item = JSON.parse(e.data);
+            if (item.code !== "1") {
+                $(`#subnet_scope_${item.index}`).html('-');
+                $(`#subnet_record_${item.index}`).html(`<b style='color: red'>${item.msg}</b>`);
+                return;
+            }
+            let result = item.result;
+            $(`#subnet_scope_${item.index}`).html(`${formatScope(result.scopePrefix)}${shareLink(result.shareId)}`);
+            if (result.record.length > 0) {
+                $(`#subnet_record_${item.index}`).html(result.record.map(function (record) {
+                    return `${record.value} (${record.ttl})`;
+                }).join('<br>'));
+            } else {
+                $(`#subnet_record_${item.index}`).html('无记录');
+            }
+        });
+        source.addEventListener('done', function () {
+            source.close();
+        });
+        source.onerror = function () {
+            source.close();
+            if (!received) {
+                streamError(url, function (msg) {
+                    table.find('tbody').html(`<tr><td><b style='color: red'>${msg}</b></td></tr>`);
+                });
+                return;
+            }
+            Swal.fire({
+                icon: "error",
+                text: t("UI_REQUEST_FAILED"),
+            });
+        };
+    }
+
+
+    //NXDOMAIN / NODATA 的 NSEC / NSEC3 否定证明
+    function denialText(denial) {
+        let lines = [`;; ${denial.kind} ${denial.denialType || ''} ${denial.proven ? '已证明' : '未证明'}`];
+        denial.records.forEach(function (item) {
+            let params = item.recordType === 'NSEC3'
+                ? ` (${item.hashAlgorithm} iterations=${item.iterations} salt=${item.salt} opt-out=${item.optOut})`
+                : '';
+            lines.push(`${item.owner} ${item.ttl} ${item.recordType} -> ${item.next} [${item.types.join(' ')}] ${item.proof}${params}`);
+        });
+        denial.explanation.forEach(function (line) {
+            lines.push(`;; ${line}`);
+        });
+        (denial.warnings || []).forEach(function (warning) {
+            lines.push(`;; ! ${warning}`);
+        });
+        return lines.join('\n') + '\n';
+    }
+
     function v1_query_sec(domainSec, recordTypeSec, dnsServerSecArr) {
         let fullDnsServerSecArr = [];
         dnsServerSecArr.forEach(function (itemSec) {
@@ -143,9 +303,10 @@ $(document).ready(function () {
 
         if (fullDnsServerSecArr.length > 0) {
             $('#resp_data_text_sec').val('');
+            secShareIds = [];
+            $('#sec_export').hide();
 
             for (let i = 0; i < fullDnsServerSecArr.length; i++) {
-                let itemSec = fullDnsServerSecArr[i];
                 let circleBarSecId = `circleBar_sec_${i}`;
                 let bar = `
                 <svg id="${circleBarSecId}" width="30" height="30" viewBox="0 0 120 120">
@@ -164,65 +325,95 @@ $(document).ready(function () {
 
                 $("#resp_data_text_sec").hide();
                 $("#sec_box").append(bar);
+            }
 
-
-                if (!isEmpty(itemSec)) {
-                    $.ajax({
-                        url: '/v1/query_dnssec',
-                        type: 'POST',
-                        async: true,
-                        contentType: 'application/json', // 请求体格式为 JSON
-                        data: JSON.stringify({
-                            domain: domainSec,
-                            recordType: recordTypeSec,
-                            dnsServer: itemSec
-                        }),
-                        success: function (responseSec) {
-                            $(`#${circleBarSecId}`).remove();
-                            $("#resp_data_text_sec").show();
-                            if (responseSec.code !== "1") {
-                                $('#resp_data_text_sec').val(responseSec.msg);
-                            } else {
-                                //成功
-                                //-----
-                                let dataSec = responseSec.data;
-                                let dItemSec = getDnsDesc(dataSec.dnsServer);
-                                let temp_html = isEmpty(dItemSec)
-                                    ? `<b style="color: #337ab7">${dataSec.dnsServer}</b>`
-                                    : dItemSec;
-                                if (dataSec && dataSec.record && dataSec.record.length > 0) {
-                                    dataSec.record.forEach(function (item) {
-                                        var newContent = `${domainSec} ${item.ttl}  ${item.value}`;
-                                        console.log(newContent);
-                                        $('#resp_data_text_sec').val(function (i, val) {
-                                            return val + newContent + "\n"; // 在现有内容后拼接
-                                        });
-                                    });
-                                } else {
-                                    $('#resp_data_text_sec').val('无记录');
-                                }
-                            }
-
-                        },
-                        error: function (xhr, status, error) {
-                            $(`#${circleBarSecId}`).remove();
-                            $("#resp_data_text_sec").show();
-                            $('#resp_data_text_sec').val('请求失败: ' + status);
-                            Swal.fire({
-                                icon: "error",
-                                text: "请求失败!: " + status,
+            //每个dns服务器返回后立即显示
+            let url = '/v1/query_dnssec/stream?' + $.param({
+                domain: domainSec,
+                recordType: recordTypeSec,
+                dnsServers: fullDnsServerSecArr.join(',')
+            });
+            let source = new EventSource(url);
+            let received = false;
+            source.addEventListener('result', function (e) {
+                received = true;
+                let responseSec = JSON.parse(e.data);
+                $(`#circleBar_sec_${responseSec.index}`).remove();
+                $("#resp_data_text_sec").show();
+                if (responseSec.code !== "1") {
+                    $('#resp_data_text_sec').val(function (i, val) {
+                        return val + responseSec.msg + "\n";
+                    });
+                } else {
+                    //成功
+                    //-----
+                    let dataSec = responseSec.result;
+                    if (dataSec && dataSec.idn) {
+                        $('#resp_data_text_sec').val(function (i, val) {
+                            let lines = [`# ${dataSec.idn.unicode} => ${dataSec.idn.ascii}`];
+                            (dataSec.idn.warnings || []).forEach(function (warning) {
+                                lines.push(`# ! ${t(warning.code, warning.args)}`);
+                            });
+                            return val + lines.join('\n') + '\n';
+                        });
+                    }
+                    if (dataSec && dataSec.trustAnchor) {
+                        let anchor = dataSec.trustAnchor;
+                        $('#resp_data_text_sec').val(function (i, val) {
+                            let keyTag = anchor.keyTag ? ` ${anchor.keyTag}` : '';
+                            let expires = anchor.expires ? ` (${anchor.expires})` : '';
+                            return val + `# trust anchor: ${anchor.zone} ${anchor.anchorType}${keyTag}${expires}\n`;
+                        });
+                    }
+                    if (dataSec && dataSec.shareId) {
+                        secShareIds[responseSec.index] = dataSec.shareId;
+                        $('#sec_export').show();
+                        $('#resp_data_text_sec').val(function (i, val) {
+                            return val + `# ${location.origin}/r/${dataSec.shareId}\n`;
+                        });
+                    }
+                    if (dataSec && dataSec.record && dataSec.record.length > 0) {
+                        dataSec.record.forEach(function (item) {
+                            var newContent = `${domainSec} ${item.ttl}  ${item.value}`;
+                            $('#resp_data_text_sec').val(function (i, val) {
+                                return val + newContent + "\n"; // 在现有内容后拼接
                             });
-                        }
+                        });
+                    } else {
+                        $('#resp_data_text_sec').val(function (i, val) {
+                            return val + '无记录\n';
+                        });
+                    }
+                    if (dataSec && dataSec.denial) {
+                        $('#resp_data_text_sec').val(function (i, val) {
+                            return val + denialText(dataSec.denial);
+                        });
+                    }
+                }
+            });
+            source.addEventListener('done', function () {
+                source.close();
+            });
+            source.onerror = function () {
+                source.close();
+                $('#sec_box svg').remove();
+                $("#resp_data_text_sec").show();
+                if (!received) {
+                    streamError(url, function (msg) {
+                        $('#resp_data_text_sec').val(msg);
                     });
-
+                    return;
                 }
-
-            }
+                Swal.fire({
+                    icon: "error",
+                    text: t("UI_REQUEST_FAILED"),
+                });
+            };
         }
 
     }
 
-    function v1_query(domain, cusDns, recordType, dnsServerArr) {
+    function v1_query(domain, cusDns, recordType, dnsServerArr, clientSubnet, timeoutMs, retries) {
         let fullDnsServerArr = [];
         if (!isEmpty(cusDns)) {
             fullDnsServerArr.push(cusDns);
@@ -234,10 +425,12 @@ $(document).ready(function () {
 
         if (fullDnsServerArr.length > 0) {
             $('#resp_data_table tbody').empty();
+            queryShareIds = [];
+            $('#query_export').hide();
+            $('#query_idn').hide().html('');
             for (let i = 0; i < fullDnsServerArr.length; i++) {
-                let item = fullDnsServerArr[i];
                 let circleBarId = `circleBar_${i}`;
-                let bar = `<tr id="${circleBarId}"> <td colspan="4"><svg width="30" height="30" viewBox="0 0 120 120">
+                let bar = `<tr id="${circleBarId}"> <td colspan="5"><svg width="30" height="30" viewBox="0 0 120 120">
   <circle cx="60" cy="60" r="50" fill="none" stroke="#e6e6e6" stroke-width="10"/>
   <circle cx="60" cy="60" r="50" fill="none" stroke="#3498db" stroke-width="10"
           stroke-dasharray="78.5 235.6" stroke-linecap="round">
@@ -250,76 +443,97 @@ $(document).ready(function () {
   </circle>
 </svg> </td>  </tr>`;
                 $('#resp_data_table tbody').append(bar);
-                if (!isEmpty(item)) {
-                    $.ajax({
-                        url: '/v1/query',
-                        type: 'POST',
-                        async: true,
-                        contentType: 'application/json', // 请求体格式为 JSON
-                        data: JSON.stringify({
-                            domain: domain,
-                            recordType: recordType,
-                            dnsServer: item
-                        }),
-                        success: function (response) {
-                            if (response.code !== "1") {
-                                $(`#${circleBarId}`).html(`   
-                                            <td><b style='color: red'>${item}</b></td>
-                                            <td><b style='color: red'>${recordType.toUpperCase()}</b></td>
-                                            <td><b style='color: red'>${response.msg}</b></td>
-                                            <td><b style='color: red'>${response.msg}</b></td>
-                                `);
-                            } else {
-                                $(`#${circleBarId}`).remove();
-                                //成功
-                                //-----
-                                let data = response.data;
-                                let dItem = getDnsDesc(data.dnsServer);
-                                let temp_html = isEmpty(dItem)
-                                    ? `<b style="color: #337ab7">${data.dnsServer}</b>`
-                                    : dItem;
-                                if (data && data.record && data.record.length > 0) {
-                                    let ips = [];
-                                    let ttls = [];
-                                    data.record.forEach(function (record) {
-                                        ips.push(record.value);
-                                        ttls.push(record.ttl);
-                                    });
-
-                                    // 动态生成新的行
-                                    let newRow = `<tr>
-                                                            <td>${temp_html}</td>
-                                                            <td>${data.recordType}</td>
-                                                            <td>${ips.join('<br>')}</td>
-                                                            <td>${ttls.join('<br>')}</td>
-                                                         </tr>`;
-                                    $('#resp_data_table tbody').append(newRow);
-                                } else {
-
-                                    let newRow = `<tr>
-                                                            <td>${temp_html}</td>
-                                                            <td>${data.recordType}</td>
-                                                            <td colspan="2">无记录</td>
-
-                                                         </tr>`;
-                                    $('#resp_data_table tbody').append(newRow);
-                                }
-                                //----
-                            }
-
-                        },
-                        error: function (xhr, status, error) {
-                            $(`#${circleBarId}`).remove();
-                            Swal.fire({
-                                icon: "error",
-                                text: "请求失败!: " + status,
-                            });
-                        }
-                    });
+            }
 
+            //每个dns服务器返回后立即替换对应的行, 不用等最慢的服务器
+            let params = {
+                domain: domain,
+                recordType: recordType,
+                dnsServers: fullDnsServerArr.join(','),
+                clientSubnet: isEmpty(clientSubnet) ? '' : clientSubnet.trim()
+            };
+            if (!isEmpty(timeoutMs)) {
+                params.timeoutMs = timeoutMs;
+            }
+            if (!isEmpty(retries)) {
+                params.retries = retries;
+            }
+            let url = '/v1/query/stream?' + $.param(params);
+            let source = new EventSource(url);
+            let received = false;
+            source.addEventListener('result', function (e) {
+                received = true;
+                let response = JSON.parse(e.data);
+                let circleBarId = `circleBar_${response.index}`;
+                if (response.code !== "1") {
+                    $(`#${circleBarId}`).html(`
+                                <td><b style='color: red'>${response.dnsServer}</b></td>
+                                <td><b style='color: red'>${recordType.toUpperCase()}</b></td>
+                                <td><b style='color: red'>${response.msg}</b></td>
+                                <td><b style='color: red'>${response.msg}</b></td>
+                                <td>-</td>
+                    `);
+                    return;
+                }
+                //成功
+                //-----
+                let data = response.result;
+                if (data.idn) {
+                    $('#query_idn').html(idnHtml(data.idn)).show();
                 }
+                let dItem = getDnsDesc(data.dnsServer);
+                let temp_html = isEmpty(dItem)
+                    ? `<b style="color: #337ab7">${data.dnsServer}</b>`
+                    : dItem;
+                temp_html += shareLink(data.shareId);
+                temp_html += groupHtml(data.group);
+                temp_html += attemptsHtml(data.attempts);
+                if (!isEmpty(data.shareId)) {
+                    queryShareIds[response.index] = data.shareId;
+                    $('#query_export').show();
+                }
+                if (data && data.record && data.record.length > 0) {
+                    let ips = [];
+                    let ttls = [];
+                    data.record.forEach(function (record) {
+                        ips.push(record.value);
+                        ttls.push(record.ttl);
+                    });
 
-            }
+                    $(`#${circleBarId}`).replaceWith(`<tr>
+                                <td>${temp_html}</td>
+                                <td>${data.recordType}</td>
+                                <td>${chainHtml(data.chain)}${ips.join('<br>')}</td>
+                                <td>${ttls.join('<br>')}</td>
+                                <td>${formatScope(data.scopePrefix)}</td>
+                             </tr>`);
+                } else {
+                    $(`#${circleBarId}`).replaceWith(`<tr>
+                                <td>${temp_html}</td>
+                                <td>${data.recordType}</td>
+                                <td colspan="2">${chainHtml(data.chain)}无记录</td>
+                                <td>${formatScope(data.scopePrefix)}</td>
+                             </tr>`);
+                }
+                //----
+            });
+            source.addEventListener('done', function () {
+                source.close();
+            });
+            source.onerror = function () {
+                source.close();
+                if (!received) {
+                    streamError(url, function (msg) {
+                        $('#resp_data_table tbody').html(`<tr><td colspan="5"><b style='color: red'>${msg}</b></td></tr>`);
+                    });
+                    return;
+                }
+                $('#resp_data_table tbody tr[id^="circleBar_"]').remove();
+                Swal.fire({
+                    icon: "error",
+                    text: t("UI_REQUEST_FAILED"),
+                });
+            };
         }
     }
 })
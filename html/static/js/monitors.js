@@ -0,0 +1,113 @@
+$(document).ready(function () {
+
+    //毫秒时间戳 => 本地时间
+    $('.ts').each(function () {
+        let ts = $(this).data('ts');
+        if (!isEmpty(String(ts))) {
+            $(this).text(formatTime(ts));
+        }
+    });
+
+    $('#btn_monitor_create').click(function () {
+        var domain = $('#inputMonitorDomain').val();
+        var recordType = $('#inputMonitorType').val();
+        var resolvers = $('#inputMonitorResolvers').val().split(',').map(function (item) {
+            return item.trim();
+        }).filter(function (item) {
+            return !isEmpty(item);
+        });
+        var interval = parseInt($('#inputMonitorInterval').val(), 10);
+        var alertType = $('#inputAlertType').val();
+        var alertTarget = $('#inputAlertTarget').val();
+        var alerts = [];
+        if (!isEmpty(alertType)) {
+            if (isEmpty(alertTarget)) {
+                Swal.fire({
+                    icon: "error",
+                    text: t("UI_INPUT_ALERT_TARGET"),
+                });
+                return;
+            }
+            if (alertType === 'email') {
+                alerts.push({
+                    type: alertType,
+                    to: alertTarget.split(',').map(function (item) {
+                        return item.trim();
+                    }).filter(function (item) {
+                        return !isEmpty(item);
+                    })
+                });
+            } else {
+                alerts.push({type: alertType, url: alertTarget.trim()});
+            }
+        }
+
+        if (isEmpty(domain) || !domainRegex.test(domain)) {
+            Swal.fire({
+                icon: "error",
+                text: t("DOMAIN_INVALID", {domain: domain}),
+            });
+            return;
+        }
+        if (resolvers.length === 0) {
+            Swal.fire({
+                icon: "error",
+                text: t("UI_INPUT_DNS_SERVER"),
+            });
+            return;
+        }
+
+        $.ajax({
+            url: '/v1/monitors',
+            type: 'POST',
+            contentType: 'application/json',
+            data: JSON.stringify({
+                domain: domain,
+                recordType: recordType,
+                resolvers: resolvers,
+                interval: isNaN(interval) ? 0 : interval,
+                alerts: alerts
+            }),
+            success: function (response) {
+                if (response.code !== "1") {
+                    Swal.fire({
+                        icon: "error",
+                        text: response.msg,
+                    });
+                    return;
+                }
+                location.reload();
+            },
+            error: function (xhr, status, error) {
+                Swal.fire({
+                    icon: "error",
+                    text: errorText(xhr, status),
+                });
+            }
+        });
+    });
+
+    $('.btn-monitor-delete').click(function () {
+        let id = $(this).data('id');
+        $.ajax({
+            url: '/v1/monitors/' + id,
+            type: 'DELETE',
+            success: function (response) {
+                if (response.code !== "1") {
+                    Swal.fire({
+                        icon: "error",
+                        text: response.msg,
+                    });
+                    return;
+                }
+                location.reload();
+            },
+            error: function (xhr, status, error) {
+                Swal.fire({
+                    icon: "error",
+                    text: errorText(xhr, status),
+                });
+            }
+        });
+    });
+});
@@ -0,0 +1,153 @@
+$(document).ready(function () {
+
+    //令牌只保存在当前标签页
+    const TOKEN_KEY = 'updateToken';
+    $('#inputUpdateToken').val(sessionStorage.getItem(TOKEN_KEY) || '');
+
+    function authorized(options) {
+        let token = $('#inputUpdateToken').val().trim();
+        sessionStorage.setItem(TOKEN_KEY, token);
+        options.headers = {Authorization: 'Bearer ' + token};
+        return $.ajax(options);
+    }
+
+    function fail(xhr, status) {
+        Swal.fire({
+            icon: "error",
+            text: errorText(xhr, status),
+        });
+    }
+
+    function lines(text, separator) {
+        return text.split(separator).map(function (value) {
+            return value.trim();
+        }).filter(function (value) {
+            return value !== '';
+        });
+    }
+
+    function loadKeys() {
+        authorized({url: '/v1/update/keys', type: 'GET'}).done(function (response) {
+            if (response.code !== "1") {
+                return;
+            }
+            $('#inputUpdateKey').html(response.data.map(function (key) {
+                let name = key.split(' ')[0];
+                return `<option value="${name}">${key}</option>`;
+            }).join(''));
+        });
+    }
+
+    function loadAudit() {
+        authorized({
+            url: '/v1/update/audit?' + $.param({zone: $('#inputUpdateZone').val().trim(), size: 50}),
+            type: 'GET'
+        }).done(function (response) {
+            if (response.code !== "1") {
+                return;
+            }
+            let rows = response.data.map(function (item) {
+                let result = item.code === "1"
+                    ? (item.responseCode || 'NOERROR')
+                    : `<b style='color: red'>${$('<div>').text(item.msg).html()}</b>`;
+                return `<tr><td>${item.id}</td><td>${formatTime(item.createdAt)}</td><td>${item.requester}</td>
+                    <td>${item.keyName}</td><td>${item.zone}</td><td>${item.action}</td>
+                    <td>${item.name} ${item.recordType}</td><td>${result}</td><td>${item.durationMs}</td></tr>`;
+            });
+            $('#audit_table tbody').html(rows.join(''));
+        }).fail(fail);
+    }
+
+    $('#btn_update_send').click(function () {
+        let kind = $('#inputPrereqKind').val();
+        let ttl = $('#inputUpdateTtl').val();
+        let request = {
+            zone: $('#inputUpdateZone').val().trim(),
+            dnsServer: $('#inputUpdateServer').val().trim(),
+            keyName: $('#inputUpdateKey').val() || '',
+            action: $('#inputUpdateAction').val(),
+            name: $('#inputUpdateName').val().trim(),
+            recordType: $('#inputUpdateType').val().trim(),
+            ttl: isEmpty(ttl) ? null : parseInt(ttl),
+            values: lines($('#inputUpdateValues').val(), '\n'),
+            prerequisites: isEmpty(kind) ? [] : [{
+                kind: kind,
+                values: lines($('#inputPrereqValues').val(), ',')
+            }]
+        };
+        $(this).prop('disabled', true);
+        authorized({
+            url: '/v1/update',
+            type: 'POST',
+            contentType: 'application/json',
+            data: JSON.stringify(request)
+        }).done(function (response) {
+            Swal.fire({
+                icon: response.code === "1" ? "success" : "error",
+                text: response.code === "1" ? `#${response.data.auditId} ${response.data.responseCode}` : response.msg,
+            });
+        }).fail(fail).always(function () {
+            $('#btn_update_send').prop('disabled', false);
+            loadAudit();
+        });
+    });
+
+    function notifyRow(item) {
+        let escape = function (text) {
+            return $('<div>').text(text).html();
+        };
+        let answer = item.error
+            ? `<b style='color: red'>${escape(item.error)}</b>`
+            : `${item.responseCode} ${item.authoritative ? 'AA' : ''} (${item.notifyMs}ms)`;
+        let serial = item.serial === undefined ? '-' : item.serial;
+        if (item.pollError) {
+            serial += `<br><b style='color: red'>${escape(item.pollError)}</b>`;
+        }
+        let synced = item.synced ? `是 (${item.syncedMs}ms)` : `<b style='color: red'>否</b>`;
+        return `<tr><td>${item.dnsServer}</td><td>${answer}</td><td>${serial}</td><td>${synced}</td><td>${item.polls}</td></tr>`;
+    }
+
+    $('#btn_notify_send').click(function () {
+        let deadline = $('#inputNotifyDeadline').val();
+        let request = {
+            zone: $('#inputUpdateZone').val().trim(),
+            primary: $('#inputNotifyPrimary').val().trim(),
+            secondaries: lines($('#inputNotifySecondaries').val(), ','),
+            keyName: $('#inputUpdateKey').val() || null,
+            deadlineMs: isEmpty(deadline) ? null : parseInt(deadline)
+        };
+        $(this).prop('disabled', true).text('等待同步...');
+        authorized({
+            url: '/v1/notify',
+            type: 'POST',
+            contentType: 'application/json',
+            data: JSON.stringify(request)
+        }).done(function (response) {
+            if (response.code !== "1") {
+                Swal.fire({
+                    icon: "error",
+                    text: response.msg,
+                });
+                return;
+            }
+            $('#notify_table').show().find('tbody').html(response.data.secondaries.map(notifyRow).join(''));
+        }).fail(fail).always(function () {
+            $('#btn_notify_send').prop('disabled', false).text('发送 NOTIFY');
+        });
+    });
+
+    $('#btn_update_audit').click(function () {
+        loadKeys();
+        loadAudit();
+    });
+
+    $('#inputUpdateToken').change(function () {
+        loadKeys();
+        loadAudit();
+    });
+
+    if ($('#inputUpdateToken').val() !== '') {
+        loadKeys();
+        loadAudit();
+    }
+});
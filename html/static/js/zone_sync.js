@@ -0,0 +1,66 @@
+$(document).ready(function () {
+
+    //serial 落后的行标红, 没有应答的标黄
+    function row(server) {
+        let escape = function (text) {
+            return $('<div>').text(text).html();
+        };
+        let rowClass = server.behind ? 'danger' : (server.error ? 'warning' : '');
+        let serial = server.serial === undefined
+            ? `<b style='color: red'>${escape(server.error || '')}</b>`
+            : server.serial;
+        let lag = server.behind ? `<b style='color: red'>${server.lag}</b>` : (server.lag === undefined ? '-' : server.lag);
+        let timers = server.serial === undefined
+            ? '-'
+            : `${server.refresh} / ${server.retry} / ${server.expire} / ${server.minimum}`;
+        let address = server.address ? `${server.address} (${server.family})` : '-';
+        let duration = server.durationMs === undefined ? '-' : server.durationMs;
+        return `<tr class="${rowClass}"><td>${server.ns}</td><td>${address}</td><td>${serial}</td><td>${lag}</td>
+            <td>${timers}</td><td>${server.authoritative ? '是' : '否'}</td><td>${duration}</td></tr>`;
+    }
+
+    $('#btn_sync_check').click(function () {
+        let zone = $('#inputSyncZone').val().trim();
+        if (isEmpty(zone)) {
+            Swal.fire({
+                icon: "error",
+                text: t("DOMAIN_EMPTY"),
+            });
+            return;
+        }
+        $(this).prop('disabled', true);
+        $('#sync_summary').text('...');
+        $.ajax({
+            url: '/v1/zone_sync',
+            type: 'POST',
+            contentType: 'application/json',
+            data: JSON.stringify({zone: zone, dnsServer: $('#inputSyncServer').val().trim()}),
+            success: function (response) {
+                if (response.code !== "1") {
+                    $('#sync_summary').text('');
+                    Swal.fire({
+                        icon: "error",
+                        text: response.msg,
+                    });
+                    return;
+                }
+                let report = response.data;
+                let status = report.consistent
+                    ? '<span class="label label-success">一致</span>'
+                    : '<span class="label label-danger">不一致</span>';
+                $('#sync_summary').html(`${report.zone} 最大 serial ${report.maxSerial === undefined ? '-' : report.maxSerial} ${status} (${report.durationMs}ms)`);
+                $('#sync_table tbody').html(report.servers.map(row).join(''));
+            },
+            error: function (xhr, status) {
+                $('#sync_summary').text('');
+                Swal.fire({
+                    icon: "error",
+                    text: errorText(xhr, status),
+                });
+            },
+            complete: function () {
+                $('#btn_sync_check').prop('disabled', false);
+            }
+        });
+    });
+});
@@ -0,0 +1,269 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AlertChannel, AlertPayload, AppError, DnsResult, ErrorCode, MonitorAnswer, MonitorChange,
+    MonitorJob,
+};
+use crate::i18n::{self, Lang};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+//告警发送, 负责去重与失败重试
+pub struct AlertDispatcher {
+    http: reqwest::Client,
+    //去重 key => 上次发送时间
+    sent: Mutex<HashMap<String, Instant>>,
+}
+
+//校验告警通道配置
+pub fn check_channels(channels: &[AlertChannel]) -> Result<(), AppError> {
+    for channel in channels {
+        match channel {
+            AlertChannel::Webhook { url } | AlertChannel::Slack { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(AppError::new(ErrorCode::AlertWebhookInvalid).arg("url", url));
+                }
+            }
+            AlertChannel::Email { to } => {
+                if constants::SMTP_HOST.is_empty() {
+                    return Err(AppError::new(ErrorCode::AlertSmtpNotConfigured));
+                }
+                if to.is_empty() {
+                    return Err(AppError::new(ErrorCode::AlertEmailEmpty));
+                }
+                for address in to {
+                    address.parse::<Mailbox>().map_err(|_| {
+                        AppError::new(ErrorCode::AlertEmailInvalid).arg("address", address)
+                    })?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl AlertDispatcher {
+    pub fn new() -> Self {
+        AlertDispatcher {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(constants::ALERT_HTTP_TIMEOUT))
+                .build()
+                .unwrap_or_default(),
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    //同一监控, 同一 dns 服务器, 同一变更结果在去重时间内只告警一次, 避免解析器抖动时反复告警
+    fn should_send(&self, change: &MonitorChange) -> bool {
+        let key = format!(
+            "{}|{}|{}|{}",
+            change.monitor_id, change.dns_server, change.kind, change.after
+        );
+        let window = Duration::from_secs(*constants::ALERT_DEDUPE_WINDOW);
+        let now = Instant::now();
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        sent.retain(|_, at| now.duration_since(*at) < window);
+        if sent.contains_key(&key) {
+            return false;
+        }
+        sent.insert(key, now);
+        true
+    }
+
+    //把变更事件发送到监控配置的所有告警通道
+    pub fn dispatch(&self, job: &MonitorJob, changes: Vec<MonitorChange>, answer: &MonitorAnswer) {
+        if job.alerts.is_empty() {
+            return;
+        }
+        for change in changes {
+            if !self.should_send(&change) {
+                info!(
+                    "alert::dispatch => suppressed {} {} {}",
+                    change.monitor_id, change.dns_server, change.kind
+                );
+                continue;
+            }
+            let payload = Arc::new(AlertPayload {
+                change,
+                result: DnsResult {
+                    dns_server: answer.dns_server.clone(),
+                    record_type: job.record_type.clone(),
+                    dns_record: answer.dns_record.clone(),
+                    client_subnet: None,
+                    scope_prefix: None,
+                    authoritative: answer.authoritative,
+                    share_id: None,
+                    idn: None,
+                    group: None,
+                    attempts: vec![],
+                    chain: None,
+                },
+            });
+            for channel in &job.alerts {
+                tokio::spawn(deliver(self.http.clone(), channel.clone(), payload.clone()));
+            }
+        }
+    }
+}
+
+impl Default for AlertDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//失败后按指数退避重试
+async fn deliver(http: reqwest::Client, channel: AlertChannel, payload: Arc<AlertPayload>) {
+    let retry_count = *constants::ALERT_RETRY_COUNT;
+    let mut backoff = Duration::from_millis(*constants::ALERT_RETRY_BACKOFF_MS);
+    for attempt in 0..=retry_count {
+        match send(&http, &channel, &payload).await {
+            Ok(()) => {
+                info!(
+                    "alert::deliver => sent {} {:?}",
+                    payload.change.monitor_id, channel
+                );
+                return;
+            }
+            Err(e) => {
+                error!(
+                    "alert::deliver => attempt {}/{} failed {:?}: {}",
+                    attempt + 1,
+                    retry_count + 1,
+                    channel,
+                    e
+                );
+                if attempt < retry_count {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+}
+
+async fn send(
+    http: &reqwest::Client,
+    channel: &AlertChannel,
+    payload: &AlertPayload,
+) -> Result<(), String> {
+    match channel {
+        AlertChannel::Webhook { url } => {
+            http.post(url)
+                .json(payload)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| e.to_string())?;
+        }
+        AlertChannel::Slack { url } => {
+            let text = format!("{}\n{}", alert_subject(payload), alert_text(payload));
+            http.post(url)
+                .json(&serde_json::json!({ "text": text }))
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| e.to_string())?;
+        }
+        AlertChannel::Email { to } => send_email(to, payload).await?,
+    }
+    Ok(())
+}
+
+async fn send_email(to: &[String], payload: &AlertPayload) -> Result<(), String> {
+    let mut builder = Message::builder()
+        .from(
+            constants::SMTP_FROM
+                .parse::<Mailbox>()
+                .map_err(|e| e.to_string())?,
+        )
+        .subject(alert_subject(payload));
+    for address in to {
+        builder = builder.to(address.parse::<Mailbox>().map_err(|e| e.to_string())?);
+    }
+    let json = serde_json::to_string_pretty(payload).map_err(|e| e.to_string())?;
+    let message = builder
+        .body(format!("{}\n\n{}", alert_text(payload), json))
+        .map_err(|e| e.to_string())?;
+
+    let mut transport = if *constants::SMTP_STARTTLS {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&constants::SMTP_HOST)
+            .map_err(|e| e.to_string())?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(constants::SMTP_HOST.as_str())
+    }
+    .port(*constants::SMTP_PORT)
+    .timeout(Some(Duration::from_secs(constants::ALERT_HTTP_TIMEOUT)));
+    if !constants::SMTP_USERNAME.is_empty() {
+        transport = transport.credentials(Credentials::new(
+            constants::SMTP_USERNAME.clone(),
+            constants::SMTP_PASSWORD.clone(),
+        ));
+    }
+    transport
+        .build()
+        .send(message)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+//告警没有请求上下文, 语言由 ALERT_LANG 配置
+fn alert_lang() -> Lang {
+    Lang::from_accept_language(&constants::ALERT_LANG)
+}
+
+fn alert_subject(payload: &AlertPayload) -> String {
+    let change = &payload.change;
+    i18n::format(
+        alert_lang(),
+        "ALERT_SUBJECT",
+        &[
+            ("id", change.monitor_id.to_string()),
+            ("domain", change.domain.clone()),
+            ("recordType", change.record_type.clone()),
+            ("dnsServer", change.dns_server.clone()),
+            ("kind", change.kind.clone()),
+        ],
+    )
+}
+
+fn alert_text(payload: &AlertPayload) -> String {
+    let change = &payload.change;
+    let empty = |s: &str| {
+        if s.is_empty() {
+            "-".to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    i18n::format(
+        alert_lang(),
+        "ALERT_TEXT",
+        &[
+            ("before", empty(&change.before)),
+            ("after", empty(&change.after)),
+        ],
+    )
+}
@@ -0,0 +1,389 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{AppError, ErrorCode, TrustAnchorInfo, TrustAnchorList};
+use crate::dns;
+use hickory_client::proto::dnssec::rdata::{DNSKEY, DNSSECRData, DS};
+use hickory_client::proto::dnssec::{PublicKey, TrustAnchors, Verifier};
+use hickory_client::proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_client::proto::serialize::txt::RDataParser;
+use hickory_client::proto::serialize::txt::trust_anchor::{Entry, Parser};
+use lazy_static::lazy_static;
+use log::error;
+use std::fs;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+lazy_static! {
+    //启动后第一次使用时读取, 修改配置需要重启
+    pub static ref ANCHORS: Anchors = Anchors::load();
+}
+
+enum AnchorData {
+    Ds(DS),
+    Dnskey(DNSKEY),
+}
+
+//配置的信任锚, 一个区域可以有多条 (例如 KSK 轮换期间)
+struct TrustAnchor {
+    zone: Name,
+    data: AnchorData,
+}
+
+impl TrustAnchor {
+    fn anchor_type(&self) -> &'static str {
+        match self.data {
+            AnchorData::Ds(_) => constants::TRUST_ANCHOR_DS,
+            AnchorData::Dnskey(_) => constants::TRUST_ANCHOR_DNSKEY,
+        }
+    }
+
+    fn key_tag(&self) -> Option<u16> {
+        match &self.data {
+            AnchorData::Ds(ds) => Some(ds.key_tag()),
+            AnchorData::Dnskey(key) => key.calculate_key_tag().ok(),
+        }
+    }
+
+    //区域的 DNSKEY 是否与信任锚对应
+    fn matches(&self, key: &DNSKEY) -> bool {
+        match &self.data {
+            AnchorData::Ds(ds) => ds.covers(&self.zone, key).unwrap_or(false),
+            AnchorData::Dnskey(anchor) => {
+                anchor.public_key().algorithm() == key.public_key().algorithm()
+                    && anchor.public_key().public_bytes() == key.public_key().public_bytes()
+            }
+        }
+    }
+
+    fn info(&self) -> TrustAnchorInfo {
+        TrustAnchorInfo {
+            zone: self.zone.to_string(),
+            anchor_type: self.anchor_type().to_string(),
+            key_tag: self.key_tag(),
+            expires: None,
+            expired: false,
+        }
+    }
+}
+
+//否定信任锚, 到期日期当天 (UTC) 结束后失效
+struct NegativeAnchor {
+    zone: Name,
+    until: String,
+    expires_at: u64,
+}
+
+impl NegativeAnchor {
+    fn expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    fn info(&self, now: u64) -> TrustAnchorInfo {
+        TrustAnchorInfo {
+            zone: self.zone.to_string(),
+            anchor_type: constants::TRUST_ANCHOR_NEGATIVE.to_string(),
+            key_tag: None,
+            expires: Some(self.until.clone()),
+            expired: self.expired(now),
+        }
+    }
+}
+
+pub struct Anchors {
+    anchors: Vec<TrustAnchor>,
+    negative: Vec<NegativeAnchor>,
+    pub errors: Vec<String>,
+}
+
+//验证方式
+pub enum Validation {
+    //使用信任锚验证
+    Anchored(TrustAnchors, TrustAnchorInfo),
+    //否定信任锚, 不验证
+    Disabled(TrustAnchorInfo),
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn zone_name(zone: &str) -> Option<Name> {
+    Name::from_str(&format!("{}.", zone.trim().trim_end_matches('.'))).ok()
+}
+
+//1970-01-01 起的天数, 公历
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+//YYYY-MM-DD => 该日结束的时间戳
+fn parse_date(date: &str) -> Option<u64> {
+    let parts: Vec<i64> = date
+        .split('-')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day) + 1;
+    Some(days as u64 * SECONDS_PER_DAY)
+}
+
+//zone 文件格式: owner [ttl] [class] DS|DNSKEY rdata
+fn parse_anchor(line: &str) -> Result<TrustAnchor, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let zone = tokens
+        .first()
+        .and_then(|owner| zone_name(owner))
+        .ok_or_else(|| "区域名称不正确".to_string())?;
+    let type_index = tokens
+        .iter()
+        .position(|token| token.eq_ignore_ascii_case("DS") || token.eq_ignore_ascii_case("DNSKEY"))
+        .ok_or_else(|| "只支持 DS 与 DNSKEY 记录".to_string())?;
+    if tokens[type_index].eq_ignore_ascii_case("DS") {
+        let rdata = tokens[type_index + 1..].join(" ");
+        match RData::try_from_str(RecordType::DS, &rdata) {
+            Ok(RData::DNSSEC(DNSSECRData::DS(ds))) => Ok(TrustAnchor {
+                zone,
+                data: AnchorData::Ds(ds),
+            }),
+            Ok(_) => Err("DS 记录不正确".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        let entries = Parser::new(line).parse().map_err(|e| e.to_string())?;
+        match entries.into_iter().next() {
+            Some(Entry::DNSKEY(record)) => Ok(TrustAnchor {
+                zone,
+                data: AnchorData::Dnskey(record.data().clone()),
+            }),
+            _ => Err("DNSKEY 记录不正确".to_string()),
+        }
+    }
+}
+
+//区域:到期日期
+fn parse_negative(item: &str) -> Result<NegativeAnchor, String> {
+    let (zone, until) = item
+        .rsplit_once(':')
+        .ok_or_else(|| "格式应为 区域:到期日期".to_string())?;
+    let zone = zone_name(zone).ok_or_else(|| "区域名称不正确".to_string())?;
+    let until = until.trim().to_string();
+    let expires_at = parse_date(&until).ok_or_else(|| "到期日期应为 YYYY-MM-DD".to_string())?;
+    Ok(NegativeAnchor {
+        zone,
+        until,
+        expires_at,
+    })
+}
+
+impl Anchors {
+    fn load() -> Self {
+        let mut anchors = Anchors {
+            anchors: vec![],
+            negative: vec![],
+            errors: vec![],
+        };
+        let path = constants::TRUST_ANCHOR_FILE.trim();
+        if !path.is_empty() {
+            match fs::read_to_string(path) {
+                Ok(text) => {
+                    for (index, line) in text.lines().enumerate() {
+                        let line = line.split(';').next().unwrap_or_default().trim();
+                        if line.is_empty() || line.starts_with('#') {
+                            continue;
+                        }
+                        match parse_anchor(line) {
+                            Ok(anchor) => anchors.anchors.push(anchor),
+                            Err(e) => {
+                                anchors
+                                    .errors
+                                    .push(format!("{} 第 {} 行: {}", path, index + 1, e))
+                            }
+                        }
+                    }
+                }
+                Err(e) => anchors
+                    .errors
+                    .push(format!("读取信任锚文件 {} 失败: {}", path, e)),
+            }
+        }
+        for item in constants::NEGATIVE_TRUST_ANCHORS.iter() {
+            match parse_negative(item) {
+                Ok(anchor) => anchors.negative.push(anchor),
+                Err(e) => anchors
+                    .errors
+                    .push(format!("NEGATIVE_TRUST_ANCHORS {}: {}", item, e)),
+            }
+        }
+        for e in &anchors.errors {
+            error!("anchor::load => {}", e);
+        }
+        anchors
+    }
+
+    //包含查询名称的最近的区域的全部信任锚
+    fn closest(&self, name: &Name) -> Vec<&TrustAnchor> {
+        let Some(labels) = self
+            .anchors
+            .iter()
+            .filter(|anchor| anchor.zone.zone_of(name))
+            .map(|anchor| anchor.zone.num_labels())
+            .max()
+        else {
+            return vec![];
+        };
+        self.anchors
+            .iter()
+            .filter(|anchor| anchor.zone.zone_of(name) && anchor.zone.num_labels() == labels)
+            .collect()
+    }
+
+    //未过期且包含查询名称的否定信任锚
+    fn negative(&self, name: &Name, now: u64) -> Option<&NegativeAnchor> {
+        self.negative
+            .iter()
+            .filter(|anchor| !anchor.expired(now) && anchor.zone.zone_of(name))
+            .max_by_key(|anchor| anchor.zone.num_labels())
+    }
+
+    pub fn list(&self) -> TrustAnchorList {
+        let now = now_secs();
+        TrustAnchorList {
+            anchors: self.anchors.iter().map(TrustAnchor::info).collect(),
+            negative_anchors: self
+                .negative
+                .iter()
+                .map(|anchor| anchor.info(now))
+                .collect(),
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+fn root_info() -> TrustAnchorInfo {
+    TrustAnchorInfo {
+        zone: Name::root().to_string(),
+        anchor_type: constants::TRUST_ANCHOR_ROOT.to_string(),
+        key_tag: None,
+        expires: None,
+        expired: false,
+    }
+}
+
+//用信任锚确认区域的 DNSKEY 记录集: 由信任锚对应的密钥签名且签名有效
+async fn trusted_keys(
+    dns_server: &str,
+    zone: &Name,
+    anchors: &[&TrustAnchor],
+) -> Result<(Vec<DNSKEY>, TrustAnchorInfo), AppError> {
+    let response = dns::query_raw(dns_server, zone.clone(), RecordType::DNSKEY, true).await?;
+    let records: Vec<&Record> = response
+        .answers()
+        .iter()
+        .filter(|record| record.record_type() == RecordType::DNSKEY)
+        .collect();
+    let keys: Vec<&DNSKEY> = records
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::DNSSEC(DNSSECRData::DNSKEY(key)) => Some(key),
+            _ => None,
+        })
+        .collect();
+    let rrsigs: Vec<_> = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::DNSSEC(DNSSECRData::RRSIG(rrsig))
+                if rrsig.type_covered() == RecordType::DNSKEY =>
+            {
+                Some(rrsig)
+            }
+            _ => None,
+        })
+        .collect();
+
+    for anchor in anchors {
+        for key in keys
+            .iter()
+            .filter(|key| !key.revoke() && anchor.matches(key))
+        {
+            let Ok(key_tag) = key.calculate_key_tag() else {
+                continue;
+            };
+            let signed = rrsigs.iter().any(|rrsig| {
+                rrsig.key_tag() == key_tag
+                    && key
+                        .verify_rrsig(zone, DNSClass::IN, rrsig, records.iter().copied())
+                        .is_ok()
+            });
+            if signed {
+                let info = TrustAnchorInfo {
+                    key_tag: Some(key_tag),
+                    ..anchor.info()
+                };
+                return Ok((keys.into_iter().cloned().collect(), info));
+            }
+        }
+    }
+    Err(AppError::new(ErrorCode::DnssecValidationFailed).arg(
+        "detail",
+        format!("{} 的 DNSKEY 记录集没有由信任锚对应的密钥签名", zone),
+    ))
+}
+
+//查询名称使用的信任锚: 否定信任锚 > 配置中最近的区域 > 内置根信任锚
+pub async fn validation(dns_server: &str, name: &Name) -> Result<Validation, AppError> {
+    let anchors = ANCHORS.closest(name);
+    let labels = anchors
+        .first()
+        .map(|anchor| anchor.zone.num_labels())
+        .unwrap_or_default();
+    let now = now_secs();
+    if let Some(negative) = ANCHORS
+        .negative(name, now)
+        .filter(|negative| negative.zone.num_labels() >= labels)
+    {
+        return Ok(Validation::Disabled(negative.info(now)));
+    }
+    let Some(zone) = anchors.first().map(|anchor| anchor.zone.clone()) else {
+        return Ok(Validation::Anchored(TrustAnchors::default(), root_info()));
+    };
+
+    let (keys, info) = trusted_keys(dns_server, &zone, &anchors).await?;
+    let mut trust_anchors = TrustAnchors::default();
+    for key in &keys {
+        trust_anchors.insert(key.public_key());
+    }
+    Ok(Validation::Anchored(trust_anchors, info))
+}
@@ -0,0 +1,422 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, BulkItem, BulkJob, BulkProgress, BulkRequest, BulkTextQuery, DnsQuery, ErrorCode,
+    QueryOptions,
+};
+use crate::i18n::Lang;
+use crate::monitor::now_millis;
+use crate::{dns, export, sse};
+use actix_web::web::{Bytes, Data};
+use futures_util::{Stream, stream};
+use log::info;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tokio::sync::{Semaphore, watch};
+
+const DEFAULT_RECORD_TYPE: &str = "A";
+const DEFAULT_DNS_SERVER: &str = "google";
+
+//批量查询任务, 只保存在内存中
+pub struct BulkStore {
+    state: Mutex<BulkState>,
+}
+
+#[derive(Default)]
+struct BulkState {
+    next_id: u64,
+    entries: BTreeMap<u64, BulkEntry>,
+}
+
+struct BulkEntry {
+    created_at: u64,
+    finished_at: Option<u64>,
+    //按提交顺序, 未完成为 None
+    items: Vec<Option<BulkItem>>,
+    progress: watch::Sender<BulkProgress>,
+}
+
+//一次待执行的查询
+#[derive(Clone, Debug)]
+pub struct BulkTask {
+    pub domain: String,
+    pub record_type: String,
+    pub dns_server: String,
+}
+
+fn not_found(id: u64) -> AppError {
+    AppError::new(ErrorCode::BulkNotFound).arg("id", id)
+}
+
+fn split_list(value: Option<&str>, default: &str) -> Vec<String> {
+    let list: Vec<String> = value
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if list.is_empty() {
+        vec![default.to_string()]
+    } else {
+        list
+    }
+}
+
+fn expand(
+    domain: &str,
+    record_types: &[String],
+    dns_servers: &[String],
+    tasks: &mut Vec<BulkTask>,
+) -> Result<(), AppError> {
+    for record_type in record_types {
+        dns::check_domain_and_type(domain, record_type)?;
+        for dns_server in dns_servers {
+            dns::resolve_dns_addr(dns_server)?;
+            if tasks.len() >= *constants::BULK_MAX_QUERIES {
+                return Err(AppError::new(ErrorCode::BulkTooManyQueries)
+                    .arg("max", *constants::BULK_MAX_QUERIES));
+            }
+            tasks.push(BulkTask {
+                domain: domain.to_string(),
+                record_type: record_type.to_uppercase(),
+                dns_server: dns_server.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_not_empty(tasks: Vec<BulkTask>) -> Result<Vec<BulkTask>, AppError> {
+    if tasks.is_empty() {
+        return Err(AppError::new(ErrorCode::BulkEmpty));
+    }
+    Ok(tasks)
+}
+
+//json 方式: 域名 x 记录类型 x dns服务器
+pub fn tasks_from_request(request: &BulkRequest) -> Result<Vec<BulkTask>, AppError> {
+    let record_types = if request.record_types.is_empty() {
+        vec![DEFAULT_RECORD_TYPE.to_string()]
+    } else {
+        request.record_types.clone()
+    };
+    let dns_servers = if request.resolvers.is_empty() {
+        vec![DEFAULT_DNS_SERVER.to_string()]
+    } else {
+        request.resolvers.clone()
+    };
+    let mut tasks = vec![];
+    for domain in request
+        .domains
+        .iter()
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+    {
+        expand(domain, &record_types, &dns_servers, &mut tasks).map_err(|e| {
+            AppError::new(ErrorCode::BulkDomainInvalid)
+                .arg("domain", domain)
+                .cause(e)
+        })?;
+    }
+    check_not_empty(tasks)
+}
+
+//文本/csv 方式: 每行 域名[,记录类型[,dns服务器]], 有 tab 时按 tab 分隔, 字段可以带引号
+//# 开头为注释, 首行可以是表头
+pub fn tasks_from_text(text: &str, query: &BulkTextQuery) -> Result<Vec<BulkTask>, AppError> {
+    let default_types = split_list(query.record_type.as_deref(), DEFAULT_RECORD_TYPE);
+    let default_servers = split_list(query.dns_server.as_deref(), DEFAULT_DNS_SERVER);
+    let delimiter = if text.contains('\t') { b'\t' } else { b',' };
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let mut tasks = vec![];
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| {
+            let line = e.position().map_or(0, |position| position.line());
+            AppError::new(ErrorCode::BulkLineInvalid)
+                .arg("line", line)
+                .cause(AppError::new(ErrorCode::BulkCsvInvalid).arg("detail", e))
+        })?;
+        let line = record.position().map_or(0, |position| position.line());
+        let domain = record.get(0).unwrap_or_default();
+        //只有空白的行, 缩进的注释
+        if (domain.is_empty() && record.iter().all(str::is_empty)) || domain.starts_with('#') {
+            continue;
+        }
+        if index == 0 && domain.eq_ignore_ascii_case("domain") {
+            continue;
+        }
+        let record_types = match record.get(1).filter(|c| !c.is_empty()) {
+            Some(record_type) => vec![record_type.to_string()],
+            None => default_types.clone(),
+        };
+        let dns_servers = match record.get(2).filter(|c| !c.is_empty()) {
+            Some(dns_server) => vec![dns_server.to_string()],
+            None => default_servers.clone(),
+        };
+        expand(domain, &record_types, &dns_servers, &mut tasks).map_err(|e| {
+            AppError::new(ErrorCode::BulkLineInvalid)
+                .arg("line", line)
+                .cause(e)
+        })?;
+    }
+    check_not_empty(tasks)
+}
+
+impl BulkEntry {
+    fn job(&self) -> BulkJob {
+        BulkJob {
+            progress: self.progress.borrow().clone(),
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+            items: self.items.iter().flatten().cloned().collect(),
+        }
+    }
+}
+
+impl BulkStore {
+    pub fn new() -> Self {
+        BulkStore {
+            state: Mutex::new(BulkState {
+                next_id: 1,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BulkState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    //同时运行的任务超过 BULK_MAX_RUNNING 时拒绝
+    fn create(&self, total: usize) -> Result<BulkProgress, AppError> {
+        let mut state = self.lock();
+        let running = state
+            .entries
+            .values()
+            .filter(|entry| entry.finished_at.is_none())
+            .count();
+        if running >= *constants::BULK_MAX_RUNNING {
+            return Err(AppError::new(ErrorCode::RateLimited));
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        let progress = BulkProgress {
+            id,
+            total,
+            done: 0,
+            failed: 0,
+            finished: false,
+        };
+        state.entries.insert(
+            id,
+            BulkEntry {
+                created_at: now_millis(),
+                finished_at: None,
+                items: vec![None; total],
+                progress: watch::Sender::new(progress.clone()),
+            },
+        );
+        //删除最早完成的任务
+        while state.entries.len() > constants::BULK_MAX_JOBS {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .find(|(_, entry)| entry.finished_at.is_some())
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        Ok(progress)
+    }
+
+    fn complete(&self, id: u64, index: usize, item: BulkItem) {
+        let mut state = self.lock();
+        let Some(entry) = state.entries.get_mut(&id) else {
+            return;
+        };
+        let failed = item.code != constants::OKAY_CODE;
+        entry.items[index] = Some(item);
+        entry.progress.send_modify(|progress| {
+            progress.done += 1;
+            if failed {
+                progress.failed += 1;
+            }
+        });
+    }
+
+    fn finish(&self, id: u64) {
+        let mut state = self.lock();
+        if let Some(entry) = state.entries.get_mut(&id) {
+            entry.finished_at = Some(now_millis());
+            entry
+                .progress
+                .send_modify(|progress| progress.finished = true);
+        }
+    }
+
+    pub fn job(&self, id: u64) -> Result<BulkJob, AppError> {
+        self.lock()
+            .entries
+            .get(&id)
+            .map(|entry| entry.job())
+            .ok_or_else(|| not_found(id))
+    }
+
+    //订阅任务进度
+    pub fn subscribe(&self, id: u64) -> Result<watch::Receiver<BulkProgress>, AppError> {
+        self.lock()
+            .entries
+            .get(&id)
+            .map(|entry| entry.progress.subscribe())
+            .ok_or_else(|| not_found(id))
+    }
+}
+
+impl Default for BulkStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn query(task: BulkTask, lang: Lang) -> BulkItem {
+    let started = Instant::now();
+    let result = dns::v1_query(
+        DnsQuery {
+            domain: task.domain.clone(),
+            record_type: task.record_type.clone(),
+            dns_server: task.dns_server.clone(),
+            client_subnet: None,
+            options: QueryOptions::default(),
+        },
+        lang,
+    )
+    .await;
+    let (code, msg, dns_record) = match result {
+        Ok(result) => (
+            constants::OKAY_CODE.to_string(),
+            constants::OKAY_MSG.to_string(),
+            result.dns_record,
+        ),
+        Err(e) => (e.code().to_string(), e.localize(lang), vec![]),
+    };
+    BulkItem {
+        domain: task.domain,
+        record_type: task.record_type,
+        dns_server: task.dns_server,
+        code,
+        msg,
+        dns_record,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+//并发数由 BULK_CONCURRENCY 限制, 错误文案使用创建任务时的语言
+async fn run(store: Data<BulkStore>, id: u64, tasks: Vec<BulkTask>, lang: Lang) {
+    let semaphore = Arc::new(Semaphore::new((*constants::BULK_CONCURRENCY).max(1)));
+    let mut handles = Vec::with_capacity(tasks.len());
+    for (index, task) in tasks.into_iter().enumerate() {
+        let Ok(permit) = semaphore.clone().acquire_owned().await else {
+            break;
+        };
+        let store = store.clone();
+        handles.push(tokio::spawn(async move {
+            let item = query(task, lang).await;
+            store.complete(id, index, item);
+            drop(permit);
+        }));
+    }
+    for handle in handles {
+        let _ = handle.await;
+    }
+    store.finish(id);
+    info!("bulk::run => {} finished", id);
+}
+
+//创建任务并在后台执行, 立即返回任务 id
+pub fn start(
+    store: Data<BulkStore>,
+    tasks: Vec<BulkTask>,
+    lang: Lang,
+) -> Result<BulkProgress, AppError> {
+    let progress = store.create(tasks.len())?;
+    info!("bulk::start => {} {} queries", progress.id, progress.total);
+    tokio::spawn(run(store, progress.id, tasks, lang));
+    Ok(progress)
+}
+
+//进度推送 (text/event-stream), 先发送当前进度, 之后每次变化发送一次, 完成后结束
+pub fn progress_stream(
+    receiver: watch::Receiver<BulkProgress>,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    stream::unfold((Some(receiver), true), |(receiver, first)| async move {
+        let mut receiver = receiver?;
+        if !first && receiver.changed().await.is_err() {
+            return None;
+        }
+        let progress = receiver.borrow_and_update().clone();
+        if progress.finished {
+            return Some((Ok(sse::event("done", &progress)), (None, false)));
+        }
+        Some((
+            Ok(sse::event("progress", &progress)),
+            (Some(receiver), false),
+        ))
+    })
+}
+
+//csv 报告, 一次查询一行, 多条记录用 ; 分隔
+pub fn report_csv(job: &BulkJob) -> String {
+    let mut csv = export::csv_row(
+        &[
+            "domain",
+            "recordType",
+            "dnsServer",
+            "code",
+            "msg",
+            "values",
+            "ttls",
+            "durationMs",
+        ]
+        .map(String::from),
+    );
+    for item in &job.items {
+        let values: Vec<&str> = item.dns_record.iter().map(|r| r.value.as_str()).collect();
+        let ttls: Vec<String> = item.dns_record.iter().map(|r| r.ttl.to_string()).collect();
+        csv.push_str(&export::csv_row(&[
+            item.domain.clone(),
+            item.record_type.clone(),
+            item.dns_server.clone(),
+            item.code.clone(),
+            item.msg.clone(),
+            values.join(";"),
+            ttls.join(";"),
+            item.duration_ms.to_string(),
+        ]));
+    }
+    csv
+}
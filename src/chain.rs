@@ -0,0 +1,173 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants::{AliasChain, ChainHop};
+use hickory_client::proto::rr::{Name, RData, Record, RecordType};
+use hickory_client::proto::serialize::binary::BinDecodable;
+use std::collections::HashSet;
+
+//hickory 没有 DNAME 类型, 按未知类型读出, rdata 为不压缩的名称 (RFC 6672)
+const DNAME: u16 = 39;
+
+//不算作与 CNAME 共存的记录, DNSSEC 记录允许与 CNAME 同名
+const ALIAS_COMPANIONS: [RecordType; 4] = [
+    RecordType::CNAME,
+    RecordType::RRSIG,
+    RecordType::NSEC,
+    RecordType::NSEC3,
+];
+
+//沿别名链走到的位置
+pub struct Walk {
+    pub hops: Vec<ChainHop>,
+    pub canonical: Name,
+    pub loop_detected: bool,
+    pub too_long: bool,
+}
+
+fn dname_target(record: &Record) -> Option<Name> {
+    match record.data() {
+        RData::Unknown { code, rdata } if u16::from(*code) == DNAME => {
+            Name::from_bytes(rdata.anything()).ok()
+        }
+        _ => None,
+    }
+}
+
+//DNAME 替换 name 中 owner 的部分, 结果超过 255 字节时为空
+fn substitute(name: &Name, owner: &Name, target: &Name) -> Option<Name> {
+    let keep = name.num_labels().checked_sub(owner.num_labels())?;
+    let prefix = Name::from_labels(name.iter().take(keep as usize)).ok()?;
+    prefix.append_domain(target).ok()
+}
+
+//DNAME 优先于解析器合成的 CNAME, 只作用于所有者的下级名称
+fn next_hop(current: &Name, answers: &[Record]) -> Option<(ChainHop, Name)> {
+    let dname = answers.iter().find_map(|record| {
+        let owner = record.name().to_lowercase();
+        if owner == *current || !owner.zone_of(current) {
+            return None;
+        }
+        let target = dname_target(record)?;
+        let next = substitute(current, &owner, &target.to_lowercase())?;
+        Some((record, owner, next, "DNAME"))
+    });
+    let (record, owner, next, record_type) = dname.or_else(|| {
+        answers.iter().find_map(|record| match record.data() {
+            RData::CNAME(cname) if record.name().to_lowercase() == *current => {
+                Some((record, current.clone(), cname.0.to_lowercase(), "CNAME"))
+            }
+            _ => None,
+        })
+    })?;
+    let hop = ChainHop {
+        name: current.to_string(),
+        record_type: record_type.to_string(),
+        owner: owner.to_string(),
+        target: next.to_string(),
+        ttl: record.ttl(),
+    };
+    Some((hop, next))
+}
+
+//从查询名称沿 CNAME / DNAME 走到没有别名的名称, 回到已有名称或超过 limit 跳时停止
+pub fn walk(name: &Name, answers: &[Record], limit: usize) -> Walk {
+    //应答中的名称都是完整名称
+    let mut start = name.to_lowercase();
+    start.set_fqdn(true);
+    let mut walk = Walk {
+        hops: vec![],
+        canonical: start,
+        loop_detected: false,
+        too_long: false,
+    };
+    let mut visited = HashSet::from([walk.canonical.clone()]);
+    while let Some((hop, next)) = next_hop(&walk.canonical, answers) {
+        if walk.hops.len() >= limit {
+            walk.too_long = true;
+            break;
+        }
+        walk.hops.push(hop);
+        walk.canonical = next.clone();
+        if !visited.insert(next) {
+            walk.loop_detected = true;
+            break;
+        }
+    }
+    walk
+}
+
+//链终点的记录
+pub fn final_records<'a>(
+    canonical: &Name,
+    query_type: RecordType,
+    answers: &'a [Record],
+) -> Vec<&'a Record> {
+    answers
+        .iter()
+        .filter(|record| {
+            record.record_type() == query_type && record.name().to_lowercase() == *canonical
+        })
+        .collect()
+}
+
+//链中 CNAME 的所有者, 去重
+pub fn cname_owners(walk: &Walk) -> Vec<Name> {
+    let mut owners: Vec<Name> = walk
+        .hops
+        .iter()
+        .filter(|hop| hop.record_type == "CNAME")
+        .filter_map(|hop| Name::from_ascii(&hop.owner).ok())
+        .collect();
+    owners.dedup();
+    owners
+}
+
+//owner 的 CNAME 检查, records 为应答或对 owner 查询 SOA 的应答 (包括 authority), 同名的 SOA 表示 owner 是 zone 顶点
+pub fn check_owner<'a>(
+    owner: &Name,
+    records: impl Iterator<Item = &'a Record>,
+    chain: &mut AliasChain,
+) {
+    for record in records.filter(|record| record.name().to_lowercase() == *owner) {
+        let record_type = record.record_type();
+        if ALIAS_COMPANIONS.contains(&record_type) || u16::from(record_type) == DNAME {
+            continue;
+        }
+        let apex = owner.to_string();
+        if record_type == RecordType::SOA && !chain.cname_at_apex.contains(&apex) {
+            chain.cname_at_apex.push(apex);
+        }
+        let coexistence = format!("{} {}", owner, record_type);
+        if !chain.coexistence.contains(&coexistence) {
+            chain.coexistence.push(coexistence);
+        }
+    }
+}
+
+pub fn chain(walk: Walk, final_ttls: impl Iterator<Item = u32>) -> AliasChain {
+    let min_ttl = walk.hops.iter().map(|hop| hop.ttl).chain(final_ttls).min();
+    AliasChain {
+        hops: walk.hops,
+        canonical: walk.canonical.to_string(),
+        min_ttl,
+        loop_detected: walk.loop_detected,
+        too_long: walk.too_long,
+        cname_at_apex: vec![],
+        coexistence: vec![],
+    }
+}
@@ -15,42 +15,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::i18n;
+use crate::i18n::Lang;
 use actix_web::body::BoxBody;
+use actix_web::http::{StatusCode, header};
 use actix_web::{HttpResponse, Responder, ResponseError};
-use hickory_client::proto::rr::RecordType;
+use hickory_client::proto::dnssec::Proof;
+use hickory_client::proto::rr::{Record, RecordType};
 use lazy_static::lazy_static;
 use log::error;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::error::Error;
 use std::fmt::Display;
 use tera::Tera;
+use utoipa::openapi::RefOr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::{IntoParams, PartialSchema, ToSchema};
 
 pub const LOG4RS_INIT_FILE: &str = "log4rs.yaml";
 pub const TERA_TEMPLATE_PATH: &str = "html/templates/**/*";
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DnsRecordSecResult {
+    //记录所有者, 完整域名
+    #[serde(default)]
+    pub name: String,
     pub value: String,
     #[serde(rename = "recordType")]
     pub record_type: String,
     pub ttl: u32,
+
+    //dnssec 验证结果 Secure / Insecure / Bogus / Indeterminate
+    pub proof: String,
 }
 
 impl DnsRecordSecResult {
     #[allow(dead_code)]
-    pub fn new(record_type: RecordType, ttl: u32, value: String) -> Self {
+    pub fn new(
+        name: String,
+        record_type: RecordType,
+        ttl: u32,
+        value: String,
+        proof: Proof,
+    ) -> Self {
         DnsRecordSecResult {
+            name,
             record_type: record_type.to_string().to_uppercase(),
             ttl,
             value,
+            proof: proof.to_string(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DnsQuery {
     pub domain: String,
 
@@ -59,9 +80,71 @@ pub struct DnsQuery {
 
     #[serde(rename = "dnsServer")]
     pub dns_server: String,
+
+    //EDNS Client Subnet, 例如 203.0.113.0/24
+    #[serde(rename = "clientSubnet", default, skip_serializing_if = "Option::is_none")]
+    pub client_subnet: Option<String>,
+
+    #[serde(flatten)]
+    pub options: QueryOptions,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+//超时与重试, 为空时使用 DNS_SERVER_TIMEOUTS / QUERY_TIMEOUT / QUERY_RETRIES / QUERY_BACKOFF_MS
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, ToSchema)]
+pub struct QueryOptions {
+    //每次尝试的超时(毫秒)
+    #[serde(rename = "timeoutMs", default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    //超时或连接失败后的重试次数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+
+    //第一次重试前的等待(毫秒), 之后每次翻倍
+    #[serde(rename = "backoffMs", default, skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+}
+
+//一次发送与结果, 截断后改用 TCP 也算一次
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct QueryAttempt {
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //同一 dns 服务器的第几次尝试, 从 1 开始
+    pub attempt: u32,
+
+    //udp / tcp
+    pub transport: String,
+
+    //ok / truncated / timeout / error
+    pub outcome: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+
+    #[serde(rename = "timeoutMs")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnsSubnetQuery {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "clientSubnets")]
+    pub client_subnets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DnsSecQuery {
     pub domain: String,
     #[serde(rename = "dnsServer")]
@@ -71,13 +154,26 @@ pub struct DnsSecQuery {
     pub record_type: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 pub struct DnsRecordResult {
+    //记录所有者, 完整域名, 经过别名链时为链末端的名称
+    #[serde(default)]
+    pub name: String,
     pub value: String,
     pub ttl: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl DnsRecordResult {
+    pub fn new(record: &Record) -> Self {
+        DnsRecordResult {
+            name: record.name().to_string(),
+            value: record.data().to_string(),
+            ttl: record.ttl(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DnsResult {
     #[serde(rename = "dnsServer")]
     pub dns_server: String,
@@ -85,17 +181,1295 @@ pub struct DnsResult {
     #[serde(rename = "recordType")]
     pub record_type: String,
 
-    #[serde(rename = "record")]
-    pub dns_record: Vec<DnsRecordResult>,
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordResult>,
+
+    #[serde(rename = "clientSubnet", skip_serializing_if = "Option::is_none")]
+    pub client_subnet: Option<String>,
+
+    //解析器返回的 ECS scope prefix
+    #[serde(rename = "scopePrefix", skip_serializing_if = "Option::is_none")]
+    pub scope_prefix: Option<u8>,
+
+    //响应带 AA 标志, 即由权威服务器直接应答
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub authoritative: bool,
+
+    //分享链接 id, /r/{shareId}
+    #[serde(rename = "shareId", default, skip_serializing_if = "Option::is_none")]
+    pub share_id: Option<String>,
+
+    //国际化域名才有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idn: Option<IdnInfo>,
+
+    //dnsServer 为解析器组时, 各成员的处理情况
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<GroupReport>,
+
+    //每次发送的结果, 区分偶发丢包与服务器不可用
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<QueryAttempt>,
+
+    //查询的名称是别名时, 从查询名称到最终名称的 CNAME / DNAME 链
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<AliasChain>,
+}
+
+//别名链的一跳
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ChainHop {
+    //被替换的名称
+    pub name: String,
+
+    //CNAME / DNAME
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    //记录的所有者, CNAME 时与 name 相同, DNAME 时为 name 的上级
+    pub owner: String,
+
+    pub target: String,
+    pub ttl: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AliasChain {
+    pub hops: Vec<ChainHop>,
+
+    //链的终点, 最终记录的所有者
+    pub canonical: String,
+
+    //各跳与最终记录中最小的 TTL, 即整个应答可以缓存的时间
+    #[serde(rename = "minTtl", default, skip_serializing_if = "Option::is_none")]
+    pub min_ttl: Option<u32>,
+
+    //目标回到了链中已有的名称
+    #[serde(rename = "loopDetected")]
+    pub loop_detected: bool,
+
+    //超过 CNAME_CHAIN_LIMIT 跳
+    #[serde(rename = "tooLong")]
+    pub too_long: bool,
+
+    //CNAME 的所有者是 zone 顶点 (有 SOA), 违反 RFC 1034
+    #[serde(rename = "cnameAtApex", default, skip_serializing_if = "Vec::is_empty")]
+    pub cname_at_apex: Vec<String>,
+
+    //与 CNAME 同名的其他记录, 名称 类型
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coexistence: Vec<String>,
+}
+
+//国际化域名, 同时给出 U-label 与 A-label 形式
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct IdnInfo {
+    pub unicode: String,
+    pub ascii: String,
+
+    //可能的仿冒域名提示
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<IdnWarning>,
+}
+
+//仿冒提示, code 对应 html/i18n/messages.json 中的文案, args 为文案参数, 由页面按语言显示
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct IdnWarning {
+    pub code: String,
+    pub args: BTreeMap<String, String>,
+}
+
+impl IdnWarning {
+    #[allow(dead_code)]
+    pub fn new(code: &str, args: &[(&str, String)]) -> Self {
+        IdnWarning {
+            code: code.to_string(),
+            args: args
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+//子网对比, 每个子网一条
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnsSubnetResult {
+    #[serde(rename = "clientSubnet")]
+    pub client_subnet: String,
+
+    pub code: String,
+    pub msg: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<DnsResult>,
+}
+
+impl DnsSubnetResult {
+    pub fn new(client_subnet: String, result: Result<DnsResult, AppError>, lang: Lang) -> Self {
+        let (code, msg, result) = match result {
+            Ok(result) => (OKAY_CODE.to_string(), OKAY_MSG.to_string(), Some(result)),
+            Err(e) => (e.code().to_string(), e.localize(lang), None),
+        };
+        DnsSubnetResult {
+            client_subnet,
+            code,
+            msg,
+            result,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnsSecResult {
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordSecResult>,
+
+    #[serde(rename = "shareId", default, skip_serializing_if = "Option::is_none")]
+    pub share_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idn: Option<IdnInfo>,
+
+    //NXDOMAIN / NODATA 时的否定应答证明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denial: Option<DenialOfExistence>,
+
+    //验证使用的信任锚
+    #[serde(rename = "trustAnchor", default, skip_serializing_if = "Option::is_none")]
+    pub trust_anchor: Option<TrustAnchorInfo>,
+}
+
+//信任锚, 内置根信任锚、配置的 DS / DNSKEY 或否定信任锚
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct TrustAnchorInfo {
+    pub zone: String,
+
+    //ROOT / DS / DNSKEY / NEGATIVE
+    #[serde(rename = "anchorType")]
+    pub anchor_type: String,
+
+    #[serde(rename = "keyTag", default, skip_serializing_if = "Option::is_none")]
+    pub key_tag: Option<u16>,
+
+    //否定信任锚的到期日期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub expired: bool,
+}
+
+//配置的信任锚与否定信任锚
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TrustAnchorList {
+    pub anchors: Vec<TrustAnchorInfo>,
+
+    #[serde(rename = "negativeAnchors")]
+    pub negative_anchors: Vec<TrustAnchorInfo>,
+
+    //配置中无法解析的条目
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+//解析器组中一个成员的处理情况
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct GroupMemberResult {
+    pub name: String,
+
+    //ANSWERED / AGREED / DISAGREED / FAILED / SLOWER / SKIPPED
+    pub status: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    //没有发出或被取消的查询为空
+    #[serde(rename = "durationMs", default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+//解析器组的一次查询
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct GroupReport {
+    pub name: String,
+
+    //failover / round-robin / race / quorum
+    pub strategy: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<usize>,
+
+    #[serde(rename = "answeredBy")]
+    pub answered_by: String,
+
+    pub members: Vec<GroupMemberResult>,
+}
+
+//配置的解析器组
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ResolverGroupInfo {
+    pub name: String,
+    pub strategy: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<usize>,
+
+    pub members: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ResolverGroupList {
+    pub groups: Vec<ResolverGroupInfo>,
+
+    //配置中无法解析的条目
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+//DNS UPDATE 的前提条件 (RFC 2136 2.4)
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdatePrerequisite {
+    //nameExists / nameNotExists / rrsetExists / rrsetNotExists / rrsetEquals
+    pub kind: String,
+
+    //为空时为要修改的名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    //rrset 条件使用, 为空时为要修改的记录类型
+    #[serde(rename = "recordType", default, skip_serializing_if = "Option::is_none")]
+    pub record_type: Option<String>,
+
+    //rrsetEquals 时 RRset 的全部记录值
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+//DNS UPDATE, 用 TSIG_KEYS 中的密钥签名后发送给主服务器
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DnsUpdateRequest {
+    pub zone: String,
+
+    //主服务器, dns服务商 key 或 ipv4[:port]
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //TSIG_KEYS 中的密钥名称
+    #[serde(rename = "keyName")]
+    pub key_name: String,
+
+    //add / delete / replace
+    pub action: String,
+
+    //相对 zone 的名称, 以 . 结尾时为完整名称, @ 或为空时为 zone 本身
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    //add / replace 使用, 默认 UPDATE_DEFAULT_TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+
+    //zone 文件格式的记录值, delete 为空时删除整个 RRset
+    #[serde(default)]
+    pub values: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerequisites: Vec<UpdatePrerequisite>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DnsUpdateResult {
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    pub action: String,
+
+    //完整名称
+    pub name: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    //主服务器的应答码
+    #[serde(rename = "responseCode")]
+    pub response_code: String,
+
+    #[serde(rename = "auditId")]
+    pub audit_id: i64,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+//DNS UPDATE 审计日志, 发送前写入, 收到应答后更新结果
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateAuditRecord {
+    pub id: i64,
+
+    //毫秒时间戳
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+
+    pub requester: String,
+
+    #[serde(rename = "keyName")]
+    pub key_name: String,
+
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    pub action: String,
+
+    pub name: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    //完整的 DnsUpdateRequest
+    #[schema(value_type = Object)]
+    pub request: serde_json::Value,
+
+    #[serde(rename = "responseCode", default, skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<String>,
+
+    //PENDING 为已发送但没有结果
+    pub code: String,
+    pub msg: String,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpdateAuditQuery {
+    pub zone: Option<String>,
+    pub page: Option<u32>,
+    pub size: Option<u32>,
+}
+
+//NOTIFY 请求, 发送给全部从服务器后轮询 SOA, 直到 serial 与主服务器一致或超过 deadlineMs
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NotifyRequest {
+    #[schema(example = "example.com")]
+    pub zone: String,
+
+    //主服务器, serial 以它的 SOA 为准
+    #[schema(example = "192.0.2.1")]
+    pub primary: String,
+
+    //从服务器, dns服务商 key 或 ip[:port]
+    pub secondaries: Vec<String>,
+
+    //TSIG_KEYS 中的密钥, 为空时不签名
+    #[serde(rename = "keyName", default, skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
+
+    //默认 NOTIFY_DEFAULT_DEADLINE_MS, 为 0 时只发送 NOTIFY 不轮询
+    #[serde(rename = "deadlineMs", default, skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+
+    //默认 NOTIFY_DEFAULT_INTERVAL_MS, 范围 MIN_NOTIFY_INTERVAL_MS 到 MAX_NOTIFY_DEADLINE_MS
+    #[serde(rename = "intervalMs", default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+}
+
+//一个从服务器的 NOTIFY 应答与 SOA 轮询结果
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NotifySecondary {
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //NOTIFY 的应答码, 没有应答时为空
+    #[serde(rename = "responseCode", default, skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<String>,
+
+    //应答的 AA 位
+    pub authoritative: bool,
+
+    #[serde(rename = "notifyMs", default, skip_serializing_if = "Option::is_none")]
+    pub notify_ms: Option<u64>,
+
+    //NOTIFY 没有应答或应答校验失败
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    //最后一次查到的 serial
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<u32>,
+
+    //serial 等于或按 RFC 1982 新于主服务器
+    pub synced: bool,
+
+    //SOA 查询次数
+    pub polls: u32,
+
+    //最后一次 SOA 查询的错误
+    #[serde(rename = "pollError", default, skip_serializing_if = "Option::is_none")]
+    pub poll_error: Option<String>,
+
+    //从发送 NOTIFY 到同步的毫秒数
+    #[serde(rename = "syncedMs", default, skip_serializing_if = "Option::is_none")]
+    pub synced_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NotifyReport {
+    pub zone: String,
+    pub primary: String,
+
+    #[serde(rename = "primarySerial")]
+    pub primary_serial: u32,
+
+    pub secondaries: Vec<NotifySecondary>,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+//转发解析器的一个上游
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ResolverUpstream {
+    //dns服务商 key 或 ip[:port]
+    pub name: String,
+    pub address: String,
+    pub queries: u64,
+    pub failures: u64,
+
+    //成功应答的平均耗时
+    #[serde(rename = "avgMs")]
+    pub avg_ms: u64,
+
+    #[serde(rename = "lastError", default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+//转发解析器状态
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ResolverStatus {
+    pub enabled: bool,
+
+    //监听地址, 未启用时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+
+    pub upstreams: Vec<ResolverUpstream>,
+
+    #[serde(rename = "cacheSize")]
+    pub cache_size: usize,
+
+    #[serde(rename = "cacheHits")]
+    pub cache_hits: u64,
+
+    #[serde(rename = "cacheMisses")]
+    pub cache_misses: u64,
+
+    //所有上游都失败, 返回 SERVFAIL 的次数
+    #[serde(rename = "servFail")]
+    pub serv_fail: u64,
+
+    //配置中无法解析的上游
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+//权威段中的一条 NSEC / NSEC3 记录
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DenialRecord {
+    pub owner: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    pub ttl: u32,
+
+    //覆盖范围 owner ~ next, NSEC3 为哈希
+    pub next: String,
+
+    //类型位图
+    pub types: Vec<String>,
+
+    pub proof: String,
+
+    //以下只有 NSEC3 才有
+    #[serde(rename = "hashAlgorithm", default, skip_serializing_if = "Option::is_none")]
+    pub hash_algorithm: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<u16>,
+
+    //十六进制, 空盐为 "-"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+
+    #[serde(rename = "optOut", default, skip_serializing_if = "Option::is_none")]
+    pub opt_out: Option<bool>,
+}
+
+//否定应答 (NXDOMAIN / NODATA) 的存在性否定证明
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DenialOfExistence {
+    //NXDOMAIN / NODATA
+    pub kind: String,
+
+    //NSEC / NSEC3, 没有证明记录时为空
+    #[serde(rename = "denialType", default, skip_serializing_if = "Option::is_none")]
+    pub denial_type: Option<String>,
+
+    pub records: Vec<DenialRecord>,
+
+    //证明是否完整
+    pub proven: bool,
+
+    pub explanation: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MonitorRequest {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    //dns服务商 key 或者 ipv4
+    pub resolvers: Vec<String>,
+
+    //执行间隔, 秒
+    pub interval: u64,
+
+    //告警通道
+    #[serde(default)]
+    pub alerts: Vec<AlertChannel>,
+}
+
+//告警通道, 按 type 区分
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "type")]
+pub enum AlertChannel {
+    //通用 webhook, POST AlertPayload json
+    #[serde(rename = "webhook")]
+    Webhook { url: String },
+
+    //Slack 兼容 webhook, POST {"text": "..."}
+    #[serde(rename = "slack")]
+    Slack { url: String },
+
+    //邮件, smtp 服务器在 .env 中配置
+    #[serde(rename = "email")]
+    Email { to: Vec<String> },
+}
+
+//告警内容, 变更事件 + 本次查询结果
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlertPayload {
+    #[serde(flatten)]
+    pub change: MonitorChange,
+    pub result: DnsResult,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MonitorJob {
+    pub id: u64,
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    pub resolvers: Vec<String>,
+    pub interval: u64,
+
+    #[serde(default)]
+    pub alerts: Vec<AlertChannel>,
+
+    //毫秒时间戳
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+
+    #[serde(rename = "lastRunAt")]
+    pub last_run_at: Option<u64>,
+}
+
+//监控任务在某个 dns 服务器上的一次查询结果
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MonitorAnswer {
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "checkedAt")]
+    pub checked_at: u64,
+
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordResult>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub authoritative: bool,
+
+    //Secure / Insecure / Bogus / Indeterminate, 无记录为 None, 查询失败为 Error, 解析器组为 Unsupported
+    pub dnssec: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MonitorChange {
+    #[serde(rename = "monitorId")]
+    pub monitor_id: u64,
+
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "changedAt")]
+    pub changed_at: u64,
+
+    //RRSET / TTL / DNSSEC / ERROR
+    pub kind: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MonitorChangesQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct MonitorDetail {
+    #[serde(flatten)]
+    pub job: MonitorJob,
+    pub answers: Vec<MonitorAnswer>,
+    pub changes: Vec<MonitorChange>,
+}
+
+//查询历史
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct HistoryRecord {
+    pub id: i64,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+
+    //请求方地址
+    pub requester: String,
+
+    //query / dnssec / resolver
+    pub kind: String,
+
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "clientSubnet", skip_serializing_if = "Option::is_none")]
+    pub client_subnet: Option<String>,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+
+    pub code: String,
+    pub msg: String,
+
+    //DnsResult / DnsSecResult
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+
+    #[serde(rename = "shareId")]
+    pub share_id: Option<String>,
+
+    //分享链接过期时间, 为空不过期
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PermalinkQuery {
+    //是否重新实时查询并与保存的结果对比
+    #[serde(default)]
+    pub rerun: bool,
+}
+
+//分享页面中的一条记录
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct PermalinkRecord {
+    //记录所有者, 旧记录保存时没有该字段
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+    pub value: String,
+    pub ttl: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
+}
+
+//保存结果与实时结果对比
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PermalinkDiff {
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+    pub value: String,
+
+    //SAME / ADDED / REMOVED
+    pub status: String,
+
+    #[serde(rename = "savedTtl")]
+    pub saved_ttl: Option<u32>,
+
+    #[serde(rename = "liveTtl")]
+    pub live_ttl: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PermalinkView {
+    pub history: HistoryRecord,
+    pub saved: Vec<PermalinkRecord>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live: Option<Vec<PermalinkRecord>>,
+
+    #[serde(rename = "liveError", skip_serializing_if = "Option::is_none")]
+    pub live_error: Option<String>,
+
+    pub diff: Vec<PermalinkDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    pub domain: Option<String>,
+
+    #[serde(rename = "recordType")]
+    pub record_type: Option<String>,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: Option<String>,
+
+    pub code: Option<String>,
+
+    //毫秒时间戳
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+
+    pub page: Option<u32>,
+    pub size: Option<u32>,
+}
+
+//流式查询, GET 参数, 多个 dns 服务器逗号分隔
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DnsStreamQuery {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServers")]
+    pub dns_servers: String,
+
+    //只用于普通查询
+    #[serde(rename = "clientSubnet", default)]
+    pub client_subnet: Option<String>,
+
+    //只用于普通查询, 同 QueryOptions
+    #[serde(rename = "timeoutMs", default)]
+    pub timeout_ms: Option<u64>,
+
+    #[serde(default)]
+    pub retries: Option<u32>,
+
+    #[serde(rename = "backoffMs", default)]
+    pub backoff_ms: Option<u64>,
+}
+
+//子网对比流式查询, 多个子网逗号分隔
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DnsSubnetStreamQuery {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "clientSubnets")]
+    pub client_subnets: String,
+}
+
+//流式查询中一个 dns 服务器 (或子网) 的结果, index 为请求中的位置
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnsStreamResult<T> {
+    pub index: usize,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "clientSubnet", skip_serializing_if = "Option::is_none")]
+    pub client_subnet: Option<String>,
+
+    pub code: String,
+    pub msg: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<T>,
+}
+
+impl<T> DnsStreamResult<T> {
+    #[allow(dead_code)]
+    pub fn new(
+        index: usize,
+        dns_server: String,
+        client_subnet: Option<String>,
+        result: Result<T, AppError>,
+        lang: Lang,
+    ) -> Self {
+        let (code, msg, result) = match result {
+            Ok(result) => (OKAY_CODE.to_string(), OKAY_MSG.to_string(), Some(result)),
+            Err(e) => (e.code().to_string(), e.localize(lang), None),
+        };
+        DnsStreamResult {
+            index,
+            dns_server,
+            client_subnet,
+            code,
+            msg,
+            result,
+        }
+    }
+}
+
+//导出格式, csv 与 zone 也可以用 Accept 头指定
+#[derive(Serialize, Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    //csv / json / zone / dig, 为空时返回普通 json
+    pub format: Option<String>,
+}
+
+//按分享链接 id 导出保存的结果, 多个逗号分隔
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportHistoryQuery {
+    #[serde(rename = "shareIds")]
+    pub share_ids: String,
+
+    pub format: Option<String>,
+}
+
+//导出时一个 dns 服务器的结果
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExportResult {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "clientSubnet", skip_serializing_if = "Option::is_none")]
+    pub client_subnet: Option<String>,
+
+    #[serde(rename = "durationMs", skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+
+    #[serde(rename = "record")]
+    pub records: Vec<PermalinkRecord>,
+}
+
+//批量查询, json 方式提交
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkRequest {
+    pub domains: Vec<String>,
+
+    //为空时查询 A 记录
+    #[serde(rename = "recordTypes", default)]
+    pub record_types: Vec<String>,
+
+    //dns服务商 key 或者 ipv4, 为空时使用 google
+    #[serde(default)]
+    pub resolvers: Vec<String>,
+}
+
+//批量查询, 文本方式提交时每行没写类型/服务器使用的默认值, 多个逗号分隔
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkTextQuery {
+    #[serde(rename = "recordType")]
+    pub record_type: Option<String>,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: Option<String>,
+}
+
+//批量查询中的一条结果
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BulkItem {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    pub code: String,
+    pub msg: String,
+
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordResult>,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+//批量查询进度
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BulkProgress {
+    pub id: u64,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub finished: bool,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BulkJob {
+    #[serde(flatten)]
+    pub progress: BulkProgress,
+
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<u64>,
+
+    //按提交顺序, 只包含已完成的查询
+    pub items: Vec<BulkItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkReportQuery {
+    //csv / json, 默认 csv
+    pub format: Option<String>,
+}
+
+//按 CD 位发送的一次查询
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnsCdAnswer {
+    #[serde(rename = "checkingDisabled")]
+    pub checking_disabled: bool,
+
+    pub code: String,
+    pub msg: String,
+
+    //NOERROR / SERVFAIL / NXDOMAIN ..., 未收到应答为空
+    #[serde(rename = "responseCode", skip_serializing_if = "Option::is_none")]
+    pub response_code: Option<String>,
+
+    //解析器验证通过 (AD 标志)
+    #[serde(rename = "authenticData", default, skip_serializing_if = "std::ops::Not::not")]
+    pub authentic_data: bool,
+
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordResult>,
+}
+
+//dnssec 诊断, 对比 CD=0 与 CD=1 的应答判断 SERVFAIL 是否由验证失败引起
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnssecDiagnosis {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //HEALTHY / DNSSEC_BOGUS / BROKEN
+    pub status: String,
+
+    //CD=0, 解析器验证
+    pub validating: DnsCdAnswer,
+
+    //CD=1, 解析器不验证
+    pub unchecked: DnsCdAnswer,
+
+    //DnssecClient 本地验证结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dnssec: Option<DnsSecResult>,
+
+    #[serde(rename = "dnssecError", skip_serializing_if = "Option::is_none")]
+    pub dnssec_error: Option<String>,
+
+    pub explanation: Vec<String>,
+}
+
+//dnssec 签名与密钥检查
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnssecHealthQuery {
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //签名剩余天数小于该值时告警, 为空使用 DNSSEC_EXPIRY_WARN_DAYS
+    #[serde(rename = "warnDays", default)]
+    pub warn_days: Option<u32>,
+}
+
+//区域中的一个 DNSKEY
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnskeyInfo {
+    #[serde(rename = "keyTag")]
+    pub key_tag: u16,
+
+    //KSK / ZSK, 按 SEP 标志区分
+    pub role: String,
+    pub flags: u16,
+    pub algorithm: String,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub revoked: bool,
+
+    //上级区域有对应的 DS
+    #[serde(rename = "hasDs")]
+    pub has_ds: bool,
+}
+
+//上级区域中的一个 DS
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DsInfo {
+    #[serde(rename = "keyTag")]
+    pub key_tag: u16,
+    pub algorithm: String,
+
+    #[serde(rename = "digestType")]
+    pub digest_type: String,
+    pub digest: String,
+
+    //与区域中某个 DNSKEY 的摘要一致
+    pub matched: bool,
+}
+
+//关键记录集上的一个 RRSIG
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RrsigInfo {
+    #[serde(rename = "typeCovered")]
+    pub type_covered: String,
+
+    #[serde(rename = "keyTag")]
+    pub key_tag: u16,
+    pub algorithm: String,
+
+    #[serde(rename = "signerName")]
+    pub signer_name: String,
+
+    //毫秒时间戳
+    pub inception: u64,
+    pub expiration: u64,
+
+    //距离过期的天数, 已过期为负数
+    #[serde(rename = "daysLeft")]
+    pub days_left: i64,
+
+    pub expired: bool,
+
+    //签名密钥在 DNSKEY 中
+    #[serde(rename = "keyKnown")]
+    pub key_known: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct DnssecHealthReport {
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //OK / WARNING / CRITICAL / UNSIGNED
+    pub status: String,
+
+    #[serde(rename = "warnDays")]
+    pub warn_days: u32,
+
+    pub keys: Vec<DnskeyInfo>,
+    pub ds: Vec<DsInfo>,
+    pub signatures: Vec<RrsigInfo>,
+
+    //DNSKEY 中使用的算法, 多于一个表示正在进行算法轮换
+    pub algorithms: Vec<String>,
+
+    #[serde(rename = "algorithmRollover")]
+    pub algorithm_rollover: bool,
+
+    pub warnings: Vec<String>,
+}
+
+//区域同步检查, 通过 dnsServer 查询 NS 与各 NS 的地址, 再直接向每个地址查询 SOA
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ZoneSyncQuery {
+    #[schema(example = "example.com")]
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    #[schema(example = "google")]
+    pub dns_server: String,
+
+    //查询权威服务器使用的端口, 默认 53
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+//一个 NS 地址的 SOA
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ZoneSyncServer {
+    pub ns: String,
+
+    //NS 没有地址时为空
+    pub address: String,
+
+    //ipv4 / ipv6
+    pub family: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mname: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<u32>,
+
+    //应答的 AA 位, 不是权威应答的服务器可能没有这个 zone
+    pub authoritative: bool,
+
+    #[serde(rename = "durationMs", default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+
+    //落后最大 serial 的数量, 按 RFC 1982 计算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lag: Option<u32>,
+
+    //serial 落后于最大 serial
+    pub behind: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ZoneSyncReport {
+    pub zone: String,
+
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    //全部 NS 中最大的 serial, 没有任何应答时为空
+    #[serde(rename = "maxSerial", default, skip_serializing_if = "Option::is_none")]
+    pub max_serial: Option<u32>,
+
+    //全部地址都应答且 serial 相同
+    pub consistent: bool,
+
+    pub servers: Vec<ZoneSyncServer>,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+//自检, 一个 dns 服务器的结果
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SelftestResult {
+    #[serde(rename = "dnsServer")]
+    pub dns_server: String,
+
+    #[serde(rename = "dnsAddr")]
+    pub dns_addr: String,
+
+    //有应答
+    pub reachable: bool,
+
+    //应答中包含期望的记录
+    pub correct: bool,
+
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+
+    pub code: String,
+    pub msg: String,
+
+    #[serde(rename = "record")]
+    pub dns_record: Vec<DnsRecordResult>,
+}
+
+//自检报告, 对 SELFTEST_SERVERS (默认为全部内置 dns 服务器) 查询已知答案的域名
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SelftestReport {
+    pub domain: String,
+
+    #[serde(rename = "recordType")]
+    pub record_type: String,
+
+    pub expected: Vec<String>,
+    pub total: usize,
+    pub reachable: usize,
+    pub correct: usize,
+    pub results: Vec<SelftestResult>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DnsSecResult {
-    #[serde(rename = "dnsServer")]
-    pub dns_server: String,
+//就绪检查中的一项
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadyCheck {
+    //templates / config / resolver
+    pub name: String,
+    pub ok: bool,
 
-    #[serde(rename = "record")]
-    pub dns_record: Vec<DnsRecordSecResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadyReport {
+    pub ready: bool,
+    pub checks: Vec<ReadyCheck>,
 }
 
 #[allow(dead_code)]
@@ -110,22 +1484,288 @@ pub const INTERNAL_SERVER_ERROR: &str = "Internal Server Error!";
 #[allow(dead_code)]
 pub const NOT_FOUND_ERROR: &str = "404 not found!";
 
+#[allow(dead_code)]
+pub const MONITORS_HTML: &str = "monitors.html";
+
+#[allow(dead_code)]
+pub const PERMALINK_HTML: &str = "result.html";
+
+#[allow(dead_code)]
+pub const BULK_HTML: &str = "bulk.html";
+
+#[allow(dead_code)]
+pub const UPDATE_HTML: &str = "update.html";
+
+#[allow(dead_code)]
+pub const ZONE_SYNC_HTML: &str = "zone_sync.html";
+
 #[allow(dead_code)]
 pub const NOT_FOUND_HTML: &str = "404.html";
 
 #[allow(dead_code)]
 pub const INTERNAL_SERVER_HTML: &str = "500.html";
 
-//template render exp code , AppError
+//监控变更类型
+#[allow(dead_code)]
+pub const MONITOR_CHANGE_RRSET: &str = "RRSET";
+
+#[allow(dead_code)]
+pub const MONITOR_CHANGE_TTL: &str = "TTL";
+
+#[allow(dead_code)]
+pub const MONITOR_CHANGE_DNSSEC: &str = "DNSSEC";
+
+#[allow(dead_code)]
+pub const MONITOR_CHANGE_ERROR: &str = "ERROR";
+
+//变更事件默认返回条数
+#[allow(dead_code)]
+pub const MONITOR_CHANGES_LIMIT: usize = 100;
+
+//告警 webhook / smtp 超时时间, 秒
+#[allow(dead_code)]
+pub const ALERT_HTTP_TIMEOUT: u64 = 10;
+
+//历史查询类型
+#[allow(dead_code)]
+pub const HISTORY_KIND_QUERY: &str = "query";
+
+#[allow(dead_code)]
+pub const HISTORY_KIND_DNSSEC: &str = "dnssec";
+
+#[allow(dead_code)]
+pub const HISTORY_KIND_RESOLVER: &str = "resolver";
+
+//解析器组策略
+#[allow(dead_code)]
+pub const GROUP_FAILOVER: &str = "failover";
+
+#[allow(dead_code)]
+pub const GROUP_ROUND_ROBIN: &str = "round-robin";
+
+#[allow(dead_code)]
+pub const GROUP_RACE: &str = "race";
+
+#[allow(dead_code)]
+pub const GROUP_QUORUM: &str = "quorum";
+
+//解析器组成员的处理情况
+#[allow(dead_code)]
+pub const GROUP_MEMBER_ANSWERED: &str = "ANSWERED";
+
+#[allow(dead_code)]
+pub const GROUP_MEMBER_AGREED: &str = "AGREED";
+
+#[allow(dead_code)]
+pub const GROUP_MEMBER_DISAGREED: &str = "DISAGREED";
+
+#[allow(dead_code)]
+pub const GROUP_MEMBER_FAILED: &str = "FAILED";
+
+#[allow(dead_code)]
+pub const GROUP_MEMBER_SLOWER: &str = "SLOWER";
+
+#[allow(dead_code)]
+pub const GROUP_MEMBER_SKIPPED: &str = "SKIPPED";
+
+//一次尝试的结果
+#[allow(dead_code)]
+pub const ATTEMPT_OK: &str = "ok";
+
+#[allow(dead_code)]
+pub const ATTEMPT_TRUNCATED: &str = "truncated";
+
+#[allow(dead_code)]
+pub const ATTEMPT_TIMEOUT: &str = "timeout";
+
+#[allow(dead_code)]
+pub const ATTEMPT_ERROR: &str = "error";
+
+//DNS UPDATE 操作
+#[allow(dead_code)]
+pub const UPDATE_ADD: &str = "add";
+
+#[allow(dead_code)]
+pub const UPDATE_DELETE: &str = "delete";
+
+#[allow(dead_code)]
+pub const UPDATE_REPLACE: &str = "replace";
+
+//DNS UPDATE 前提条件
+#[allow(dead_code)]
+pub const PREREQ_NAME_EXISTS: &str = "nameExists";
+
+#[allow(dead_code)]
+pub const PREREQ_NAME_NOT_EXISTS: &str = "nameNotExists";
+
+#[allow(dead_code)]
+pub const PREREQ_RRSET_EXISTS: &str = "rrsetExists";
+
+#[allow(dead_code)]
+pub const PREREQ_RRSET_NOT_EXISTS: &str = "rrsetNotExists";
+
+#[allow(dead_code)]
+pub const PREREQ_RRSET_EQUALS: &str = "rrsetEquals";
+
+//add / replace 没有指定 ttl 时使用
+#[allow(dead_code)]
+pub const UPDATE_DEFAULT_TTL: u32 = 300;
+
+//审计日志中已发送、还没有结果的更新
+#[allow(dead_code)]
+pub const UPDATE_AUDIT_PENDING: &str = "PENDING";
+
+//TSIG 签名允许的时间偏差(秒)
+#[allow(dead_code)]
+pub const TSIG_FUDGE: u16 = 300;
+
+//NOTIFY 之后轮询从服务器 SOA 的默认时长与间隔(毫秒)
+#[allow(dead_code)]
+pub const NOTIFY_DEFAULT_DEADLINE_MS: u64 = 30000;
+
+#[allow(dead_code)]
+pub const MAX_NOTIFY_DEADLINE_MS: u64 = 120000;
+
+#[allow(dead_code)]
+pub const NOTIFY_DEFAULT_INTERVAL_MS: u64 = 1000;
+
+#[allow(dead_code)]
+pub const MIN_NOTIFY_INTERVAL_MS: u64 = 200;
+
+//一次 NOTIFY 的从服务器数量上限
+#[allow(dead_code)]
+pub const MAX_NOTIFY_SECONDARIES: usize = 20;
+
+//实时历史推送的缓冲条数, 订阅方处理不过来时丢弃最早的
+#[allow(dead_code)]
+pub const HISTORY_STREAM_CAPACITY: usize = 256;
+
+//检查过期分享链接的间隔, 秒
+#[allow(dead_code)]
+pub const SHARE_EXPIRE_INTERVAL: u64 = 3600;
+
+//分享链接 id 长度
+#[allow(dead_code)]
+pub const SHARE_ID_LEN: usize = 10;
+
+//对比状态
+#[allow(dead_code)]
+pub const DIFF_SAME: &str = "SAME";
+
+#[allow(dead_code)]
+pub const DIFF_ADDED: &str = "ADDED";
+
+#[allow(dead_code)]
+pub const DIFF_REMOVED: &str = "REMOVED";
+
+//历史分页
+#[allow(dead_code)]
+pub const HISTORY_PAGE_SIZE: u32 = 20;
+
+#[allow(dead_code)]
+pub const HISTORY_MAX_PAGE_SIZE: u32 = 100;
+
+//子网对比最多子网数量
+#[allow(dead_code)]
+pub const MAX_COMPARE_SUBNETS: usize = 16;
+
+//流式查询最多 dns 服务器数量
+#[allow(dead_code)]
+pub const MAX_STREAM_SERVERS: usize = 16;
+
+//请求中 timeoutMs / retries / backoffMs 的上限
+#[allow(dead_code)]
+pub const MAX_QUERY_TIMEOUT_MS: u64 = 60_000;
+
+#[allow(dead_code)]
+pub const MAX_QUERY_RETRIES: u32 = 5;
+
+#[allow(dead_code)]
+pub const MAX_QUERY_BACKOFF_MS: u64 = 10_000;
+
+//内存中保留的批量任务数量, 超出时删除最早完成的
+#[allow(dead_code)]
+pub const BULK_MAX_JOBS: usize = 50;
+
+//TXT 记录中单个字符串的最大字节数
+#[allow(dead_code)]
+pub const TXT_STRING_MAX: usize = 255;
+
+//接口路径前缀, 这些路径的错误始终返回 json
+#[allow(dead_code)]
+pub const API_PATH_PREFIX: &str = "/v1/";
+
+//健康检查路径, 给负载均衡探测用
+#[allow(dead_code)]
+pub const PROBE_PATHS: [&str; 2] = ["/healthz", "/readyz"];
+
+//RFC 7807 type, 具体错误见扩展字段 code
+#[allow(dead_code)]
+pub const PROBLEM_TYPE: &str = "about:blank";
+
+#[allow(dead_code)]
+pub const CONTENT_TYPE_PROBLEM_JSON: &str = "application/problem+json";
+
+//429 响应的 Retry-After, 秒
+#[allow(dead_code)]
+pub const RATE_LIMIT_RETRY_AFTER: u64 = 10;
+
+//验证使用的信任锚类型
+#[allow(dead_code)]
+pub const TRUST_ANCHOR_ROOT: &str = "ROOT";
+
+#[allow(dead_code)]
+pub const TRUST_ANCHOR_DS: &str = "DS";
+
+#[allow(dead_code)]
+pub const TRUST_ANCHOR_DNSKEY: &str = "DNSKEY";
+
+#[allow(dead_code)]
+pub const TRUST_ANCHOR_NEGATIVE: &str = "NEGATIVE";
+
+//dnssec 诊断结果
+#[allow(dead_code)]
+pub const DIAGNOSIS_HEALTHY: &str = "HEALTHY";
+
+#[allow(dead_code)]
+pub const DIAGNOSIS_DNSSEC_BOGUS: &str = "DNSSEC_BOGUS";
+
+#[allow(dead_code)]
+pub const DIAGNOSIS_BROKEN: &str = "BROKEN";
+
+//dnssec 签名与密钥检查结果
+#[allow(dead_code)]
+pub const DNSSEC_HEALTH_OK: &str = "OK";
+
+#[allow(dead_code)]
+pub const DNSSEC_HEALTH_WARNING: &str = "WARNING";
+
+#[allow(dead_code)]
+pub const DNSSEC_HEALTH_CRITICAL: &str = "CRITICAL";
+
+#[allow(dead_code)]
+pub const DNSSEC_HEALTH_UNSIGNED: &str = "UNSIGNED";
+
+//否定应答类型
+#[allow(dead_code)]
+pub const DENIAL_NXDOMAIN: &str = "NXDOMAIN";
+
+#[allow(dead_code)]
+pub const DENIAL_NODATA: &str = "NODATA";
+
+//RFC 9276, 超过该迭代次数时常见验证器按 Insecure 处理或返回 SERVFAIL
+#[allow(dead_code)]
+pub const NSEC3_ITERATIONS_LIMIT: u16 = 100;
+
+//就绪检查项
 #[allow(dead_code)]
-pub const TERA_RENDER_EXP_CODE: &str = "TERA500";
+pub const READY_CHECK_TEMPLATES: &str = "templates";
 
-//bus exp code , AppError
 #[allow(dead_code)]
-pub const BUS_EXP_CODE: &str = "BUS500";
+pub const READY_CHECK_CONFIG: &str = "config";
 
 #[allow(dead_code)]
-pub const QUERY_DNS_TIMEOUT: &str = "QUERY_DNS_TIMEOUT";
+pub const READY_CHECK_RESOLVER: &str = "resolver";
 
 #[allow(dead_code)]
 pub const OKAY_CODE: &str = "1";
@@ -154,10 +1794,7 @@ lazy_static! {
         .unwrap_or_else(|_| "8080".to_string())
         .parse()
         .unwrap_or(8080);
-    pub static ref TEMPLATES: Tera = {
-        let tera = Tera::new(TERA_TEMPLATE_PATH).unwrap();
-        tera
-    };
+    pub static ref TEMPLATES: Tera = Tera::new(TERA_TEMPLATE_PATH).unwrap();
     pub static ref DNS_MAP: HashMap<String, String> = {
         let mut m = HashMap::new();
         m.insert("google".to_string(), "8.8.8.8".to_string());
@@ -182,68 +1819,443 @@ lazy_static! {
         .unwrap_or_else(|_| "10".to_string())
         .parse()
         .unwrap_or(10);
+    //单个 dns 服务器的超时(毫秒), 覆盖 QUERY_TIMEOUT, 例如 google=2000,1.1.1.1=500
+    pub static ref DNS_SERVER_TIMEOUTS: Vec<String> = env::var("DNS_SERVER_TIMEOUTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //超时或连接失败后的重试次数, 0 为不重试
+    pub static ref QUERY_RETRIES: u32 = env::var("QUERY_RETRIES")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0);
+    //第一次重试前的等待(毫秒), 之后每次翻倍
+    pub static ref QUERY_BACKOFF_MS: u64 = env::var("QUERY_BACKOFF_MS")
+        .unwrap_or_else(|_| "200".to_string())
+        .parse()
+        .unwrap_or(200);
+    //CNAME / DNAME 链的最大跳数
+    pub static ref CNAME_CHAIN_LIMIT: usize = env::var("CNAME_CHAIN_LIMIT")
+        .unwrap_or_else(|_| "8".to_string())
+        .parse()
+        .unwrap_or(8);
+    //对链中每个 CNAME 的所有者查询 SOA, 检查 CNAME 在 zone 顶点与同名的其他记录
+    pub static ref CNAME_CHAIN_CHECKS: bool = env::var("CNAME_CHAIN_CHECKS")
+        .map(|v| v != "false")
+        .unwrap_or(true);
+
+    //查询历史 sqlite 文件
+    pub static ref HISTORY_DB: String =
+        env::var("HISTORY_DB").unwrap_or_else(|_| "data/history.db".to_string());
+
+    //分享链接有效期, 天, 0 为不过期, 创建时写入 expires_at, 修改后只影响之后保存的记录
+    pub static ref PERMALINK_TTL_DAYS: u64 = env::var("PERMALINK_TTL_DAYS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30);
+
+    //监控最小执行间隔, 秒
+    pub static ref MONITOR_MIN_INTERVAL: u64 = env::var("MONITOR_MIN_INTERVAL")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30);
+
+    //每个监控保留的查询结果条数
+    pub static ref MONITOR_HISTORY_SIZE: usize = env::var("MONITOR_HISTORY_SIZE")
+        .unwrap_or_else(|_| "200".to_string())
+        .parse()
+        .unwrap_or(200);
+
+    //告警发送失败重试次数
+    pub static ref ALERT_RETRY_COUNT: u32 = env::var("ALERT_RETRY_COUNT")
+        .unwrap_or_else(|_| "3".to_string())
+        .parse()
+        .unwrap_or(3);
+
+    //告警重试初始间隔, 毫秒, 每次翻倍
+    pub static ref ALERT_RETRY_BACKOFF_MS: u64 = env::var("ALERT_RETRY_BACKOFF_MS")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000);
+
+    //相同告警在该时间内只发送一次, 秒
+    pub static ref ALERT_DEDUPE_WINDOW: u64 = env::var("ALERT_DEDUPE_WINDOW")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse()
+        .unwrap_or(600);
+
+    //告警标题与正文的语言, zh / en
+    pub static ref ALERT_LANG: String = env::var("ALERT_LANG").unwrap_or_else(|_| "zh".to_string());
+
+    //邮件告警 smtp 配置, SMTP_HOST 为空时不能使用邮件告警
+    pub static ref SMTP_HOST: String = env::var("SMTP_HOST").unwrap_or_default();
+    pub static ref SMTP_PORT: u16 = env::var("SMTP_PORT")
+        .unwrap_or_else(|_| "25".to_string())
+        .parse()
+        .unwrap_or(25);
+    pub static ref SMTP_USERNAME: String = env::var("SMTP_USERNAME").unwrap_or_default();
+    pub static ref SMTP_PASSWORD: String = env::var("SMTP_PASSWORD").unwrap_or_default();
+    pub static ref SMTP_FROM: String =
+        env::var("SMTP_FROM").unwrap_or_else(|_| "dns-tool@localhost".to_string());
+    pub static ref SMTP_STARTTLS: bool = env::var("SMTP_STARTTLS")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    //批量查询并发数
+    pub static ref BULK_CONCURRENCY: usize = env::var("BULK_CONCURRENCY")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10);
+
+    //同时运行的批量任务数量, 超出返回 429
+    pub static ref BULK_MAX_RUNNING: usize = env::var("BULK_MAX_RUNNING")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5);
+
+    //单个批量任务最多查询次数 (域名 x 记录类型 x dns服务器)
+    pub static ref BULK_MAX_QUERIES: usize = env::var("BULK_MAX_QUERIES")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000);
+
+    //保留的变更事件条数
+    pub static ref MONITOR_MAX_CHANGES: usize = env::var("MONITOR_MAX_CHANGES")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000);
+    //自检查询的域名、记录类型与期望的应答, 多个逗号分隔
+    pub static ref SELFTEST_DOMAIN: String =
+        env::var("SELFTEST_DOMAIN").unwrap_or_else(|_| "one.one.one.one".to_string());
+    pub static ref SELFTEST_RECORD_TYPE: String =
+        env::var("SELFTEST_RECORD_TYPE").unwrap_or_else(|_| "A".to_string());
+    pub static ref SELFTEST_EXPECTED: Vec<String> = env::var("SELFTEST_EXPECTED")
+        .unwrap_or_else(|_| "1.1.1.1,1.0.0.1".to_string())
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //自检与 canary 查询的 dns 服务器, dns服务商 key 或 ipv4[:port], 为空时使用全部内置 dns 服务器
+    pub static ref SELFTEST_SERVERS: Vec<String> = env::var("SELFTEST_SERVERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+
+    //就绪检查中 canary 查询的超时时间, 毫秒
+    pub static ref READY_CANARY_TIMEOUT_MS: u64 = env::var("READY_CANARY_TIMEOUT_MS")
+        .unwrap_or_else(|_| "3000".to_string())
+        .parse()
+        .unwrap_or(3000);
+    //RRSIG 剩余天数小于该值时告警
+    pub static ref DNSSEC_EXPIRY_WARN_DAYS: u32 = env::var("DNSSEC_EXPIRY_WARN_DAYS")
+        .unwrap_or_else(|_| "7".to_string())
+        .parse()
+        .unwrap_or(7);
+    //额外的信任锚文件, 每行一条 DS 或 DNSKEY 记录, 为空时只使用内置的根信任锚
+    pub static ref TRUST_ANCHOR_FILE: String = env::var("TRUST_ANCHOR_FILE").unwrap_or_default();
+    //否定信任锚, 区域:到期日期, 多个逗号分隔, 例如 broken.example:2026-12-31
+    pub static ref NEGATIVE_TRUST_ANCHORS: Vec<String> = env::var("NEGATIVE_TRUST_ANCHORS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //本地转发解析器监听地址与端口, 端口为 0 时不启用
+    pub static ref RESOLVER_ADDRESS: String =
+        env::var("RESOLVER_ADDRESS").unwrap_or_else(|_| "127.0.0.1".to_string());
+    pub static ref RESOLVER_PORT: u16 = env::var("RESOLVER_PORT")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0);
+    //转发的上游, dns服务商 key 或 ipv4[:port], 逗号分隔, 按顺序故障转移
+    pub static ref RESOLVER_UPSTREAMS: Vec<String> = env::var("RESOLVER_UPSTREAMS")
+        .unwrap_or_else(|_| "cloudflare,google".to_string())
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //解析器组, 名称=策略:成员,成员, 多个组分号分隔, 例如 public=failover:cloudflare,google;vote=quorum/2:cloudflare,google,ali
+    pub static ref RESOLVER_GROUPS: Vec<String> = env::var("RESOLVER_GROUPS")
+        .unwrap_or_default()
+        .split(';')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //DNS UPDATE 接口的令牌, Authorization: Bearer <令牌>, 为空时不启用 DNS UPDATE
+    pub static ref UPDATE_TOKEN: String = env::var("UPDATE_TOKEN").unwrap_or_default();
+    //TSIG 密钥, 名称:算法:base64 密钥, 逗号分隔, 例如 lab-key:hmac-sha256:c2VjcmV0
+    pub static ref TSIG_KEYS: Vec<String> = env::var("TSIG_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    //应答缓存条数, 0 为不缓存
+    pub static ref RESOLVER_CACHE_SIZE: usize = env::var("RESOLVER_CACHE_SIZE")
+        .unwrap_or_else(|_| "10000".to_string())
+        .parse()
+        .unwrap_or(10000);
+}
+
+//错误码定义: 枚举值 => 接口返回的 code, HTTP 状态码, 文案见 html/i18n/messages.json
+//参数错误 400, 未授权 401, 未启用 403, 前提条件不满足 409, 上游 dns 失败 502, 超时 504, 限流 429, 未就绪 503
+//枚举、as_str、status 与 ALL 由同一份列表生成, 新增错误码只需要加一行
+macro_rules! error_codes {
+    ($($name:ident => $code:literal, $status:ident;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($name,)*
+        }
+
+        impl ErrorCode {
+            //全部错误码, 生成 openapi 文档时使用
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$name,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$name => $code,)*
+                }
+            }
+
+            pub fn status(self) -> StatusCode {
+                match self {
+                    $(ErrorCode::$name => StatusCode::$status,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    DomainEmpty => "DOMAIN_EMPTY", BAD_REQUEST;
+    DomainInvalid => "DOMAIN_INVALID", BAD_REQUEST;
+    IdnaTooLong => "IDNA_TOO_LONG", BAD_REQUEST;
+    IdnaEmptyLabel => "IDNA_EMPTY_LABEL", BAD_REQUEST;
+    IdnaHyphenEdge => "IDNA_HYPHEN_EDGE", BAD_REQUEST;
+    IdnaBadPunycode => "IDNA_BAD_PUNYCODE", BAD_REQUEST;
+    IdnaHyphen34 => "IDNA_HYPHEN_34", BAD_REQUEST;
+    IdnaDisallowedChar => "IDNA_DISALLOWED_CHAR", BAD_REQUEST;
+    IdnaLabelTooLong => "IDNA_LABEL_TOO_LONG", BAD_REQUEST;
+    IdnaInvalid => "IDNA_INVALID", BAD_REQUEST;
+    RecordTypeEmpty => "RECORD_TYPE_EMPTY", BAD_REQUEST;
+    RecordTypeUnsupported => "RECORD_TYPE_UNSUPPORTED", BAD_REQUEST;
+    DnsServerEmpty => "DNS_SERVER_EMPTY", BAD_REQUEST;
+    DnsServerInvalid => "DNS_SERVER_INVALID", BAD_REQUEST;
+    ClientSubnetEmpty => "CLIENT_SUBNET_EMPTY", BAD_REQUEST;
+    ClientSubnetInvalid => "CLIENT_SUBNET_INVALID", BAD_REQUEST;
+    ShareIdEmpty => "SHARE_ID_EMPTY", BAD_REQUEST;
+    ShareNotFound => "SHARE_NOT_FOUND", NOT_FOUND;
+    TooManyItems => "TOO_MANY_ITEMS", BAD_REQUEST;
+    RequestInvalid => "REQUEST_INVALID", BAD_REQUEST;
+    FormatUnsupported => "FORMAT_UNSUPPORTED", BAD_REQUEST;
+    NotFound => "NOT_FOUND", NOT_FOUND;
+    MethodNotAllowed => "METHOD_NOT_ALLOWED", METHOD_NOT_ALLOWED;
+    PayloadTooLarge => "PAYLOAD_TOO_LARGE", PAYLOAD_TOO_LARGE;
+    RateLimited => "RATE_LIMITED", TOO_MANY_REQUESTS;
+    NotReady => "NOT_READY", SERVICE_UNAVAILABLE;
+    //查询结果
+    DnsConnectFailed => "DNS_CONNECT_FAILED", BAD_GATEWAY;
+    DnsTimeout => "DNS_TIMEOUT", GATEWAY_TIMEOUT;
+    DnsServFail => "DNS_SERVFAIL", BAD_GATEWAY;
+    DnsRefused => "DNS_REFUSED", BAD_GATEWAY;
+    DnsQueryFailed => "DNS_QUERY_FAILED", BAD_GATEWAY;
+    DnssecValidationFailed => "DNSSEC_VALIDATION_FAILED", BAD_GATEWAY;
+    DnsQuorumFailed => "DNS_QUORUM_FAILED", BAD_GATEWAY;
+    DnsGroupFailed => "DNS_GROUP_FAILED", BAD_GATEWAY;
+    DnsAttemptsFailed => "DNS_ATTEMPTS_FAILED", BAD_GATEWAY;
+    QueryOptionInvalid => "QUERY_OPTION_INVALID", BAD_REQUEST;
+    //DNS UPDATE
+    Unauthorized => "UNAUTHORIZED", UNAUTHORIZED;
+    UpdateDisabled => "UPDATE_DISABLED", FORBIDDEN;
+    TsigKeyNotFound => "TSIG_KEY_NOT_FOUND", BAD_REQUEST;
+    UpdateNameOutsideZone => "UPDATE_NAME_OUTSIDE_ZONE", BAD_REQUEST;
+    UpdateRecordInvalid => "UPDATE_RECORD_INVALID", BAD_REQUEST;
+    UpdateValuesRequired => "UPDATE_VALUES_REQUIRED", BAD_REQUEST;
+    UpdateActionUnsupported => "UPDATE_ACTION_UNSUPPORTED", BAD_REQUEST;
+    UpdatePrerequisiteUnsupported => "UPDATE_PREREQUISITE_UNSUPPORTED", BAD_REQUEST;
+    DnsUpdatePrerequisiteFailed => "DNS_UPDATE_PREREQUISITE_FAILED", CONFLICT;
+    DnsUpdateRejected => "DNS_UPDATE_REJECTED", BAD_GATEWAY;
+    //NOTIFY 与 SOA
+    NotifySecondariesEmpty => "NOTIFY_SECONDARIES_EMPTY", BAD_REQUEST;
+    SoaNotFound => "SOA_NOT_FOUND", BAD_GATEWAY;
+    NsNotFound => "NS_NOT_FOUND", BAD_GATEWAY;
+    NsAddressNotFound => "NS_ADDRESS_NOT_FOUND", BAD_GATEWAY;
+    //监控与告警
+    MonitorNotFound => "MONITOR_NOT_FOUND", NOT_FOUND;
+    MonitorIntervalTooShort => "MONITOR_INTERVAL_TOO_SHORT", BAD_REQUEST;
+    AlertWebhookInvalid => "ALERT_WEBHOOK_INVALID", BAD_REQUEST;
+    AlertSmtpNotConfigured => "ALERT_SMTP_NOT_CONFIGURED", BAD_REQUEST;
+    AlertEmailEmpty => "ALERT_EMAIL_EMPTY", BAD_REQUEST;
+    AlertEmailInvalid => "ALERT_EMAIL_INVALID", BAD_REQUEST;
+    //批量查询
+    BulkNotFound => "BULK_NOT_FOUND", NOT_FOUND;
+    BulkEmpty => "BULK_EMPTY", BAD_REQUEST;
+    BulkTooManyQueries => "BULK_TOO_MANY_QUERIES", BAD_REQUEST;
+    BulkNotUtf8 => "BULK_NOT_UTF8", BAD_REQUEST;
+    BulkLineInvalid => "BULK_LINE_INVALID", BAD_REQUEST;
+    BulkCsvInvalid => "BULK_CSV_INVALID", BAD_REQUEST;
+    BulkDomainInvalid => "BULK_DOMAIN_INVALID", BAD_REQUEST;
+    //服务端
+    TemplateError => "TEMPLATE_ERROR", INTERNAL_SERVER_ERROR;
+    DatabaseError => "DATABASE_ERROR", INTERNAL_SERVER_ERROR;
+    InternalError => "INTERNAL_ERROR", INTERNAL_SERVER_ERROR;
+}
+
+//openapi 中为字符串枚举, 取值与 as_str 一致
+impl PartialSchema for ErrorCode {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .enum_values(Some(ErrorCode::ALL.iter().map(|code| code.as_str())))
+            .into()
+    }
+}
+
+impl ToSchema for ErrorCode {}
+
+//RFC 7807 problem details, 请求头 Accept: application/problem+json 时返回
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+
+    //扩展字段, 与 ApiResponse 的 code 相同
+    #[schema(value_type = ErrorCode)]
+    pub code: String,
 }
 
-#[derive(Debug, Serialize)]
+//code + 文案参数, 文案在输出时按语言生成
+#[derive(Debug, Clone)]
 pub struct AppError {
-    code: String,
-    msg: String,
+    code: ErrorCode,
+    args: Vec<(&'static str, String)>,
+    //内层错误, 对应文案中的 {cause}
+    cause: Option<Box<AppError>>,
 }
 
 impl AppError {
     #[allow(dead_code)]
-    pub fn new(code: impl Into<String>, msg: impl Into<String>) -> Self {
+    pub fn new(code: ErrorCode) -> Self {
         AppError {
-            code: code.into(),
-            msg: msg.into(),
+            code,
+            args: vec![],
+            cause: None,
         }
     }
+
+    //文案参数 {name}
+    #[allow(dead_code)]
+    pub fn arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn cause(mut self, cause: AppError) -> Self {
+        self.cause = Some(Box::new(cause));
+        self
+    }
+
     //template render exp
     #[allow(dead_code)]
     pub fn tera(error: Box<dyn Error>) -> Self {
         error!("{:?}", error);
-        AppError {
-            code: TERA_RENDER_EXP_CODE.to_string(),
-            msg: error.to_string(),
-        }
+        AppError::new(ErrorCode::TemplateError).arg("detail", error)
     }
 
-    //bus exp
+    //database exp
     #[allow(dead_code)]
-    pub fn bus(msg: impl Into<String>) -> Self {
-        AppError {
-            code: BUS_EXP_CODE.to_string(),
-            msg: msg.into(),
+    pub fn db(error: Box<dyn Error>) -> Self {
+        error!("{:?}", error);
+        AppError::new(ErrorCode::DatabaseError).arg("detail", error)
+    }
+
+    #[allow(dead_code)]
+    pub fn internal(detail: impl ToString) -> Self {
+        AppError::new(ErrorCode::InternalError).arg("detail", detail)
+    }
+
+    #[allow(dead_code)]
+    pub fn error_code(&self) -> ErrorCode {
+        self.code
+    }
+
+    #[allow(dead_code)]
+    pub fn code(&self) -> &'static str {
+        self.code.as_str()
+    }
+
+    //默认语言的文案, 日志、历史记录使用
+    #[allow(dead_code)]
+    pub fn msg(&self) -> String {
+        self.localize(Lang::default())
+    }
+
+    #[allow(dead_code)]
+    pub fn localize(&self, lang: Lang) -> String {
+        let mut args = self.args.clone();
+        if let Some(cause) = &self.cause {
+            args.push(("cause", cause.localize(lang)));
         }
+        i18n::format(lang, self.code(), &args)
     }
+
     #[allow(dead_code)]
-    pub fn query_dns_timeout(msg: String) -> Self {
-        AppError {
-            code: QUERY_DNS_TIMEOUT.to_string(),
-            msg: msg.into(),
+    pub fn problem(&self, lang: Lang, instance: &str) -> Problem {
+        let status = self.code.status();
+        Problem {
+            problem_type: PROBLEM_TYPE.to_string(),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: self.localize(lang),
+            instance: instance.to_string(),
+            code: self.code().to_string(),
         }
     }
+
+    #[allow(dead_code)]
+    pub fn api_response(&self, lang: Lang) -> ApiResponse<String> {
+        ApiResponse::full(
+            self.code().to_string(),
+            self.localize(lang),
+            EMPTY_STR.to_string(),
+        )
+    }
 }
 
 impl Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "AppError: code = {} msg = {} ", self.code, self.msg)
+        write!(f, "AppError: code = {} msg = {} ", self.code(), self.msg())
     }
 }
 
+//默认输出 ApiResponse, problem+json 与语言由 problem::render_errors 处理
 impl ResponseError for AppError {
-    fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::BAD_REQUEST
+    fn status_code(&self) -> StatusCode {
+        self.code.status()
     }
 
     fn error_response(&self) -> HttpResponse {
-        let json =
-            ApiResponse::<String>::full(self.code.clone(), self.msg.clone(), EMPTY_STR.to_string());
-        HttpResponse::Ok().json(json)
+        let mut builder = HttpResponse::build(self.status_code());
+        if self.code == ErrorCode::RateLimited {
+            builder.insert_header((header::RETRY_AFTER, RATE_LIMIT_RETRY_AFTER.to_string()));
+        }
+        if self.code == ErrorCode::Unauthorized {
+            builder.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        builder.json(self.api_response(Lang::default()))
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct ApiResponse<T> {
     pub code: String,
     pub msg: String,
@@ -330,11 +2342,10 @@ impl<T: Serialize> Responder for ApiResponse<T> {
                 };
                 let error_json = serde_json::to_string(&error_response).unwrap_or_else(|_| {
                     // 如果再次序列化失败，返回一个固定的备用错误信息
-                    return format!(
+                    format!(
                         r#"{{"code":"{}","msg":"{}","data":null}}"#,
-                        ERR_SERDE_CODE,
-                        "格式化json错误!".to_string()
-                    );
+                        ERR_SERDE_CODE, "格式化json错误!"
+                    )
                 });
 
                 HttpResponse::Ok()
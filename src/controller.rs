@@ -15,35 +15,966 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::constants::{ApiResponse, AppError, DnsQuery, DnsResult, DnsSecQuery, DnsSecResult};
-use actix_web::{HttpResponse, web};
+use crate::constants::{
+    ApiResponse, AppError, BulkItem, BulkJob, BulkProgress, BulkReportQuery, BulkRequest,
+    BulkTextQuery, DnsQuery, DnsResult, DnsSecQuery, DnsSecResult, DnsStreamQuery, DnsStreamResult,
+    DnsSubnetQuery, DnsSubnetResult, DnsSubnetStreamQuery, DnsUpdateRequest, DnsUpdateResult,
+    DnssecDiagnosis, DnssecHealthQuery, DnssecHealthReport, ErrorCode, ExportHistoryQuery,
+    ExportQuery, ExportResult, HistoryQuery, HistoryRecord, MonitorChange, MonitorChangesQuery,
+    MonitorDetail, MonitorJob, MonitorRequest, NotifyReport, NotifyRequest, PermalinkQuery,
+    QueryOptions, ReadyReport, ResolverGroupList, ResolverStatus, SelftestReport, TrustAnchorList,
+    UpdateAuditQuery, UpdateAuditRecord, ZoneSyncQuery, ZoneSyncReport,
+};
+use actix_web::http::header;
+use actix_web::mime;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError, web};
+use std::time::Instant;
 
-use crate::dns;
+use crate::bulk::BulkStore;
+use crate::export::ExportFormat;
+use crate::history::{HistoryStore, NewHistory};
+use crate::i18n::Lang;
+use crate::monitor::MonitorStore;
+use crate::resolver::Resolver;
+use crate::{
+    anchor, bulk, constants, dns, dnssec, export, group, health, history, i18n, notify, openapi,
+    permalink, retry, sse, update, zonesync,
+};
 use tera::Tera;
 
 //index
-pub async fn index(tera: web::Data<Tera>) -> Result<HttpResponse, AppError> {
-    let context = tera::Context::new();
+pub async fn index(req: HttpRequest, tera: web::Data<Tera>) -> Result<HttpResponse, AppError> {
+    let context = i18n::context(Lang::from_headers(req.headers()));
     match tera.render("index.html", &context) {
         Ok(template) => Ok(HttpResponse::Ok().body(template)),
         Err(e) => Err(AppError::tera(e.into())),
     }
 }
 
-//query
-pub async fn v1_query(request: web::Json<DnsQuery>) -> Result<ApiResponse<DnsResult>, AppError> {
-    let query_result = dns::v1_query(request.into_inner()).await;
-    match query_result {
-        Ok(dns_query) => Ok(ApiResponse::okay(dns_query)),
-        Err(e) => Err(e),
+//monitors page
+pub async fn monitors(
+    req: HttpRequest,
+    tera: web::Data<Tera>,
+    store: web::Data<MonitorStore>,
+) -> Result<HttpResponse, AppError> {
+    let mut context = i18n::context(Lang::from_headers(req.headers()));
+    context.insert("monitors", &store.list());
+    context.insert("changes", &store.changes(constants::MONITOR_CHANGES_LIMIT));
+    match tera.render(constants::MONITORS_HTML, &context) {
+        Ok(template) => Ok(HttpResponse::Ok().body(template)),
+        Err(e) => Err(AppError::tera(e.into())),
+    }
+}
+
+//bulk page
+pub async fn bulk_page(req: HttpRequest, tera: web::Data<Tera>) -> Result<HttpResponse, AppError> {
+    let context = i18n::context(Lang::from_headers(req.headers()));
+    match tera.render(constants::BULK_HTML, &context) {
+        Ok(template) => Ok(HttpResponse::Ok().body(template)),
+        Err(e) => Err(AppError::tera(e.into())),
     }
 }
 
+//DNS UPDATE page
+pub async fn update_page(
+    req: HttpRequest,
+    tera: web::Data<Tera>,
+) -> Result<HttpResponse, AppError> {
+    let context = i18n::context(Lang::from_headers(req.headers()));
+    match tera.render(constants::UPDATE_HTML, &context) {
+        Ok(template) => Ok(HttpResponse::Ok().body(template)),
+        Err(e) => Err(AppError::tera(e.into())),
+    }
+}
+
+//zone sync page
+pub async fn zone_sync_page(
+    req: HttpRequest,
+    tera: web::Data<Tera>,
+) -> Result<HttpResponse, AppError> {
+    let context = i18n::context(Lang::from_headers(req.headers()));
+    match tera.render(constants::ZONE_SYNC_HTML, &context) {
+        Ok(template) => Ok(HttpResponse::Ok().body(template)),
+        Err(e) => Err(AppError::tera(e.into())),
+    }
+}
+
+//permalink page, 不存在或过期交给 error_handler 渲染 404
+pub async fn permalink(
+    req: HttpRequest,
+    tera: web::Data<Tera>,
+    history: web::Data<HistoryStore>,
+    share_id: web::Path<String>,
+    query: web::Query<PermalinkQuery>,
+) -> Result<HttpResponse, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    let Some(view) = permalink::load(&history, &share_id, query.rerun, lang).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let mut context = i18n::context(lang);
+    context.insert("view", &view);
+    match tera.render(constants::PERMALINK_HTML, &context) {
+        Ok(template) => Ok(HttpResponse::Ok().body(template)),
+        Err(e) => Err(AppError::tera(e.into())),
+    }
+}
+
+//请求方地址
+fn requester(req: &HttpRequest) -> String {
+    req.connection_info()
+        .realip_remote_addr()
+        .unwrap_or("-")
+        .to_string()
+}
+
+//查询并保存历史
+async fn query_and_save(
+    history: &HistoryStore,
+    requester: String,
+    dns_query: DnsQuery,
+    lang: Lang,
+) -> Result<DnsResult, AppError> {
+    let entry = NewHistory {
+        requester,
+        kind: constants::HISTORY_KIND_QUERY,
+        domain: dns_query.domain.clone(),
+        record_type: dns_query.record_type.clone(),
+        dns_server: dns_query.dns_server.clone(),
+        client_subnet: dns_query.client_subnet.clone(),
+    };
+    let started = Instant::now();
+    let query_result = dns::v1_query(dns_query, lang).await;
+    let share_id = history.save(entry, started.elapsed(), &query_result).await;
+    query_result.map(|dns_query| DnsResult {
+        share_id,
+        ..dns_query
+    })
+}
+
+async fn query_dnssec_and_save(
+    history: &HistoryStore,
+    requester: String,
+    dns_sec_query: DnsSecQuery,
+    lang: Lang,
+) -> Result<DnsSecResult, AppError> {
+    let entry = NewHistory {
+        requester,
+        kind: constants::HISTORY_KIND_DNSSEC,
+        domain: dns_sec_query.domain.clone(),
+        record_type: dns_sec_query.record_type.clone(),
+        dns_server: dns_sec_query.dns_server.clone(),
+        client_subnet: None,
+    };
+    let started = Instant::now();
+    let query_result = dns::v1_query_dnssec(dns_sec_query, lang).await;
+    let share_id = history.save(entry, started.elapsed(), &query_result).await;
+    query_result.map(|dns_query| DnsSecResult {
+        share_id,
+        ..dns_query
+    })
+}
+
+//query, ?format= 可以导出为 csv / json / zone / dig, Accept 头可以指定 csv / zone
+#[utoipa::path(
+    post,
+    path = "/v1/query",
+    tag = "query",
+    summary = "查询 dns 记录",
+    description = "?format= 可以导出为 csv / json / zone / dig, Accept 头可以指定 text/csv / text/dns, dnsServer 为解析器组名时按组策略查询, group 为各成员的结果",
+    request_body = DnsQuery,
+    params(ExportQuery),
+    responses((status = 200, content(
+        (ApiResponse<DnsResult> = "application/json"),
+        (String = "text/csv"),
+        (String = "text/dns"),
+        (String = "text/plain"),
+    )))
+)]
+pub async fn v1_query(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    request: web::Json<DnsQuery>,
+    export_query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let format = ExportFormat::from_request(&req, export_query.format.as_deref())?;
+    let lang = Lang::from_headers(req.headers());
+    let domain = request.domain.clone();
+    let started = Instant::now();
+    let result = query_and_save(&history, requester(&req), request.into_inner(), lang).await?;
+    if format == ExportFormat::Envelope {
+        return Ok(ApiResponse::okay(result).respond_to(&req));
+    }
+    let exported =
+        export::from_dns_result(&domain, &result, Some(started.elapsed().as_millis() as u64));
+    Ok(export::render(format, &result, &[exported]))
+}
+
 //query_dnssec
-pub async fn v1_query_dnssec(request: web::Json<DnsSecQuery>) -> Result<ApiResponse<DnsSecResult>, AppError> {
-    let query_result = dns::v1_query_dnssec(request.into_inner()).await;
-    match query_result {
-        Ok(dns_query) => Ok(ApiResponse::okay(dns_query)),
-        Err(e) => Err(e),
+#[utoipa::path(
+    post,
+    path = "/v1/query_dnssec",
+    tag = "query",
+    summary = "查询 dns 记录并验证 dnssec",
+    description = "NXDOMAIN / NODATA 时 denial 给出权威段中的 NSEC / NSEC3 记录以及否定证明是否完整",
+    request_body = DnsSecQuery,
+    params(ExportQuery),
+    responses((status = 200, content(
+        (ApiResponse<DnsSecResult> = "application/json"),
+        (String = "text/csv"),
+        (String = "text/dns"),
+        (String = "text/plain"),
+    )))
+)]
+pub async fn v1_query_dnssec(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    request: web::Json<DnsSecQuery>,
+    export_query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let format = ExportFormat::from_request(&req, export_query.format.as_deref())?;
+    let lang = Lang::from_headers(req.headers());
+    let (domain, record_type) = (request.domain.clone(), request.record_type.clone());
+    let started = Instant::now();
+    let result =
+        query_dnssec_and_save(&history, requester(&req), request.into_inner(), lang).await?;
+    if format == ExportFormat::Envelope {
+        return Ok(ApiResponse::okay(result).respond_to(&req));
+    }
+    let exported = export::from_dns_sec_result(
+        &domain,
+        &record_type,
+        &result,
+        Some(started.elapsed().as_millis() as u64),
+    );
+    Ok(export::render(format, &result, &[exported]))
+}
+
+//dnssec 诊断, 对比 CD=0 / CD=1 的应答
+#[utoipa::path(
+    post,
+    path = "/v1/diagnose_dnssec",
+    tag = "query",
+    summary = "判断 SERVFAIL 是否由 dnssec 验证失败引起",
+    description = "分别以 CD=0 / CD=1 查询并用 DnssecClient 本地验证, status 为 HEALTHY / DNSSEC_BOGUS / BROKEN",
+    request_body = DnsSecQuery,
+    responses((status = 200, body = ApiResponse<DnssecDiagnosis>))
+)]
+pub async fn v1_diagnose_dnssec(
+    req: HttpRequest,
+    request: web::Json<DnsSecQuery>,
+) -> Result<ApiResponse<DnssecDiagnosis>, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    dnssec::diagnose(request.into_inner(), lang)
+        .await
+        .map(ApiResponse::okay)
+}
+
+//各 NS 的 IPv4 / IPv6 地址的 SOA serial 是否一致
+#[utoipa::path(
+    post,
+    path = "/v1/zone_sync",
+    tag = "query",
+    summary = "检查区域同步",
+    description = "通过 dnsServer 查询 NS 与各 NS 的 A / AAAA, 再直接向每个地址查询 SOA, behind 为 serial 落后于最大 serial",
+    request_body = ZoneSyncQuery,
+    responses((status = 200, body = ApiResponse<ZoneSyncReport>))
+)]
+pub async fn v1_zone_sync(
+    req: HttpRequest,
+    query: web::Json<ZoneSyncQuery>,
+) -> Result<ApiResponse<ZoneSyncReport>, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    zonesync::check(query.into_inner(), lang)
+        .await
+        .map(ApiResponse::okay)
+}
+
+//dnssec 签名过期与密钥轮换检查
+#[utoipa::path(
+    post,
+    path = "/v1/dnssec_health",
+    tag = "query",
+    summary = "检查区域的 DNSKEY / DS / RRSIG",
+    description = "签名剩余天数、KSK / ZSK、DS 与 DNSKEY 是否匹配、算法轮换, status 为 OK / WARNING / CRITICAL / UNSIGNED",
+    request_body = DnssecHealthQuery,
+    responses((status = 200, body = ApiResponse<DnssecHealthReport>))
+)]
+pub async fn v1_dnssec_health(
+    req: HttpRequest,
+    request: web::Json<DnssecHealthQuery>,
+) -> Result<ApiResponse<DnssecHealthReport>, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    dnssec::health(request.into_inner(), lang)
+        .await
+        .map(ApiResponse::okay)
+}
+
+//配置的信任锚与否定信任锚
+#[utoipa::path(
+    get,
+    path = "/v1/trust_anchors",
+    tag = "query",
+    summary = "信任锚列表",
+    description = "TRUST_ANCHOR_FILE 中的 DS / DNSKEY 与 NEGATIVE_TRUST_ANCHORS, errors 为无法解析的条目",
+    responses((status = 200, body = ApiResponse<TrustAnchorList>))
+)]
+pub async fn v1_trust_anchors() -> ApiResponse<TrustAnchorList> {
+    ApiResponse::okay(anchor::ANCHORS.list())
+}
+
+//配置的解析器组, 组名可以作为 dnsServer 查询
+#[utoipa::path(
+    get,
+    path = "/v1/resolver_groups",
+    tag = "query",
+    summary = "解析器组列表",
+    description = "RESOLVER_GROUPS 中的解析器组与策略 (failover / round-robin / race / quorum), errors 为无法解析的条目",
+    responses((status = 200, body = ApiResponse<ResolverGroupList>))
+)]
+pub async fn v1_resolver_groups() -> ApiResponse<ResolverGroupList> {
+    ApiResponse::okay(group::GROUPS.list())
+}
+
+//逗号分隔的列表
+fn split_list(value: &str, max: usize, empty: ErrorCode) -> Result<Vec<String>, AppError> {
+    let list: Vec<String> = value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if list.is_empty() {
+        return Err(AppError::new(empty));
+    }
+    if list.len() > max {
+        return Err(AppError::new(ErrorCode::TooManyItems).arg("max", max));
+    }
+    Ok(list)
+}
+
+fn stream_servers(query: &DnsStreamQuery) -> Result<Vec<String>, AppError> {
+    dns::check_domain_and_type(&query.domain, &query.record_type)?;
+    let servers = split_list(
+        &query.dns_servers,
+        constants::MAX_STREAM_SERVERS,
+        ErrorCode::DnsServerEmpty,
+    )?;
+    for server in &servers {
+        dns::resolve_dns_addr(server)?;
+    }
+    if let Some(client_subnet) = query.client_subnet.as_deref().map(str::trim)
+        && !client_subnet.is_empty()
+    {
+        dns::check_client_subnet(client_subnet)?;
+    }
+    retry::check_options(&QueryOptions {
+        timeout_ms: query.timeout_ms,
+        retries: query.retries,
+        backoff_ms: query.backoff_ms,
+    })?;
+    Ok(servers)
+}
+
+//query 流式版本, 每个 dns 服务器返回后立即推送
+#[utoipa::path(
+    get,
+    path = "/v1/query/stream",
+    tag = "query",
+    summary = "流式查询多个 dns 服务器",
+    description = "每个 dns 服务器返回后推送一条 DnsStreamResult<DnsResult>",
+    params(DnsStreamQuery),
+    responses((status = 200, body = DnsStreamResult<DnsResult>, content_type = "text/event-stream"))
+)]
+pub async fn v1_query_stream(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    query: web::Query<DnsStreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    //参数错误在事件流开始前返回, 与其他接口一样是 problem 响应
+    let servers = stream_servers(&query)?;
+    let requester = requester(&req);
+    let tasks: Vec<_> = servers
+        .into_iter()
+        .enumerate()
+        .map(|(index, dns_server)| {
+            let history = history.clone();
+            let dns_query = DnsQuery {
+                domain: query.domain.clone(),
+                record_type: query.record_type.clone(),
+                dns_server: dns_server.clone(),
+                client_subnet: query.client_subnet.clone().filter(|s| !s.trim().is_empty()),
+                options: QueryOptions {
+                    timeout_ms: query.timeout_ms,
+                    retries: query.retries,
+                    backoff_ms: query.backoff_ms,
+                },
+            };
+            let requester = requester.clone();
+            async move {
+                let result = query_and_save(&history, requester, dns_query, lang).await;
+                DnsStreamResult::new(index, dns_server, None, result, lang)
+            }
+        })
+        .collect();
+    Ok(sse::spawn_all(tasks))
+}
+
+//query_dnssec 流式版本
+#[utoipa::path(
+    get,
+    path = "/v1/query_dnssec/stream",
+    tag = "query",
+    summary = "流式查询多个 dns 服务器并验证 dnssec",
+    description = "每个 dns 服务器返回后推送一条 DnsStreamResult<DnsSecResult>",
+    params(DnsStreamQuery),
+    responses((status = 200, body = DnsStreamResult<DnsSecResult>, content_type = "text/event-stream"))
+)]
+pub async fn v1_query_dnssec_stream(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    query: web::Query<DnsStreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    //参数错误在事件流开始前返回, 与其他接口一样是 problem 响应
+    let servers = stream_servers(&query)?;
+    let requester = requester(&req);
+    let tasks: Vec<_> = servers
+        .into_iter()
+        .enumerate()
+        .map(|(index, dns_server)| {
+            let history = history.clone();
+            let dns_sec_query = DnsSecQuery {
+                domain: query.domain.clone(),
+                dns_server: dns_server.clone(),
+                record_type: query.record_type.clone(),
+            };
+            let requester = requester.clone();
+            async move {
+                let result = query_dnssec_and_save(&history, requester, dns_sec_query, lang).await;
+                DnsStreamResult::new(index, dns_server, None, result, lang)
+            }
+        })
+        .collect();
+    Ok(sse::spawn_all(tasks))
+}
+
+//query_subnets 流式版本, 每个子网返回后立即推送
+#[utoipa::path(
+    get,
+    path = "/v1/query_subnets/stream",
+    tag = "query",
+    summary = "流式对比多个 ECS 子网",
+    description = "每个子网返回后推送一条 DnsStreamResult<DnsResult>",
+    params(DnsSubnetStreamQuery),
+    responses((status = 200, body = DnsStreamResult<DnsResult>, content_type = "text/event-stream"))
+)]
+pub async fn v1_query_subnets_stream(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    query: web::Query<DnsSubnetStreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let lang = Lang::from_headers(req.headers());
+    dns::check_domain_and_type(&query.domain, &query.record_type)?;
+    dns::resolve_dns_addr(&query.dns_server)?;
+    let subnets = split_list(
+        &query.client_subnets,
+        constants::MAX_COMPARE_SUBNETS,
+        ErrorCode::ClientSubnetEmpty,
+    )?;
+    for client_subnet in &subnets {
+        dns::check_client_subnet(client_subnet)?;
     }
-}
\ No newline at end of file
+    let requester = requester(&req);
+    let tasks: Vec<_> = subnets
+        .into_iter()
+        .enumerate()
+        .map(|(index, client_subnet)| {
+            let history = history.clone();
+            let dns_query = DnsQuery {
+                domain: query.domain.clone(),
+                record_type: query.record_type.clone(),
+                dns_server: query.dns_server.clone(),
+                client_subnet: Some(client_subnet.clone()),
+                options: QueryOptions::default(),
+            };
+            let dns_server = query.dns_server.clone();
+            let requester = requester.clone();
+            async move {
+                let result = query_and_save(&history, requester, dns_query, lang).await;
+                DnsStreamResult::new(index, dns_server, Some(client_subnet), result, lang)
+            }
+        })
+        .collect();
+    Ok(sse::spawn_all(tasks))
+}
+
+//query_subnets, 多个 ECS 子网对比
+#[utoipa::path(
+    post,
+    path = "/v1/query_subnets",
+    tag = "query",
+    summary = "对比多个 ECS 子网的查询结果",
+    request_body = DnsSubnetQuery,
+    responses((status = 200, body = ApiResponse<Vec<DnsSubnetResult>>))
+)]
+pub async fn v1_query_subnets(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    request: web::Json<DnsSubnetQuery>,
+) -> Result<ApiResponse<Vec<DnsSubnetResult>>, AppError> {
+    let dns_subnet_query = request.into_inner();
+    let requester = requester(&req);
+    let (domain, record_type, dns_server) = (
+        dns_subnet_query.domain.clone(),
+        dns_subnet_query.record_type.clone(),
+        dns_subnet_query.dns_server.clone(),
+    );
+    let lang = Lang::from_headers(req.headers());
+    let started = Instant::now();
+    let results = dns::v1_query_subnets(dns_subnet_query, lang).await?;
+    //各子网并发查询, 耗时记为整体耗时
+    let mut subnet_results = Vec::with_capacity(results.len());
+    for (client_subnet, result) in results {
+        let entry = NewHistory {
+            requester: requester.clone(),
+            kind: constants::HISTORY_KIND_QUERY,
+            domain: domain.clone(),
+            record_type: record_type.clone(),
+            dns_server: dns_server.clone(),
+            client_subnet: Some(client_subnet.clone()),
+        };
+        let share_id = history.save(entry, started.elapsed(), &result).await;
+        let result = result.map(|dns_result| DnsResult {
+            share_id,
+            ..dns_result
+        });
+        subnet_results.push(DnsSubnetResult::new(client_subnet, result, lang));
+    }
+    Ok(ApiResponse::okay(subnet_results))
+}
+
+//monitors
+#[utoipa::path(
+    get,
+    path = "/v1/monitors",
+    tag = "monitor",
+    summary = "监控列表",
+    responses((status = 200, body = ApiResponse<Vec<MonitorJob>>))
+)]
+pub async fn v1_monitor_list(
+    store: web::Data<MonitorStore>,
+) -> Result<ApiResponse<Vec<MonitorJob>>, AppError> {
+    let monitors = store.list();
+    Ok(ApiResponse::ok_page(monitors.len() as i64, monitors))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/monitors",
+    tag = "monitor",
+    summary = "创建监控",
+    request_body = MonitorRequest,
+    responses((status = 200, body = ApiResponse<MonitorJob>))
+)]
+pub async fn v1_monitor_create(
+    store: web::Data<MonitorStore>,
+    request: web::Json<MonitorRequest>,
+) -> Result<ApiResponse<MonitorJob>, AppError> {
+    store.create(request.into_inner()).map(ApiResponse::okay)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/monitors/{id}",
+    tag = "monitor",
+    summary = "监控详情, 包含最近的查询结果与变更",
+    params(("id" = u64, Path)),
+    responses((status = 200, body = ApiResponse<MonitorDetail>))
+)]
+pub async fn v1_monitor_detail(
+    store: web::Data<MonitorStore>,
+    id: web::Path<u64>,
+) -> Result<ApiResponse<MonitorDetail>, AppError> {
+    store.detail(id.into_inner()).map(ApiResponse::okay)
+}
+
+#[utoipa::path(
+    put,
+    path = "/v1/monitors/{id}",
+    tag = "monitor",
+    summary = "修改监控",
+    params(("id" = u64, Path)),
+    request_body = MonitorRequest,
+    responses((status = 200, body = ApiResponse<MonitorJob>))
+)]
+pub async fn v1_monitor_update(
+    store: web::Data<MonitorStore>,
+    id: web::Path<u64>,
+    request: web::Json<MonitorRequest>,
+) -> Result<ApiResponse<MonitorJob>, AppError> {
+    store
+        .update(id.into_inner(), request.into_inner())
+        .map(ApiResponse::okay)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/monitors/{id}",
+    tag = "monitor",
+    summary = "删除监控",
+    params(("id" = u64, Path)),
+    responses((status = 200, body = ApiResponse<Option<String>>))
+)]
+pub async fn v1_monitor_delete(
+    store: web::Data<MonitorStore>,
+    id: web::Path<u64>,
+) -> Result<ApiResponse<()>, AppError> {
+    store.delete(id.into_inner()).map(ApiResponse::okay)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/monitors/changes",
+    tag = "monitor",
+    summary = "全部监控的变更事件",
+    params(MonitorChangesQuery),
+    responses((status = 200, body = ApiResponse<Vec<MonitorChange>>))
+)]
+pub async fn v1_monitor_changes(
+    store: web::Data<MonitorStore>,
+    query: web::Query<MonitorChangesQuery>,
+) -> Result<ApiResponse<Vec<MonitorChange>>, AppError> {
+    let limit = query.limit.unwrap_or(constants::MONITOR_CHANGES_LIMIT);
+    let changes = store.changes(limit);
+    Ok(ApiResponse::ok_page(changes.len() as i64, changes))
+}
+
+//按分享链接 id 导出保存的结果, 下载文件, 默认 csv
+#[utoipa::path(
+    get,
+    path = "/v1/export",
+    tag = "export",
+    summary = "按分享链接 id 导出保存的结果",
+    description = "下载文件, 默认 csv",
+    params(ExportHistoryQuery),
+    responses((status = 200, content(
+        (String = "text/csv"),
+        (Vec<ExportResult> = "application/json"),
+        (String = "text/dns"),
+        (String = "text/plain"),
+    )))
+)]
+pub async fn v1_export(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    query: web::Query<ExportHistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let format = match ExportFormat::from_request(&req, query.format.as_deref())? {
+        ExportFormat::Envelope => ExportFormat::Csv,
+        format => format,
+    };
+    let share_ids = split_list(
+        &query.share_ids,
+        constants::HISTORY_MAX_PAGE_SIZE as usize,
+        ErrorCode::ShareIdEmpty,
+    )?;
+    let mut results = Vec::with_capacity(share_ids.len());
+    for share_id in &share_ids {
+        let record = history
+            .find_by_share_id(share_id)
+            .await?
+            .ok_or_else(|| AppError::new(ErrorCode::ShareNotFound).arg("shareId", share_id))?;
+        results.push(export::from_history(&record));
+    }
+    let filename = format!("dns-{}.{}", results[0].domain, format.extension());
+    let mut response = export::render(format, &results, &results);
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        header::HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+            .map_err(AppError::internal)?,
+    );
+    Ok(response)
+}
+
+//history
+#[utoipa::path(
+    get,
+    path = "/v1/history",
+    tag = "history",
+    summary = "查询历史",
+    params(HistoryQuery),
+    responses((status = 200, body = ApiResponse<Vec<HistoryRecord>>))
+)]
+pub async fn v1_history(
+    history: web::Data<HistoryStore>,
+    query: web::Query<HistoryQuery>,
+) -> Result<ApiResponse<Vec<HistoryRecord>>, AppError> {
+    let (total, records) = history.search(&query).await?;
+    Ok(ApiResponse::ok_page(total, records))
+}
+
+//新保存的历史, text/event-stream, 包括本地转发解析器的查询
+#[utoipa::path(
+    get,
+    path = "/v1/history/stream",
+    tag = "history",
+    summary = "实时查询历史",
+    description = "每保存一条历史推送一条 history 事件",
+    responses((status = 200, body = HistoryRecord, content_type = "text/event-stream"))
+)]
+pub async fn v1_history_stream(history: web::Data<HistoryStore>) -> HttpResponse {
+    sse::response(history::live_stream(history.subscribe()))
+}
+
+//本地转发解析器
+#[utoipa::path(
+    get,
+    path = "/v1/resolver",
+    tag = "history",
+    summary = "转发解析器状态",
+    description = "RESOLVER_PORT 不为 0 时监听 UDP/TCP, 按 RESOLVER_UPSTREAMS 顺序转发, 包括每个上游的统计与缓存命中",
+    responses((status = 200, body = ApiResponse<ResolverStatus>))
+)]
+pub async fn v1_resolver(resolver: web::Data<Resolver>) -> ApiResponse<ResolverStatus> {
+    ApiResponse::okay(resolver.status())
+}
+
+//bulk, application/json 提交 BulkRequest, 其他按文本/csv 每行一个域名
+#[utoipa::path(
+    post,
+    path = "/v1/bulk",
+    tag = "bulk",
+    summary = "创建批量查询",
+    description = "application/json 提交 BulkRequest, 其他按文本/csv 每行一个域名",
+    params(BulkTextQuery),
+    request_body(content(
+        (BulkRequest = "application/json"),
+        (String = "text/plain"),
+        (String = "text/csv"),
+    )),
+    responses((status = 200, body = ApiResponse<BulkProgress>))
+)]
+pub async fn v1_bulk_create(
+    req: HttpRequest,
+    store: web::Data<BulkStore>,
+    query: web::Query<BulkTextQuery>,
+    body: web::Bytes,
+) -> Result<ApiResponse<BulkProgress>, AppError> {
+    let tasks = if req.content_type() == mime::APPLICATION_JSON.essence_str() {
+        let request: BulkRequest = serde_json::from_slice(&body)
+            .map_err(|e| AppError::new(ErrorCode::RequestInvalid).arg("detail", e))?;
+        bulk::tasks_from_request(&request)?
+    } else {
+        let text = std::str::from_utf8(&body).map_err(|_| AppError::new(ErrorCode::BulkNotUtf8))?;
+        bulk::tasks_from_text(text, &query)?
+    };
+    let lang = Lang::from_headers(req.headers());
+    bulk::start(store, tasks, lang).map(ApiResponse::okay)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/bulk/{id}",
+    tag = "bulk",
+    summary = "批量查询详情",
+    params(("id" = u64, Path)),
+    responses((status = 200, body = ApiResponse<BulkJob>))
+)]
+pub async fn v1_bulk_detail(
+    store: web::Data<BulkStore>,
+    id: web::Path<u64>,
+) -> Result<ApiResponse<BulkJob>, AppError> {
+    store.job(id.into_inner()).map(ApiResponse::okay)
+}
+
+//进度推送, text/event-stream
+#[utoipa::path(
+    get,
+    path = "/v1/bulk/{id}/events",
+    tag = "bulk",
+    summary = "批量查询进度推送",
+    params(("id" = u64, Path)),
+    responses((status = 200, body = BulkProgress, content_type = "text/event-stream"))
+)]
+pub async fn v1_bulk_events(
+    store: web::Data<BulkStore>,
+    id: web::Path<u64>,
+) -> Result<HttpResponse, AppError> {
+    let receiver = store.subscribe(id.into_inner())?;
+    Ok(sse::response(bulk::progress_stream(receiver)))
+}
+
+//下载报告, ?format=csv|json
+#[utoipa::path(
+    get,
+    path = "/v1/bulk/{id}/report",
+    tag = "bulk",
+    summary = "下载批量查询报告",
+    params(("id" = u64, Path), BulkReportQuery),
+    responses((status = 200, content(
+        (String = "text/csv"),
+        (Vec<BulkItem> = "application/json"),
+    )))
+)]
+pub async fn v1_bulk_report(
+    store: web::Data<BulkStore>,
+    id: web::Path<u64>,
+    query: web::Query<BulkReportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let job = store.job(id)?;
+    let (content_type, extension, body) = match query.format.as_deref().unwrap_or("csv") {
+        "csv" => ("text/csv; charset=utf-8", "csv", bulk::report_csv(&job)),
+        "json" => (
+            constants::CONTENT_TYPE_JSON_VALUE,
+            "json",
+            serde_json::to_string_pretty(&job.items).unwrap_or_default(),
+        ),
+        format => {
+            return Err(AppError::new(ErrorCode::FormatUnsupported).arg("format", format));
+        }
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"bulk-{}.{}\"", id, extension),
+        ))
+        .body(body))
+}
+
+//DNS UPDATE, 需要 Authorization: Bearer <UPDATE_TOKEN>, 使用 TSIG_KEYS 中的密钥签名
+#[utoipa::path(
+    post,
+    path = "/v1/update",
+    tag = "update",
+    summary = "DNS UPDATE",
+    description = "RFC 2136 动态更新, action 为 add / delete / replace, 可带前提条件, 每次请求写入审计日志",
+    request_body = DnsUpdateRequest,
+    responses((status = 200, body = ApiResponse<DnsUpdateResult>)),
+    security(("bearerAuth" = []))
+)]
+pub async fn v1_update(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    request: web::Json<DnsUpdateRequest>,
+) -> Result<ApiResponse<DnsUpdateResult>, AppError> {
+    update::authorize(&req)?;
+    update::update(&history, &requester(&req), request.into_inner())
+        .await
+        .map(ApiResponse::okay)
+}
+
+//NOTIFY, 需要 Authorization: Bearer <UPDATE_TOKEN>, 返回时全部从服务器已同步或超过 deadlineMs
+#[utoipa::path(
+    post,
+    path = "/v1/notify",
+    tag = "update",
+    summary = "发送 NOTIFY",
+    description = "向从服务器发送 NOTIFY (RFC 1996) 并报告应答码, 再轮询各自的 SOA 直到 serial 与主服务器一致或超过 deadlineMs",
+    request_body = NotifyRequest,
+    responses((status = 200, body = ApiResponse<NotifyReport>)),
+    security(("bearerAuth" = []))
+)]
+pub async fn v1_notify(
+    req: HttpRequest,
+    request: web::Json<NotifyRequest>,
+) -> Result<ApiResponse<NotifyReport>, AppError> {
+    update::authorize(&req)?;
+    let lang = Lang::from_headers(req.headers());
+    notify::notify(request.into_inner(), lang)
+        .await
+        .map(ApiResponse::okay)
+}
+
+//可用的 TSIG 密钥名称与算法, 不包括密钥
+#[utoipa::path(
+    get,
+    path = "/v1/update/keys",
+    tag = "update",
+    summary = "TSIG 密钥",
+    responses((status = 200, body = ApiResponse<Vec<String>>)),
+    security(("bearerAuth" = []))
+)]
+pub async fn v1_update_keys(req: HttpRequest) -> Result<ApiResponse<Vec<String>>, AppError> {
+    update::authorize(&req)?;
+    Ok(ApiResponse::okay(update::KEYS.names()))
+}
+
+//审计日志, 按时间倒序
+#[utoipa::path(
+    get,
+    path = "/v1/update/audit",
+    tag = "update",
+    summary = "DNS UPDATE 审计日志",
+    params(UpdateAuditQuery),
+    responses((status = 200, body = ApiResponse<Vec<UpdateAuditRecord>>)),
+    security(("bearerAuth" = []))
+)]
+pub async fn v1_update_audit(
+    req: HttpRequest,
+    history: web::Data<HistoryStore>,
+    query: web::Query<UpdateAuditQuery>,
+) -> Result<ApiResponse<Vec<UpdateAuditRecord>>, AppError> {
+    update::authorize(&req)?;
+    let (total, records) = history.audits(&query).await?;
+    Ok(ApiResponse::ok_page(total, records))
+}
+
+//openapi 文档, 由接口与类型定义生成
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(constants::CONTENT_TYPE_JSON_VALUE)
+        .body(openapi::OPENAPI_JSON.as_str())
+}
+
+//存活检查, 不依赖模板与 dns
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    summary = "存活检查",
+    responses((status = 200, body = ApiResponse<String>))
+)]
+pub async fn healthz() -> ApiResponse<String> {
+    ApiResponse::okay("ok".to_string())
+}
+
+//就绪检查, 模板、配置与 canary 查询都通过才返回 200
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    summary = "就绪检查",
+    description = "模板已加载、配置有效, 并且至少一个自检 dns 服务器 (SELFTEST_SERVERS, 默认为全部内置 dns 服务器) 在 READY_CANARY_TIMEOUT_MS 内应答 canary 查询",
+    responses(
+        (status = 200, body = ApiResponse<ReadyReport>),
+        (status = 503, description = "NOT_READY", body = ApiResponse<ReadyReport>),
+    )
+)]
+pub async fn readyz(req: HttpRequest, tera: web::Data<Tera>) -> HttpResponse {
+    let report = health::ready(&tera).await;
+    if report.ready {
+        return ApiResponse::okay(report).respond_to(&req);
+    }
+    let error = AppError::new(ErrorCode::NotReady).arg("detail", health::failed_checks(&report));
+    let lang = Lang::from_headers(req.headers());
+    HttpResponse::build(error.status_code()).json(ApiResponse::full(
+        error.code().to_string(),
+        error.localize(lang),
+        report,
+    ))
+}
+
+//自检, 对每个自检 dns 服务器查询已知答案的域名
+#[utoipa::path(
+    get,
+    path = "/v1/selftest",
+    tag = "health",
+    summary = "dns 服务器自检",
+    description = "对 SELFTEST_SERVERS (默认为全部内置 dns 服务器) 查询 SELFTEST_DOMAIN, 返回是否可达以及应答是否包含 SELFTEST_EXPECTED",
+    responses((status = 200, body = ApiResponse<SelftestReport>))
+)]
+pub async fn v1_selftest(req: HttpRequest) -> ApiResponse<SelftestReport> {
+    ApiResponse::okay(health::selftest(Lang::from_headers(req.headers())).await)
+}
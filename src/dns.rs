@@ -15,18 +15,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::anchor::Validation;
 use crate::constants;
 use crate::constants::{
-    AppError, DnsQuery, DnsRecordResult, DnsRecordSecResult, DnsResult, DnsSecQuery, DnsSecResult,
+    AliasChain, AppError, DnsQuery, DnsRecordResult, DnsRecordSecResult, DnsResult, DnsSecQuery,
+    DnsSecResult, DnsSubnetQuery, ErrorCode, GroupReport, QueryAttempt, QueryOptions,
 };
+use crate::i18n::Lang;
+use crate::retry::QueryPolicy;
+use crate::{anchor, chain, group, idn, nsec, retry};
+use futures_util::future;
 use hickory_client::client::{Client, ClientHandle, DnssecClient};
-use hickory_client::proto::rr::{DNSClass, Name, RecordType};
+use hickory_client::proto::DnsHandle;
+use hickory_client::proto::dnssec::Proof;
+use hickory_client::proto::op::{Edns, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_client::proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use hickory_client::proto::rr::{DNSClass, Name, Record, RecordType};
 use hickory_client::proto::runtime::TokioRuntimeProvider;
+use hickory_client::proto::tcp::TcpClientStream;
 use hickory_client::proto::udp::UdpClientStream;
+use hickory_client::proto::xfer::{DnsRequest, DnsRequestOptions, DnsResponse, FirstAnswer};
+use hickory_client::proto::{ProtoError, ProtoErrorKind};
+use hickory_client::{ClientError, ClientErrorKind};
 use log::info;
-use std::net::SocketAddr;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 fn get_record_type_from_str(record_type: String) -> Result<RecordType, AppError> {
     let record_type_upper = record_type.to_uppercase();
@@ -41,156 +56,681 @@ fn get_record_type_from_str(record_type: String) -> Result<RecordType, AppError>
             4 => RecordType::TXT,
             _ => unreachable!(),
         })
-        .ok_or_else(|| AppError::bus(format!("Unsupported record type: {}", record_type)))
+        .ok_or_else(|| {
+            AppError::new(ErrorCode::RecordTypeUnsupported).arg("recordType", record_type)
+        })
 }
 
-pub async fn v1_query_dnssec(dns_sec_query: DnsSecQuery) -> Result<DnsSecResult, AppError> {
-    let domain = &dns_sec_query.domain;
-    let record_type = &dns_sec_query.record_type;
-    let dns_server_box = &dns_sec_query.dns_server;
-    if domain.trim().is_empty() {
-        return Err(AppError::bus("域名不能为空!".to_string()));
-    }
+//校验客户端子网格式
+pub fn check_client_subnet(client_subnet: &str) -> Result<(), AppError> {
+    parse_client_subnet(client_subnet).map(|_| ())
+}
 
-    if !constants::DOMAIN_REG.is_match(domain) {
-        return Err(AppError::bus("域名格式不正确!".to_string()));
+//校验域名与记录类型
+pub fn check_domain_and_type(domain: &str, record_type: &str) -> Result<(), AppError> {
+    if domain.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::DomainEmpty));
     }
-
+    idn::normalize(domain)?;
     if record_type.trim().is_empty() {
-        return Err(AppError::bus("记录类型不能为空!".to_string()));
+        return Err(AppError::new(ErrorCode::RecordTypeEmpty));
     }
     if !constants::RECORD_TYPES.contains(&record_type.to_uppercase().as_str()) {
-        return Err(AppError::bus("不支持的记录类型!".to_string()));
+        return Err(AppError::new(ErrorCode::RecordTypeUnsupported).arg("recordType", record_type));
     }
+    Ok(())
+}
 
-    if dns_server_box.trim().is_empty() {
-        return Err(AppError::bus("dns供应商不能为空!".to_string()));
+//dns服务商 key 或者 ipv4, ipv4 可以带端口 => ipv4[:port], 解析器组原样返回
+pub fn resolve_dns_addr(dns_server: &str) -> Result<String, AppError> {
+    if group::GROUPS.find(dns_server).is_some() {
+        return Ok(dns_server.to_string());
     }
+    server_addr(dns_server)
+}
 
+//单个 dns 服务器, 不包括解析器组
+fn server_addr(dns_server: &str) -> Result<String, AppError> {
+    if dns_server.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::DnsServerEmpty));
+    }
     let dns_addr = constants::DNS_MAP
-        .get(dns_server_box)
-        .map(|v| v.clone())
-        .unwrap_or_else(|| dns_server_box.clone());
+        .get(dns_server)
+        .cloned()
+        .unwrap_or_else(|| dns_server.to_string());
+    let ip = match dns_addr.rsplit_once(':') {
+        Some((ip, port)) if port.parse::<u16>().is_ok_and(|port| port > 0) => ip,
+        _ => dns_addr.as_str(),
+    };
+    if !constants::V4_REG.is_match(ip) {
+        return Err(AppError::new(ErrorCode::DnsServerInvalid).arg("dnsServer", dns_server));
+    }
+    Ok(dns_addr)
+}
 
-    if !constants::V4_REG.is_match(&*dns_addr) {
-        return Err(AppError::bus("dns 服务器 ip地址不正确!".to_string()));
+//没有指定端口时使用 53, 解析器组没有单独的地址
+pub fn dns_socket_addr(dns_server: &str) -> Result<SocketAddr, AppError> {
+    let dns_addr = server_addr(dns_server)?;
+    let full_addr = if dns_addr.contains(':') {
+        dns_addr.clone()
+    } else {
+        format!("{}:53", dns_addr)
+    };
+    SocketAddr::from_str(&full_addr)
+        .map_err(|_| AppError::new(ErrorCode::DnsServerInvalid).arg("dnsServer", &dns_addr))
+}
+
+//解析 clientSubnet, 地址按前缀长度截断 (RFC 7871 要求超出前缀的位为 0)
+fn parse_client_subnet(client_subnet: &str) -> Result<ClientSubnet, AppError> {
+    let subnet = ClientSubnet::from_str(client_subnet.trim()).map_err(|_| {
+        AppError::new(ErrorCode::ClientSubnetInvalid).arg("clientSubnet", client_subnet)
+    })?;
+    let prefix = subnet.source_prefix();
+    let addr = match subnet.addr() {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+        }
+    };
+    Ok(ClientSubnet::new(addr, prefix, 0))
+}
+
+//查询请求, 有 subnet 时带 ECS option, 其余与 ClientHandle::query 一致
+fn query_request(name: Name, query_type: RecordType, subnet: Option<ClientSubnet>) -> DnsRequest {
+    let mut message = Message::new();
+    message
+        .add_query(Query::query(name, query_type))
+        .set_id(0) // UdpClientStream 发送时会重新生成随机 id
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true);
+    let edns = message.extensions_mut().get_or_insert_with(Edns::new);
+    edns.set_max_payload(hickory_client::proto::op::update_message::MAX_PAYLOAD_LEN)
+        .set_version(0);
+    if let Some(subnet) = subnet {
+        edns.options_mut().insert(EdnsOption::Subnet(subnet));
     }
+    DnsRequest::new(message, DnsRequestOptions::default())
+}
 
-    let full_addr = format!("{}:53", dns_addr);
-    info!("dns::v1_query_dnssec => full_addr: {}", full_addr);
+//按 dns 服务器配置的超时与重试查询
+pub async fn exchange(
+    dns_server: &str,
+    socket_addr: SocketAddr,
+    request: DnsRequest,
+) -> Result<DnsResponse, AppError> {
+    let policy = QueryPolicy::new(dns_server, &QueryOptions::default());
+    exchange_with(dns_server, socket_addr, request, &policy, &mut vec![]).await
+}
 
-    let socket_addr = SocketAddr::from_str(&full_addr)
-        .map_err(|_| AppError::bus(format!("无效的地址格式: {}", dns_addr)))?;
+//超时或连接失败时按 policy 等待后重试, 每次发送都记录到 attempts
+pub async fn exchange_with(
+    dns_server: &str,
+    socket_addr: SocketAddr,
+    request: DnsRequest,
+    policy: &QueryPolicy,
+    attempts: &mut Vec<QueryAttempt>,
+) -> Result<DnsResponse, AppError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        tokio::time::sleep(policy.delay(attempt)).await;
+        let result = exchange_once(
+            dns_server,
+            socket_addr,
+            request.clone(),
+            policy.timeout,
+            attempt,
+            attempts,
+        )
+        .await;
+        match result {
+            Ok(response) => return Ok(response),
+            Err(e) if attempt <= policy.retries && retryable(&e) => {
+                info!(
+                    "dns::exchange_with => attempt {} @{} failed: {}",
+                    attempt,
+                    socket_addr,
+                    e.msg()
+                );
+            }
+            Err(e) if attempt > 1 => {
+                return Err(AppError::new(ErrorCode::DnsAttemptsFailed)
+                    .arg("dnsServer", dns_server)
+                    .arg("attempts", attempt)
+                    .cause(e));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+//只重试超时与连接失败, 收到的应答 (包括 SERVFAIL) 不重试
+fn retryable(error: &AppError) -> bool {
+    error.code() == ErrorCode::DnsTimeout.as_str()
+        || error.code() == ErrorCode::DnsConnectFailed.as_str()
+}
 
+//UDP 查询, 应答被截断 (TC) 时改用 TCP 重新查询
+async fn exchange_once(
+    dns_server: &str,
+    socket_addr: SocketAddr,
+    request: DnsRequest,
+    timeout: Duration,
+    attempt: u32,
+    attempts: &mut Vec<QueryAttempt>,
+) -> Result<DnsResponse, AppError> {
+    let started = Instant::now();
     let conn = UdpClientStream::builder(socket_addr, TokioRuntimeProvider::default())
-        .with_timeout(Option::from(Duration::from_secs(*constants::QUERY_TIMEOUT)))
+        .with_timeout(Some(timeout))
         .build();
-    let (mut client, bg) = DnssecClient::connect(conn)
-        .await
-        .map_err(|e| AppError::bus(e.to_string()))?;
-    tokio::spawn(bg);
+    let result = match Client::connect(conn).await {
+        Ok((client, bg)) => {
+            tokio::spawn(bg);
+            client
+                .send(request.clone())
+                .first_answer()
+                .await
+                .map_err(|e| proto_error(dns_server, &e))
+        }
+        Err(e) => Err(proto_error(dns_server, &e)),
+    };
+    attempts.push(attempt_result(
+        dns_server, attempt, "udp", timeout, started, &result,
+    ));
+    let response = result?;
+    if !response.truncated() {
+        return Ok(response);
+    }
+
+    info!(
+        "dns::exchange => truncated, retry over tcp @{}",
+        socket_addr
+    );
+    let started = Instant::now();
+    let (stream, sender) = TcpClientStream::new(
+        socket_addr,
+        None,
+        Some(timeout),
+        TokioRuntimeProvider::default(),
+    );
+    let result = match Client::with_timeout(stream, sender, timeout, None).await {
+        Ok((client, bg)) => {
+            tokio::spawn(bg);
+            client
+                .send(request)
+                .first_answer()
+                .await
+                .map_err(|e| proto_error(dns_server, &e))
+        }
+        Err(e) => Err(proto_error(dns_server, &e)),
+    };
+    attempts.push(attempt_result(
+        dns_server, attempt, "tcp", timeout, started, &result,
+    ));
+    result
+}
+
+fn attempt_result(
+    dns_server: &str,
+    attempt: u32,
+    transport: &str,
+    timeout: Duration,
+    started: Instant,
+    result: &Result<DnsResponse, AppError>,
+) -> QueryAttempt {
+    let (outcome, error) = match result {
+        Ok(response) if response.truncated() => (constants::ATTEMPT_TRUNCATED, None),
+        Ok(_) => (constants::ATTEMPT_OK, None),
+        Err(e) if e.code() == ErrorCode::DnsTimeout.as_str() => {
+            (constants::ATTEMPT_TIMEOUT, Some(e.msg()))
+        }
+        Err(e) => (constants::ATTEMPT_ERROR, Some(e.msg())),
+    };
+    QueryAttempt {
+        dns_server: dns_server.to_string(),
+        attempt,
+        transport: transport.to_string(),
+        outcome: outcome.to_string(),
+        error,
+        duration_ms: started.elapsed().as_millis() as u64,
+        timeout_ms: timeout.as_millis() as u64,
+    }
+}
+
+//带 DO 位的查询请求, checking_disabled 为 CD 位, 对比解析器验证与不验证时的应答
+fn cd_request(name: Name, query_type: RecordType, checking_disabled: bool) -> DnsRequest {
+    let mut message = Message::new();
+    message
+        .add_query(Query::query(name, query_type))
+        .set_id(0)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .set_checking_disabled(checking_disabled);
+    message
+        .extensions_mut()
+        .get_or_insert_with(Edns::new)
+        .set_max_payload(hickory_client::proto::op::update_message::MAX_PAYLOAD_LEN)
+        .set_version(0)
+        .enable_dnssec();
+    DnsRequest::new(message, DnsRequestOptions::default())
+}
+
+//直接查询权威服务器, RD=0, 地址可以是 IPv6, dns_server 只用于错误信息与超时配置
+pub async fn query_authoritative(
+    dns_server: &str,
+    socket_addr: SocketAddr,
+    name: Name,
+    query_type: RecordType,
+) -> Result<DnsResponse, AppError> {
+    let mut message = Message::new();
+    message
+        .add_query(Query::query(name, query_type))
+        .set_id(0)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(false);
+    message
+        .extensions_mut()
+        .get_or_insert_with(Edns::new)
+        .set_max_payload(hickory_client::proto::op::update_message::MAX_PAYLOAD_LEN)
+        .set_version(0);
+    let request = DnsRequest::new(message, DnsRequestOptions::default());
+    exchange(dns_server, socket_addr, request).await
+}
+
+fn subnet_to_string(subnet: &ClientSubnet) -> String {
+    format!("{}/{}", subnet.addr(), subnet.source_prefix())
+}
+
+//解析器返回的 scope prefix
+fn response_scope_prefix(response: &DnsResponse) -> Option<u8> {
+    match response.extensions().as_ref()?.option(EdnsCode::Subnet)? {
+        EdnsOption::Subnet(subnet) => Some(subnet.scope_prefix()),
+        _ => None,
+    }
+}
+
+//hickory 错误 => 错误码
+pub fn proto_error(dns_server: &str, error: &ProtoError) -> AppError {
+    match error.kind() {
+        ProtoErrorKind::Timeout => {
+            AppError::new(ErrorCode::DnsTimeout).arg("dnsServer", dns_server)
+        }
+        ProtoErrorKind::Io(_) | ProtoErrorKind::NoConnections | ProtoErrorKind::Busy => {
+            AppError::new(ErrorCode::DnsConnectFailed)
+                .arg("dnsServer", dns_server)
+                .arg("detail", error)
+        }
+        ProtoErrorKind::Nsec { .. } => {
+            AppError::new(ErrorCode::DnssecValidationFailed).arg("detail", error)
+        }
+        _ => AppError::new(ErrorCode::DnsQueryFailed).arg("detail", error),
+    }
+}
+
+fn client_error(dns_server: &str, error: &ClientError) -> AppError {
+    match error.kind() {
+        ClientErrorKind::Proto(e) => proto_error(dns_server, e),
+        ClientErrorKind::Timeout => {
+            AppError::new(ErrorCode::DnsTimeout).arg("dnsServer", dns_server)
+        }
+        ClientErrorKind::Io(e) => AppError::new(ErrorCode::DnsConnectFailed)
+            .arg("dnsServer", dns_server)
+            .arg("detail", e),
+        ClientErrorKind::DnsSec(e) => {
+            AppError::new(ErrorCode::DnssecValidationFailed).arg("detail", e)
+        }
+        _ => AppError::new(ErrorCode::DnsQueryFailed).arg("detail", error),
+    }
+}
+
+//SERVFAIL / REFUSED 视为查询失败, NXDOMAIN 按无记录返回
+pub fn check_response_code(dns_server: &str, response: &DnsResponse) -> Result<(), AppError> {
+    match response.response_code() {
+        ResponseCode::ServFail => {
+            Err(AppError::new(ErrorCode::DnsServFail).arg("dnsServer", dns_server))
+        }
+        ResponseCode::Refused => {
+            Err(AppError::new(ErrorCode::DnsRefused).arg("dnsServer", dns_server))
+        }
+        _ => Ok(()),
+    }
+}
+
+//lang 用于否定证明的说明文字
+pub async fn v1_query_dnssec(
+    dns_sec_query: DnsSecQuery,
+    lang: Lang,
+) -> Result<DnsSecResult, AppError> {
+    let domain = &dns_sec_query.domain;
+    let record_type = &dns_sec_query.record_type;
+    let dns_server_box = &dns_sec_query.dns_server;
+    check_domain_and_type(domain, record_type)?;
+    let (ascii_domain, idn) = idn::normalize(domain)?;
+    let socket_addr = dns_socket_addr(dns_server_box)?;
+    info!("dns::v1_query_dnssec => full_addr: {}", socket_addr);
 
     let query_type = get_record_type_from_str(record_type.clone())?;
-    let query = client.query(Name::from_str(domain).unwrap(), DNSClass::IN, query_type);
-    let response = query.await.map_err(|e| {
-        let debug_info = format!("{:?}", e);
-        let display_info = e.to_string();
-
-        let error_message = format!(
-            " {},{}",
-            debug_info, display_info
-        );
+    //NSEC 证明按名称比较, 需要与应答中的记录一样是 fqdn
+    let name = Name::from_str(&ascii_domain)
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", domain))?
+        .append_domain(&Name::root())
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", domain))?;
 
-        AppError::query_dns_timeout(error_message)
-    })?;
+    //否定信任锚覆盖的区域不验证, 记录按 Insecure 返回
+    let (response, trust_anchor, validated) =
+        match anchor::validation(dns_server_box, &name).await? {
+            Validation::Disabled(info) => {
+                let response = query_raw(dns_server_box, name.clone(), query_type, true).await?;
+                (response, info, false)
+            }
+            Validation::Anchored(trust_anchors, info) => {
+                let policy = QueryPolicy::new(dns_server_box, &QueryOptions::default());
+                let conn = UdpClientStream::builder(socket_addr, TokioRuntimeProvider::default())
+                    .with_timeout(Option::from(policy.timeout))
+                    .build();
+                let (mut client, bg) = DnssecClient::builder(conn)
+                    .trust_anchor(trust_anchors)
+                    .build()
+                    .await
+                    .map_err(|e| proto_error(dns_server_box, &e))?;
+                tokio::spawn(bg);
+
+                let query = client.query(name.clone(), DNSClass::IN, query_type);
+                let response = query.await.map_err(|e| client_error(dns_server_box, &e))?;
+                (response, info, true)
+            }
+        };
+    check_response_code(dns_server_box, &response)?;
+    let denial = nsec::inspect(&name, query_type, &response, lang);
 
     let mut arr: Vec<DnsRecordSecResult> = vec![];
     response.answers().iter().for_each(|record| {
         let ttl = record.ttl();
         let data = record.data();
         arr.push(DnsRecordSecResult::new(
+            record.name().to_string(),
             record.record_type(),
             ttl,
             data.to_string(),
+            if validated {
+                record.proof()
+            } else {
+                Proof::Insecure
+            },
         ));
     });
 
     let result = DnsSecResult {
         dns_server: dns_server_box.clone(),
         dns_record: arr,
+        share_id: None,
+        idn,
+        denial,
+        trust_anchor: Some(trust_anchor),
     };
     Ok(result)
 }
 
-pub async fn v1_query(dns_query: DnsQuery) -> Result<DnsResult, AppError> {
+//lang 用于解析器组成员的 reason
+pub async fn v1_query(dns_query: DnsQuery, lang: Lang) -> Result<DnsResult, AppError> {
     let domain = &dns_query.domain;
     let record_type = &dns_query.record_type;
     let dns_server_box = &dns_query.dns_server;
 
-    if domain.trim().is_empty() {
-        return Err(AppError::bus("域名不能为空!".to_string()));
-    }
-    if !constants::DOMAIN_REG.is_match(domain) {
-        return Err(AppError::bus("域名格式不正确!".to_string()));
-    }
-    if record_type.trim().is_empty() {
-        return Err(AppError::bus("记录类型不能为空!".to_string()));
-    }
-    if !constants::RECORD_TYPES.contains(&record_type.to_uppercase().as_str()) {
-        return Err(AppError::bus("不支持的记录类型!".to_string()));
-    }
-    if dns_server_box.trim().is_empty() {
-        return Err(AppError::bus("dns供应商不能为空!".to_string()));
+    check_domain_and_type(domain, record_type)?;
+    let (ascii_domain, idn) = idn::normalize(domain)?;
+    resolve_dns_addr(dns_server_box)?;
+    retry::check_options(&dns_query.options)?;
+    let client_subnet = match dns_query.client_subnet.as_deref().map(str::trim) {
+        Some(subnet) if !subnet.is_empty() => Some(parse_client_subnet(subnet)?),
+        _ => None,
+    };
+
+    // Get record type
+    let query_type = get_record_type_from_str(record_type.clone())?;
+
+    info!("dns::v1_query => query for domain: {}", domain);
+    info!("dns::v1_query => query for type: {}", query_type);
+
+    let name = Name::from_str(&ascii_domain)
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", domain))?;
+    if let Some(subnet) = &client_subnet {
+        info!(
+            "dns::v1_query => client subnet: {}",
+            subnet_to_string(subnet)
+        );
     }
+    let request = query_request(name.clone(), query_type, client_subnet);
+    let mut attempts = vec![];
+    let (response, group) = send_query(&dns_query, request, &mut attempts, lang).await?;
 
-    let dns_addr = constants::DNS_MAP
-        .get(dns_server_box)
-        .map(|v| v.clone())
-        .unwrap_or_else(|| dns_server_box.clone());
+    //查询 CNAME 时不跟随别名
+    let mut answers = response.answers().to_vec();
+    let chain = if query_type == RecordType::CNAME {
+        None
+    } else {
+        alias_chain(&dns_query, &name, query_type, client_subnet, &mut answers).await
+    };
+    let records: Vec<DnsRecordResult> = match &chain {
+        Some(chain) => Name::from_ascii(&chain.canonical)
+            .map(|canonical| chain::final_records(&canonical, query_type, &answers))
+            .unwrap_or_default(),
+        None => answers
+            .iter()
+            .filter(|record| record.record_type() == query_type)
+            .collect(),
+    }
+    .into_iter()
+    .map(DnsRecordResult::new)
+    .collect();
+    let result = DnsResult {
+        dns_server: dns_server_box.clone(),
+        record_type: dns_query.record_type.clone().to_uppercase(),
+        dns_record: records,
+        client_subnet: client_subnet.as_ref().map(subnet_to_string),
+        scope_prefix: response_scope_prefix(&response),
+        authoritative: response.authoritative(),
+        share_id: None,
+        idn,
+        group,
+        attempts,
+        chain,
+    };
+    Ok(result)
+}
 
-    if !constants::V4_REG.is_match(&*dns_addr) {
-        return Err(AppError::bus("dns 服务器 ip地址不正确!".to_string()));
+//解析器组按策略查询各成员, 否则直接查询 dnsServer
+async fn send_query(
+    dns_query: &DnsQuery,
+    request: DnsRequest,
+    attempts: &mut Vec<QueryAttempt>,
+    lang: Lang,
+) -> Result<(DnsResponse, Option<GroupReport>), AppError> {
+    let dns_server = &dns_query.dns_server;
+    match group::GROUPS.find(dns_server) {
+        Some(group) => {
+            info!("dns::v1_query => group: {} {}", group.name, group.strategy);
+            let (response, report) = group
+                .exchange(request, &dns_query.options, attempts, lang)
+                .await?;
+            Ok((response, Some(report)))
+        }
+        None => {
+            let socket_addr = dns_socket_addr(dns_server)?;
+            info!("dns::v1_query => full_addr: {}", socket_addr);
+            let policy = QueryPolicy::new(dns_server, &dns_query.options);
+            let response =
+                exchange_with(dns_server, socket_addr, request, &policy, attempts).await?;
+            check_response_code(dns_server, &response)?;
+            Ok((response, None))
+        }
     }
+}
 
-    let full_addr = format!("{}:53", dns_addr);
-    info!("dns::v1_query => full_addr: {}", full_addr);
+//查询名称是别名时的 CNAME / DNAME 链, 应答中的链不完整时 (例如目标在其他 zone) 继续查询链的终点
+async fn alias_chain(
+    dns_query: &DnsQuery,
+    name: &Name,
+    query_type: RecordType,
+    client_subnet: Option<ClientSubnet>,
+    answers: &mut Vec<Record>,
+) -> Option<AliasChain> {
+    let mut queried = HashSet::from([name.to_lowercase()]);
+    let walk = loop {
+        let walk = chain::walk(name, answers, *constants::CNAME_CHAIN_LIMIT);
+        if walk.hops.is_empty() {
+            return None;
+        }
+        if walk.loop_detected
+            || walk.too_long
+            || !chain::final_records(&walk.canonical, query_type, answers).is_empty()
+            || !queried.insert(walk.canonical.clone())
+        {
+            break walk;
+        }
+        info!("dns::alias_chain => follow {}", walk.canonical);
+        let request = query_request(walk.canonical.clone(), query_type, client_subnet);
+        //只取应答记录, 不使用解析器组的报告
+        match send_query(dns_query, request, &mut vec![], Lang::default()).await {
+            Ok((response, _)) => answers.extend(response.answers().iter().cloned()),
+            Err(e) => {
+                info!("dns::alias_chain => {} failed: {}", walk.canonical, e.msg());
+                break walk;
+            }
+        }
+    };
 
-    let socket_addr = SocketAddr::from_str(&full_addr)
-        .map_err(|_| AppError::bus(format!("无效的地址格式: {}", dns_addr)))?;
+    let owners = chain::cname_owners(&walk);
+    let final_ttls: Vec<u32> = chain::final_records(&walk.canonical, query_type, answers)
+        .iter()
+        .map(|record| record.ttl())
+        .collect();
+    let mut alias_chain = chain::chain(walk, final_ttls.into_iter());
+    for owner in &owners {
+        chain::check_owner(owner, answers.iter(), &mut alias_chain);
+    }
+    //对 CNAME 的所有者查询 SOA, 有同名 SOA 时为 zone 顶点
+    if *constants::CNAME_CHAIN_CHECKS {
+        let checks = owners.iter().map(|owner| async move {
+            let request = query_request(owner.clone(), RecordType::SOA, None);
+            let result = send_query(dns_query, request, &mut vec![], Lang::default()).await;
+            (owner, result)
+        });
+        for (owner, result) in future::join_all(checks).await {
+            if let Ok((response, _)) = result {
+                let records = response.answers().iter().chain(response.name_servers());
+                chain::check_owner(owner, records, &mut alias_chain);
+            }
+        }
+    }
+    Some(alias_chain)
+}
 
-    let conn = UdpClientStream::builder(socket_addr, TokioRuntimeProvider::default())
-        .with_timeout(Option::from(Duration::from_secs(*constants::QUERY_TIMEOUT)))
-        .build();
-    let (mut client, bg) = Client::connect(conn)
-        .await
-        .map_err(|e| AppError::bus(e.to_string()))?;
-    tokio::spawn(bg);
+//同一查询按多个客户端子网分别发送, 结果按请求顺序返回
+pub async fn v1_query_subnets(
+    dns_subnet_query: DnsSubnetQuery,
+    lang: Lang,
+) -> Result<Vec<(String, Result<DnsResult, AppError>)>, AppError> {
+    let subnets: Vec<String> = dns_subnet_query
+        .client_subnets
+        .iter()
+        .map(|subnet| subnet.trim().to_string())
+        .filter(|subnet| !subnet.is_empty())
+        .collect();
+    if subnets.is_empty() {
+        return Err(AppError::new(ErrorCode::ClientSubnetEmpty));
+    }
+    if subnets.len() > constants::MAX_COMPARE_SUBNETS {
+        return Err(
+            AppError::new(ErrorCode::TooManyItems).arg("max", constants::MAX_COMPARE_SUBNETS)
+        );
+    }
+    for subnet in &subnets {
+        parse_client_subnet(subnet)?;
+    }
 
-    // Get record type
-    let query_type = get_record_type_from_str(record_type.clone())?;
+    let handles: Vec<_> = subnets
+        .iter()
+        .map(|subnet| {
+            tokio::spawn(v1_query(
+                DnsQuery {
+                    domain: dns_subnet_query.domain.clone(),
+                    record_type: dns_subnet_query.record_type.clone(),
+                    dns_server: dns_subnet_query.dns_server.clone(),
+                    client_subnet: Some(subnet.clone()),
+                    options: QueryOptions::default(),
+                },
+                lang,
+            ))
+        })
+        .collect();
 
-    info!("dns::v1_query => query for domain: {}", domain);
-    info!("dns::v1_query => query for type: {}", query_type);
+    let mut results = Vec::with_capacity(handles.len());
+    for (subnet, handle) in subnets.into_iter().zip(handles) {
+        let result = handle.await.unwrap_or_else(|e| Err(AppError::internal(e)));
+        results.push((subnet, result));
+    }
+    Ok(results)
+}
 
-    let query = client.query(Name::from_str(domain).unwrap(), DNSClass::IN, query_type);
+//按 CD 位查询, SERVFAIL 等应答码不视为错误, 由调用方比较
+pub async fn query_with_cd(
+    domain: &str,
+    record_type: &str,
+    dns_server: &str,
+    checking_disabled: bool,
+) -> Result<DnsResponse, AppError> {
+    check_domain_and_type(domain, record_type)?;
+    let (ascii_domain, _) = idn::normalize(domain)?;
+    let query_type = get_record_type_from_str(record_type.to_string())?;
+    let name = Name::from_str(&ascii_domain)
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", domain))?;
+    query_raw(dns_server, name, query_type, checking_disabled).await
+}
 
-    let response = query
-        .await
-        .map_err(|e| AppError::query_dns_timeout(e.to_string()))?;
+//任意记录类型 (DNSKEY / DS / SOA ...) 的查询, 带 DO 位, 应答码原样返回
+pub async fn query_raw(
+    dns_server: &str,
+    name: Name,
+    query_type: RecordType,
+    checking_disabled: bool,
+) -> Result<DnsResponse, AppError> {
+    let socket_addr = dns_socket_addr(dns_server)?;
+    info!(
+        "dns::query_raw => {} {} @{} cd={}",
+        name, query_type, socket_addr, checking_disabled
+    );
+    exchange(
+        dns_server,
+        socket_addr,
+        cd_request(name, query_type, checking_disabled),
+    )
+    .await
+}
 
-    let records: Vec<DnsRecordResult> = response
+//应答中与查询类型相同的记录
+pub fn answer_records(response: &DnsResponse, record_type: &str) -> Vec<DnsRecordResult> {
+    response
         .answers()
         .iter()
-        .filter(|record| record.record_type() == query_type)
-        .map(|record| DnsRecordResult {
-            value: record.data().to_string(),
-            ttl: record.ttl(),
+        .filter(|record| {
+            record
+                .record_type()
+                .to_string()
+                .eq_ignore_ascii_case(record_type)
         })
-        .collect();
-    let result = DnsResult {
-        dns_server: dns_server_box.clone(),
-        record_type: dns_query.record_type.clone().to_uppercase(),
-        dns_record: records,
-    };
-    Ok(result)
+        .map(DnsRecordResult::new)
+        .collect()
+}
+
+//NOERROR / SERVFAIL / NXDOMAIN 形式的应答码
+pub fn response_code_name(response: &DnsResponse) -> String {
+    format!("{:?}", response.response_code()).to_uppercase()
 }
@@ -0,0 +1,540 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsCdAnswer, DnsSecQuery, DnsSecResult, DnskeyInfo, DnssecDiagnosis,
+    DnssecHealthQuery, DnssecHealthReport, DsInfo, ErrorCode, RrsigInfo,
+};
+use crate::i18n::{self, Lang};
+use crate::{dns, idn};
+use hickory_client::proto::dnssec::PublicKey;
+use hickory_client::proto::dnssec::rdata::{DNSKEY, DNSSECRData, DS, RRSIG};
+use hickory_client::proto::op::ResponseCode;
+use hickory_client::proto::rr::{Name, RData, RecordType};
+use hickory_client::proto::xfer::DnsResponse;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//dnssec 检查中需要检查签名的关键记录集
+const KEY_RRSETS: [RecordType; 3] = [RecordType::DNSKEY, RecordType::SOA, RecordType::NS];
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+fn cd_answer(
+    checking_disabled: bool,
+    record_type: &str,
+    result: &Result<DnsResponse, AppError>,
+    lang: Lang,
+) -> DnsCdAnswer {
+    match result {
+        Ok(response) => DnsCdAnswer {
+            checking_disabled,
+            code: constants::OKAY_CODE.to_string(),
+            msg: constants::OKAY_MSG.to_string(),
+            response_code: Some(dns::response_code_name(response)),
+            authentic_data: response.authentic_data(),
+            dns_record: dns::answer_records(response, record_type),
+        },
+        Err(e) => DnsCdAnswer {
+            checking_disabled,
+            code: e.code().to_string(),
+            msg: e.localize(lang),
+            response_code: None,
+            authentic_data: false,
+            dns_record: vec![],
+        },
+    }
+}
+
+//收到应答且不是 SERVFAIL / REFUSED, NXDOMAIN 也算正常应答
+fn answered(result: &Result<DnsResponse, AppError>) -> bool {
+    result.as_ref().is_ok_and(|response| {
+        !matches!(
+            response.response_code(),
+            ResponseCode::ServFail | ResponseCode::Refused
+        )
+    })
+}
+
+fn describe(answer: &DnsCdAnswer, lang: Lang) -> String {
+    match &answer.response_code {
+        Some(response_code) => i18n::format(
+            lang,
+            "DNSSEC_EXPLAIN_ANSWER",
+            &[
+                ("responseCode", response_code.clone()),
+                ("count", answer.dns_record.len().to_string()),
+            ],
+        ),
+        None => answer.msg.clone(),
+    }
+}
+
+//本地验证结果按 proof 计数, 例如 Secure 2, Bogus 1
+fn proof_summary(result: &DnsSecResult, lang: Lang) -> String {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for record in &result.dns_record {
+        match counts.iter_mut().find(|(proof, _)| *proof == record.proof) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((record.proof.clone(), 1)),
+        }
+    }
+    if counts.is_empty() {
+        return i18n::text(lang, "DNSSEC_EXPLAIN_NO_RECORDS");
+    }
+    counts
+        .iter()
+        .map(|(proof, count)| format!("{} {}", proof, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn explain(
+    status: &str,
+    validating: &DnsCdAnswer,
+    unchecked: &DnsCdAnswer,
+    dnssec: &Result<DnsSecResult, AppError>,
+    lang: Lang,
+) -> Vec<String> {
+    let mut explanation = vec![i18n::text(
+        lang,
+        match status {
+            constants::DIAGNOSIS_HEALTHY => "DNSSEC_EXPLAIN_HEALTHY",
+            constants::DIAGNOSIS_DNSSEC_BOGUS => "DNSSEC_EXPLAIN_BOGUS",
+            _ => "DNSSEC_EXPLAIN_BROKEN",
+        },
+    )];
+    explanation.push(format!("CD=0: {}", describe(validating, lang)));
+    explanation.push(format!("CD=1: {}", describe(unchecked, lang)));
+    if validating.authentic_data {
+        explanation.push(i18n::text(lang, "DNSSEC_EXPLAIN_AD"));
+    }
+
+    match dnssec {
+        Ok(result) => {
+            explanation.push(i18n::format(
+                lang,
+                "DNSSEC_EXPLAIN_LOCAL",
+                &[("summary", proof_summary(result, lang))],
+            ));
+            let bogus: Vec<String> = result
+                .dns_record
+                .iter()
+                .filter(|record| record.proof == "Bogus")
+                .map(|record| format!("{} {}", record.record_type, record.value))
+                .collect();
+            if !bogus.is_empty() {
+                explanation.push(i18n::format(
+                    lang,
+                    "DNSSEC_EXPLAIN_BOGUS_RECORDS",
+                    &[("records", bogus.join("; "))],
+                ));
+            } else if status == constants::DIAGNOSIS_HEALTHY
+                && !result.dns_record.is_empty()
+                && result
+                    .dns_record
+                    .iter()
+                    .all(|record| record.proof == "Insecure")
+            {
+                explanation.push(i18n::text(lang, "DNSSEC_EXPLAIN_INSECURE"));
+            }
+        }
+        Err(e) => explanation.push(i18n::format(
+            lang,
+            "DNSSEC_EXPLAIN_LOCAL_FAILED",
+            &[("error", e.localize(lang))],
+        )),
+    }
+    explanation
+}
+
+//同一查询分别以 CD=0 / CD=1 发送, 再用 DnssecClient 本地验证, 判断故障是否由 dnssec 引起
+pub async fn diagnose(query: DnsSecQuery, lang: Lang) -> Result<DnssecDiagnosis, AppError> {
+    dns::check_domain_and_type(&query.domain, &query.record_type)?;
+    dns::resolve_dns_addr(&query.dns_server)?;
+
+    let (validating, unchecked, dnssec) = tokio::join!(
+        dns::query_with_cd(&query.domain, &query.record_type, &query.dns_server, false),
+        dns::query_with_cd(&query.domain, &query.record_type, &query.dns_server, true),
+        dns::v1_query_dnssec(
+            DnsSecQuery {
+                domain: query.domain.clone(),
+                dns_server: query.dns_server.clone(),
+                record_type: query.record_type.clone(),
+            },
+            lang
+        ),
+    );
+
+    let status = if answered(&validating) {
+        constants::DIAGNOSIS_HEALTHY
+    } else if answered(&unchecked) {
+        constants::DIAGNOSIS_DNSSEC_BOGUS
+    } else {
+        constants::DIAGNOSIS_BROKEN
+    };
+    let record_type = query.record_type.to_uppercase();
+    let validating = cd_answer(false, &record_type, &validating, lang);
+    let unchecked = cd_answer(true, &record_type, &unchecked, lang);
+    let explanation = explain(status, &validating, &unchecked, &dnssec, lang);
+    let (dnssec, dnssec_error) = match dnssec {
+        Ok(result) => (Some(result), None),
+        Err(e) => (None, Some(e.localize(lang))),
+    };
+    Ok(DnssecDiagnosis {
+        domain: query.domain,
+        record_type,
+        dns_server: query.dns_server,
+        status: status.to_string(),
+        validating,
+        unchecked,
+        dnssec,
+        dnssec_error,
+        explanation,
+    })
+}
+
+fn dnssec_rdata(response: &DnsResponse) -> impl Iterator<Item = &DNSSECRData> {
+    response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::DNSSEC(rdata) => Some(rdata),
+            _ => None,
+        })
+}
+
+fn dnskeys(response: &DnsResponse) -> Vec<&DNSKEY> {
+    dnssec_rdata(response)
+        .filter_map(|rdata| match rdata {
+            DNSSECRData::DNSKEY(key) => Some(key),
+            _ => None,
+        })
+        .collect()
+}
+
+fn ds_records(response: &DnsResponse) -> Vec<&DS> {
+    dnssec_rdata(response)
+        .filter_map(|rdata| match rdata {
+            DNSSECRData::DS(ds) => Some(ds),
+            _ => None,
+        })
+        .collect()
+}
+
+fn rrsigs(response: &DnsResponse, covered: RecordType) -> Vec<&RRSIG> {
+    dnssec_rdata(response)
+        .filter_map(|rdata| match rdata {
+            DNSSECRData::RRSIG(rrsig) if rrsig.type_covered() == covered => Some(rrsig),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+//检查结果, 只会从 OK 升级到 WARNING / CRITICAL
+struct Findings {
+    lang: Lang,
+    critical: bool,
+    warnings: Vec<String>,
+}
+
+impl Findings {
+    fn warn(&mut self, key: &str, args: &[(&str, String)]) {
+        self.warnings.push(i18n::format(self.lang, key, args));
+    }
+
+    fn critical(&mut self, key: &str, args: &[(&str, String)]) {
+        self.critical = true;
+        self.warn(key, args);
+    }
+}
+
+fn check_signatures(
+    covered: RecordType,
+    response: &Result<DnsResponse, AppError>,
+    keys: &[DnskeyInfo],
+    warn_days: u32,
+    now: i64,
+    findings: &mut Findings,
+) -> Vec<RrsigInfo> {
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            let error = e.localize(findings.lang);
+            findings.warn(
+                "DNSSEC_HEALTH_QUERY_FAILED",
+                &[("recordType", covered.to_string()), ("error", error)],
+            );
+            return vec![];
+        }
+    };
+    let has_rrset = response
+        .answers()
+        .iter()
+        .any(|record| record.record_type() == covered);
+    if !has_rrset {
+        if covered == RecordType::SOA {
+            findings.warn("DNSSEC_HEALTH_NO_SOA", &[]);
+        }
+        return vec![];
+    }
+
+    let signatures: Vec<RrsigInfo> = rrsigs(response, covered)
+        .into_iter()
+        .map(|rrsig| {
+            let expiration = rrsig.sig_expiration().get() as i64;
+            RrsigInfo {
+                type_covered: covered.to_string(),
+                key_tag: rrsig.key_tag(),
+                algorithm: rrsig.algorithm().as_str().to_string(),
+                signer_name: rrsig.signer_name().to_string(),
+                inception: rrsig.sig_inception().get() as u64 * 1000,
+                expiration: expiration as u64 * 1000,
+                days_left: (expiration - now).div_euclid(SECONDS_PER_DAY),
+                expired: expiration < now,
+                key_known: keys.iter().any(|key| key.key_tag == rrsig.key_tag()),
+            }
+        })
+        .collect();
+
+    let record_type = covered.to_string();
+    if signatures.is_empty() {
+        findings.critical(
+            "DNSSEC_HEALTH_NO_RRSIG",
+            &[("recordType", record_type.clone())],
+        );
+    } else if signatures.iter().all(|sig| sig.expired) {
+        findings.critical(
+            "DNSSEC_HEALTH_ALL_EXPIRED",
+            &[("recordType", record_type.clone())],
+        );
+    }
+    for sig in &signatures {
+        let key_tag = ("keyTag", sig.key_tag.to_string());
+        if sig.expired {
+            findings.warn(
+                "DNSSEC_HEALTH_RRSIG_EXPIRED",
+                &[
+                    ("recordType", record_type.clone()),
+                    key_tag.clone(),
+                    ("days", (-sig.days_left).to_string()),
+                ],
+            );
+        } else if sig.days_left < warn_days as i64 {
+            findings.warn(
+                "DNSSEC_HEALTH_RRSIG_EXPIRING",
+                &[
+                    ("recordType", record_type.clone()),
+                    key_tag.clone(),
+                    ("days", (sig.days_left + 1).to_string()),
+                ],
+            );
+        }
+        if !sig.key_known {
+            findings.warn(
+                "DNSSEC_HEALTH_RRSIG_KEY_UNKNOWN",
+                &[("recordType", record_type.clone()), key_tag],
+            );
+        }
+    }
+    signatures
+}
+
+fn check_chain(keys: &[DnskeyInfo], ds: &[DsInfo], findings: &mut Findings) {
+    if keys.is_empty() {
+        if !ds.is_empty() {
+            findings.critical("DNSSEC_HEALTH_DS_WITHOUT_DNSKEY", &[]);
+        }
+        return;
+    }
+    if ds.is_empty() {
+        findings.warn("DNSSEC_HEALTH_NO_DS", &[]);
+        return;
+    }
+    if ds.iter().all(|ds| !ds.matched) {
+        findings.critical("DNSSEC_HEALTH_DS_MISMATCH", &[]);
+    }
+    for ds in ds.iter().filter(|ds| !ds.matched) {
+        findings.warn(
+            "DNSSEC_HEALTH_DS_ORPHAN",
+            &[
+                ("keyTag", ds.key_tag.to_string()),
+                ("digestType", ds.digest_type.clone()),
+            ],
+        );
+    }
+    for key in keys.iter().filter(|key| key.role == "KSK" && !key.has_ds) {
+        findings.warn(
+            "DNSSEC_HEALTH_KSK_WITHOUT_DS",
+            &[("keyTag", key.key_tag.to_string())],
+        );
+    }
+    for key in keys.iter().filter(|key| key.revoked) {
+        findings.warn(
+            "DNSSEC_HEALTH_KEY_REVOKED",
+            &[("keyTag", key.key_tag.to_string())],
+        );
+    }
+}
+
+//区域的 DNSKEY / DS / 关键记录集 RRSIG 检查, 签名过期、DS 不匹配、算法轮换
+pub async fn health(query: DnssecHealthQuery, lang: Lang) -> Result<DnssecHealthReport, AppError> {
+    if query.zone.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::DomainEmpty));
+    }
+    let (ascii_zone, _) = idn::normalize(&query.zone)?;
+    dns::resolve_dns_addr(&query.dns_server)?;
+    let zone = Name::from_str(&format!("{}.", ascii_zone.trim_end_matches('.')))
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", &query.zone))?;
+    let warn_days = query
+        .warn_days
+        .unwrap_or(*constants::DNSSEC_EXPIRY_WARN_DAYS);
+
+    //CD=1, 签名有问题时解析器也会返回记录
+    let server = &query.dns_server;
+    let (dnskey_response, ds_response, soa_response, ns_response) = tokio::join!(
+        dns::query_raw(server, zone.clone(), RecordType::DNSKEY, true),
+        dns::query_raw(server, zone.clone(), RecordType::DS, true),
+        dns::query_raw(server, zone.clone(), RecordType::SOA, true),
+        dns::query_raw(server, zone.clone(), RecordType::NS, true),
+    );
+    let dnskey_response = dnskey_response?;
+    let mut findings = Findings {
+        lang,
+        critical: false,
+        warnings: vec![],
+    };
+
+    let ds_list = match &ds_response {
+        Ok(response) => ds_records(response),
+        Err(e) => {
+            let error = e.localize(lang);
+            findings.warn(
+                "DNSSEC_HEALTH_QUERY_FAILED",
+                &[("recordType", "DS".to_string()), ("error", error)],
+            );
+            vec![]
+        }
+    };
+    let key_list = dnskeys(&dnskey_response);
+    let keys: Vec<DnskeyInfo> = key_list
+        .iter()
+        .map(|key| DnskeyInfo {
+            key_tag: key.calculate_key_tag().unwrap_or_default(),
+            role: if key.secure_entry_point() {
+                "KSK"
+            } else {
+                "ZSK"
+            }
+            .to_string(),
+            flags: key.flags(),
+            algorithm: key.public_key().algorithm().as_str().to_string(),
+            revoked: key.revoke(),
+            has_ds: ds_list
+                .iter()
+                .any(|ds| ds.covers(&zone, key).unwrap_or(false)),
+        })
+        .collect();
+    let ds: Vec<DsInfo> = ds_list
+        .iter()
+        .map(|ds| DsInfo {
+            key_tag: ds.key_tag(),
+            algorithm: ds.algorithm().as_str().to_string(),
+            digest_type: format!("{:?}", ds.digest_type()),
+            digest: hex(ds.digest()),
+            matched: key_list
+                .iter()
+                .any(|key| ds.covers(&zone, key).unwrap_or(false)),
+        })
+        .collect();
+
+    if keys.is_empty() && ds.is_empty() {
+        return Ok(DnssecHealthReport {
+            zone: zone.to_string(),
+            dns_server: query.dns_server,
+            status: constants::DNSSEC_HEALTH_UNSIGNED.to_string(),
+            warn_days,
+            keys,
+            ds,
+            signatures: vec![],
+            algorithms: vec![],
+            algorithm_rollover: false,
+            warnings: vec![i18n::text(lang, "DNSSEC_HEALTH_UNSIGNED_ZONE")],
+        });
+    }
+    check_chain(&keys, &ds, &mut findings);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let responses = [Ok(dnskey_response), soa_response, ns_response];
+    let mut signatures = vec![];
+    for (covered, response) in KEY_RRSETS.iter().zip(responses.iter()) {
+        signatures.extend(check_signatures(
+            *covered,
+            response,
+            &keys,
+            warn_days,
+            now,
+            &mut findings,
+        ));
+    }
+
+    //RFC 6781 4.1.4, 算法轮换期间 DNSKEY 中同时存在新旧两种算法
+    let mut algorithms: Vec<String> = vec![];
+    for key in &keys {
+        if !algorithms.contains(&key.algorithm) {
+            algorithms.push(key.algorithm.clone());
+        }
+    }
+    let algorithm_rollover = algorithms.len() > 1
+        || ds
+            .iter()
+            .any(|ds| !keys.is_empty() && !algorithms.contains(&ds.algorithm));
+    if algorithm_rollover {
+        findings.warn(
+            "DNSSEC_HEALTH_ALGORITHM_ROLLOVER",
+            &[("algorithms", algorithms.join(", "))],
+        );
+    }
+
+    let status = if findings.critical {
+        constants::DNSSEC_HEALTH_CRITICAL
+    } else if findings.warnings.is_empty() {
+        constants::DNSSEC_HEALTH_OK
+    } else {
+        constants::DNSSEC_HEALTH_WARNING
+    };
+    Ok(DnssecHealthReport {
+        zone: zone.to_string(),
+        dns_server: query.dns_server,
+        status: status.to_string(),
+        warn_days,
+        keys,
+        ds,
+        signatures,
+        algorithms,
+        algorithm_rollover,
+        warnings: findings.warnings,
+    })
+}
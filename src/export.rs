@@ -0,0 +1,324 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsResult, DnsSecResult, ErrorCode, ExportResult, HistoryRecord, PermalinkRecord,
+};
+use crate::{dns, idn, permalink};
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+
+//导出格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    //默认, ApiResponse json
+    Envelope,
+    Csv,
+    //只有数据本身, 格式化的 json
+    Json,
+    //RFC 1035 master file
+    Zone,
+    Dig,
+}
+
+impl ExportFormat {
+    fn from_param(format: &str) -> Result<Self, AppError> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "zone" => Ok(ExportFormat::Zone),
+            "dig" => Ok(ExportFormat::Dig),
+            _ => Err(AppError::new(ErrorCode::FormatUnsupported).arg("format", format)),
+        }
+    }
+
+    //format 参数优先, 其次 Accept 头 (只认 text/csv 与 text/dns), dig 格式必须用 format=dig 指定
+    //text/plain 常见于命令行工具与浏览器的默认 Accept, 仍返回普通 json
+    pub fn from_request(req: &HttpRequest, format: Option<&str>) -> Result<Self, AppError> {
+        if let Some(format) = format.map(str::trim).filter(|f| !f.is_empty()) {
+            return Self::from_param(format);
+        }
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let accepts = |mime: &str| {
+            accept
+                .split(',')
+                .any(|item| item.split(';').next().unwrap_or_default().trim() == mime)
+        };
+        Ok(if accepts("text/csv") {
+            ExportFormat::Csv
+        } else if accepts("text/dns") {
+            ExportFormat::Zone
+        } else {
+            ExportFormat::Envelope
+        })
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Envelope | ExportFormat::Json => constants::CONTENT_TYPE_JSON_VALUE,
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Zone => "text/dns; charset=utf-8",
+            ExportFormat::Dig => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Envelope | ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Zone => "zone",
+            ExportFormat::Dig => "txt",
+        }
+    }
+}
+
+pub fn from_dns_result(domain: &str, result: &DnsResult, duration_ms: Option<u64>) -> ExportResult {
+    ExportResult {
+        domain: domain.to_string(),
+        record_type: result.record_type.clone(),
+        dns_server: result.dns_server.clone(),
+        client_subnet: result.client_subnet.clone(),
+        duration_ms,
+        records: permalink::from_dns_result(result),
+    }
+}
+
+pub fn from_dns_sec_result(
+    domain: &str,
+    record_type: &str,
+    result: &DnsSecResult,
+    duration_ms: Option<u64>,
+) -> ExportResult {
+    ExportResult {
+        domain: domain.to_string(),
+        record_type: record_type.to_uppercase(),
+        dns_server: result.dns_server.clone(),
+        client_subnet: None,
+        duration_ms,
+        records: permalink::from_dns_sec_result(result),
+    }
+}
+
+//保存的查询历史
+pub fn from_history(history: &HistoryRecord) -> ExportResult {
+    ExportResult {
+        domain: history.domain.clone(),
+        record_type: history.record_type.clone(),
+        dns_server: history.dns_server.clone(),
+        client_subnet: history.client_subnet.clone(),
+        duration_ms: Some(history.duration_ms),
+        records: permalink::saved_records(history),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+//csv 一行
+pub fn csv_row(fields: &[String]) -> String {
+    let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+    format!("{}\n", row.join(","))
+}
+
+//国际化域名使用 A-label
+fn fqdn(domain: &str) -> String {
+    let domain = idn::normalize(domain)
+        .map(|(ascii, _)| ascii)
+        .unwrap_or_else(|_| domain.to_string());
+    if domain.ends_with('.') {
+        domain
+    } else {
+        format!("{}.", domain)
+    }
+}
+
+//记录所有者, 旧的历史记录没有保存所有者时用查询的域名
+fn owner(result: &ExportResult, record: &PermalinkRecord) -> String {
+    if record.name.is_empty() {
+        fqdn(&result.domain)
+    } else {
+        fqdn(&record.name)
+    }
+}
+
+//TXT 在 master file 中需要加引号, 每个字符串最多 255 字节, 超出时拆成多个 "..."
+fn rdata(record: &PermalinkRecord) -> String {
+    if record.record_type == "TXT" && !record.value.starts_with('"') {
+        txt_chunks(&record.value)
+            .iter()
+            .map(|chunk| format!("\"{}\"", chunk.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        record.value.clone()
+    }
+}
+
+//按字节拆分, 不拆开多字节字符
+fn txt_chunks(value: &str) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = value;
+    while rest.len() > constants::TXT_STRING_MAX {
+        let mut end = constants::TXT_STRING_MAX;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks.push(rest);
+    chunks
+}
+
+fn to_csv(results: &[ExportResult]) -> String {
+    let mut csv = csv_row(
+        &[
+            "domain",
+            "dnsServer",
+            "clientSubnet",
+            "recordType",
+            "ttl",
+            "value",
+            "proof",
+        ]
+        .map(String::from),
+    );
+    for result in results {
+        for record in &result.records {
+            csv.push_str(&csv_row(&[
+                result.domain.clone(),
+                result.dns_server.clone(),
+                result.client_subnet.clone().unwrap_or_default(),
+                record.record_type.clone(),
+                record.ttl.to_string(),
+                record.value.clone(),
+                record.proof.clone().unwrap_or_default(),
+            ]));
+        }
+    }
+    csv
+}
+
+fn to_zone(results: &[ExportResult]) -> String {
+    let mut zone = String::new();
+    for result in results {
+        zone.push_str(&format!(
+            "; {} {} @{}",
+            result.domain, result.record_type, result.dns_server
+        ));
+        if let Some(client_subnet) = &result.client_subnet {
+            zone.push_str(&format!(" subnet {}", client_subnet));
+        }
+        zone.push('\n');
+        for record in &result.records {
+            zone.push_str(&format!(
+                "{}\t{}\tIN\t{}\t{}\n",
+                owner(result, record),
+                record.ttl,
+                record.record_type,
+                rdata(record)
+            ));
+        }
+    }
+    zone
+}
+
+fn to_dig(results: &[ExportResult]) -> String {
+    let blocks: Vec<String> = results
+        .iter()
+        .map(|result| {
+            let name = fqdn(&result.domain);
+            //解析器组没有单一的服务器地址
+            let socket_addr = dns::dns_socket_addr(&result.dns_server).ok();
+            let server = match socket_addr {
+                Some(addr) if addr.port() != 53 => format!("{} -p {}", addr.ip(), addr.port()),
+                Some(addr) => addr.ip().to_string(),
+                None => result.dns_server.clone(),
+            };
+            let mut dig = format!(
+                "; <<>> dns-tool <<>> @{} {} {}\n",
+                server, result.domain, result.record_type
+            );
+            dig.push_str(&format!(
+                ";; ANSWER: {}\n\n;; QUESTION SECTION:\n;{}\t\tIN\t{}\n\n",
+                result.records.len(),
+                name,
+                result.record_type
+            ));
+            if !result.records.is_empty() {
+                dig.push_str(";; ANSWER SECTION:\n");
+                for record in &result.records {
+                    dig.push_str(&format!(
+                        "{}\t\t{}\tIN\t{}\t{}\n",
+                        owner(result, record),
+                        record.ttl,
+                        record.record_type,
+                        rdata(record)
+                    ));
+                }
+                dig.push('\n');
+            }
+            if let Some(client_subnet) = &result.client_subnet {
+                dig.push_str(&format!(";; CLIENT-SUBNET: {}\n", client_subnet));
+            }
+            if let Some(duration_ms) = result.duration_ms {
+                dig.push_str(&format!(";; Query time: {} msec\n", duration_ms));
+            }
+            if let Some(addr) = socket_addr {
+                dig.push_str(&format!(
+                    ";; SERVER: {}#{}({})\n",
+                    addr.ip(),
+                    addr.port(),
+                    result.dns_server
+                ));
+            }
+            dig
+        })
+        .collect();
+    blocks.join("\n")
+}
+
+//按格式输出, Envelope 由调用方处理
+pub fn render<T: Serialize>(
+    format: ExportFormat,
+    data: &T,
+    results: &[ExportResult],
+) -> HttpResponse {
+    let body = match format {
+        ExportFormat::Envelope | ExportFormat::Json => {
+            serde_json::to_string_pretty(data).unwrap_or_default()
+        }
+        ExportFormat::Csv => to_csv(results),
+        ExportFormat::Zone => to_zone(results),
+        ExportFormat::Dig => to_dig(results),
+    };
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(body)
+}
@@ -0,0 +1,534 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, ErrorCode, GroupMemberResult, GroupReport, QueryAttempt, QueryOptions,
+    ResolverGroupInfo, ResolverGroupList,
+};
+use crate::dns;
+use crate::i18n::{self, Lang};
+use crate::retry::QueryPolicy;
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use hickory_client::proto::op::ResponseCode;
+use hickory_client::proto::xfer::{DnsRequest, DnsResponse};
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    pub static ref GROUPS: Groups = Groups::load();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    //按顺序, 失败时换下一个
+    Failover,
+    //每次从下一个成员开始, 失败时继续往后
+    RoundRobin,
+    //同时查询, 第一个成功的应答
+    Race,
+    //同时查询, N 个成员应答一致时返回
+    Quorum(usize),
+}
+
+impl Strategy {
+    fn name(&self) -> &'static str {
+        match self {
+            Strategy::Failover => constants::GROUP_FAILOVER,
+            Strategy::RoundRobin => constants::GROUP_ROUND_ROBIN,
+            Strategy::Race => constants::GROUP_RACE,
+            Strategy::Quorum(_) => constants::GROUP_QUORUM,
+        }
+    }
+
+    fn quorum(&self) -> Option<usize> {
+        match self {
+            Strategy::Quorum(quorum) => Some(*quorum),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Quorum(quorum) => write!(f, "{}/{}", self.name(), quorum),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+struct Member {
+    name: String,
+    addr: SocketAddr,
+}
+
+pub struct Group {
+    pub name: String,
+    pub strategy: Strategy,
+    members: Vec<Member>,
+    //轮询的下一个起点
+    next: AtomicUsize,
+}
+
+pub struct Groups {
+    groups: Vec<Group>,
+    pub errors: Vec<String>,
+}
+
+//一个成员的查询结果与每次发送, SERVFAIL 与 REFUSED 视为失败
+type Outcome = (
+    usize,
+    Result<DnsResponse, AppError>,
+    Duration,
+    Vec<QueryAttempt>,
+);
+
+fn member_result(
+    name: &str,
+    status: &str,
+    reason: Option<String>,
+    elapsed: Option<Duration>,
+) -> GroupMemberResult {
+    GroupMemberResult {
+        name: name.to_string(),
+        status: status.to_string(),
+        reason,
+        duration_ms: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+    }
+}
+
+//所有成员失败或没有凑齐 quorum 时附在错误中的成员情况: 成员: 原因; 成员: 原因
+fn member_summary(members: &[Option<GroupMemberResult>]) -> String {
+    members
+        .iter()
+        .flatten()
+        .map(|member| match &member.reason {
+            Some(reason) => format!("{}: {}", member.name, reason),
+            None => format!("{}: {}", member.name, member.status),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+//quorum 比较的内容: 应答码与应答记录, 不比较 TTL
+fn answer_key(response: &DnsResponse) -> (ResponseCode, Vec<String>) {
+    let mut values: Vec<String> = response
+        .answers()
+        .iter()
+        .map(|record| format!("{} {}", record.record_type(), record.data()))
+        .collect();
+    values.sort();
+    values.dedup();
+    (response.response_code(), values)
+}
+
+fn answer_text((code, values): &(ResponseCode, Vec<String>)) -> String {
+    if values.is_empty() {
+        format!("{:?}", code).to_uppercase()
+    } else {
+        values.join(", ")
+    }
+}
+
+//名称=策略:成员,成员, 策略 quorum/N 中 N 省略时为多数
+fn parse_group(item: &str) -> Result<Group, String> {
+    let (name, rest) = item
+        .split_once('=')
+        .ok_or_else(|| format!("{}: 格式应为 名称=策略:成员,成员", item))?;
+    let (strategy, members) = rest
+        .split_once(':')
+        .ok_or_else(|| format!("{}: 格式应为 名称=策略:成员,成员", item))?;
+    let name = name.trim();
+    if name.is_empty() || constants::DNS_MAP.contains_key(name) {
+        return Err(format!(
+            "{}: 组名不能为空, 也不能与 dns服务商 key 相同",
+            item
+        ));
+    }
+    let members = members
+        .split(',')
+        .map(str::trim)
+        .filter(|member| !member.is_empty())
+        .map(|member| {
+            dns::dns_socket_addr(member)
+                .map(|addr| Member {
+                    name: member.to_string(),
+                    addr,
+                })
+                .map_err(|e| format!("{}: {}", name, e.msg()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if members.is_empty() {
+        return Err(format!("{}: 没有成员", name));
+    }
+    let strategy = match strategy.trim().split_once('/') {
+        None if strategy.trim() == constants::GROUP_FAILOVER => Strategy::Failover,
+        None if strategy.trim() == constants::GROUP_ROUND_ROBIN => Strategy::RoundRobin,
+        None if strategy.trim() == constants::GROUP_RACE => Strategy::Race,
+        None if strategy.trim() == constants::GROUP_QUORUM => {
+            Strategy::Quorum(members.len() / 2 + 1)
+        }
+        Some((quorum, count)) if quorum == constants::GROUP_QUORUM => match count.parse() {
+            Ok(count) if count > 0 && count <= members.len() => Strategy::Quorum(count),
+            _ => return Err(format!("{}: quorum 数量应为 1 到 {}", name, members.len())),
+        },
+        _ => {
+            return Err(format!(
+                "{}: 不支持的策略 {}, 可选 failover / round-robin / race / quorum[/N]",
+                name, strategy
+            ));
+        }
+    };
+    Ok(Group {
+        name: name.to_string(),
+        strategy,
+        members,
+        next: AtomicUsize::new(0),
+    })
+}
+
+impl Groups {
+    fn load() -> Self {
+        let mut groups = Groups {
+            groups: vec![],
+            errors: vec![],
+        };
+        for item in constants::RESOLVER_GROUPS.iter() {
+            match parse_group(item) {
+                Ok(group) if groups.find(&group.name).is_some() => {
+                    groups.errors.push(format!("{}: 组名重复", group.name))
+                }
+                Ok(group) => groups.groups.push(group),
+                Err(e) => {
+                    error!("group::load => {}", e);
+                    groups.errors.push(format!("RESOLVER_GROUPS {}", e));
+                }
+            }
+        }
+        groups
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn list(&self) -> ResolverGroupList {
+        ResolverGroupList {
+            groups: self
+                .groups
+                .iter()
+                .map(|group| ResolverGroupInfo {
+                    name: group.name.clone(),
+                    strategy: group.strategy.name().to_string(),
+                    quorum: group.strategy.quorum(),
+                    members: group
+                        .members
+                        .iter()
+                        .map(|member| member.name.clone())
+                        .collect(),
+                })
+                .collect(),
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+impl Group {
+    async fn ask(&self, index: usize, request: DnsRequest, options: &QueryOptions) -> Outcome {
+        let member = &self.members[index];
+        let started = Instant::now();
+        let policy = QueryPolicy::new(&member.name, options);
+        let mut attempts = vec![];
+        let result = dns::exchange_with(&member.name, member.addr, request, &policy, &mut attempts)
+            .await
+            .and_then(|response| {
+                dns::check_response_code(&member.name, &response)?;
+                Ok(response)
+            });
+        (index, result, started.elapsed(), attempts)
+    }
+
+    fn report(
+        &self,
+        answered_by: usize,
+        members: Vec<Option<GroupMemberResult>>,
+        lang: Lang,
+    ) -> GroupReport {
+        let answered_by = &self.members[answered_by].name;
+        GroupReport {
+            name: self.name.clone(),
+            strategy: self.strategy.name().to_string(),
+            quorum: self.strategy.quorum(),
+            answered_by: answered_by.clone(),
+            members: members
+                .into_iter()
+                .zip(&self.members)
+                .map(|(result, member)| {
+                    result.unwrap_or_else(|| {
+                        member_result(
+                            &member.name,
+                            constants::GROUP_MEMBER_SKIPPED,
+                            Some(i18n::format(
+                                lang,
+                                "GROUP_ANSWERED_BY",
+                                &[("member", answered_by.clone())],
+                            )),
+                            None,
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    //按策略查询, 返回应答与每个成员的处理情况, 已完成的成员的每次发送记录到 attempts
+    //lang 用于成员的 reason
+    pub async fn exchange(
+        &self,
+        request: DnsRequest,
+        options: &QueryOptions,
+        attempts: &mut Vec<QueryAttempt>,
+        lang: Lang,
+    ) -> Result<(DnsResponse, GroupReport), AppError> {
+        match self.strategy {
+            Strategy::Failover => self.sequential(0, request, options, attempts, lang).await,
+            Strategy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % self.members.len();
+                self.sequential(start, request, options, attempts, lang)
+                    .await
+            }
+            Strategy::Race => self.race(request, options, attempts, lang).await,
+            Strategy::Quorum(quorum) => self.quorum(quorum, request, options, attempts, lang).await,
+        }
+    }
+
+    //从 start 开始依次查询, 第一个成功的应答
+    async fn sequential(
+        &self,
+        start: usize,
+        request: DnsRequest,
+        options: &QueryOptions,
+        attempts: &mut Vec<QueryAttempt>,
+        lang: Lang,
+    ) -> Result<(DnsResponse, GroupReport), AppError> {
+        let count = self.members.len();
+        let mut members = vec![None; count];
+        for index in (0..count).map(|offset| (start + offset) % count) {
+            let name = &self.members[index].name;
+            let (_, result, elapsed, sent) = self.ask(index, request.clone(), options).await;
+            attempts.extend(sent);
+            match result {
+                Ok(response) => {
+                    members[index] = Some(member_result(
+                        name,
+                        constants::GROUP_MEMBER_ANSWERED,
+                        None,
+                        Some(elapsed),
+                    ));
+                    return Ok((response, self.report(index, members, lang)));
+                }
+                Err(e) => {
+                    info!(
+                        "group::sequential => {} {} failed: {}",
+                        self.name,
+                        name,
+                        e.msg()
+                    );
+                    members[index] = Some(member_result(
+                        name,
+                        constants::GROUP_MEMBER_FAILED,
+                        Some(e.localize(lang)),
+                        Some(elapsed),
+                    ));
+                }
+            }
+        }
+        Err(self.all_failed(&members))
+    }
+
+    //同时查询, 第一个成功的应答, 其余的取消
+    async fn race(
+        &self,
+        request: DnsRequest,
+        options: &QueryOptions,
+        attempts: &mut Vec<QueryAttempt>,
+        lang: Lang,
+    ) -> Result<(DnsResponse, GroupReport), AppError> {
+        let mut pending: FuturesUnordered<_> = (0..self.members.len())
+            .map(|index| self.ask(index, request.clone(), options))
+            .collect();
+        let mut members = vec![None; self.members.len()];
+        while let Some((index, result, elapsed, sent)) = pending.next().await {
+            attempts.extend(sent);
+            let name = &self.members[index].name;
+            match result {
+                Ok(response) => {
+                    members[index] = Some(member_result(
+                        name,
+                        constants::GROUP_MEMBER_ANSWERED,
+                        None,
+                        Some(elapsed),
+                    ));
+                    for (other, result) in members.iter_mut().enumerate() {
+                        if result.is_none() {
+                            *result = Some(member_result(
+                                &self.members[other].name,
+                                constants::GROUP_MEMBER_SLOWER,
+                                Some(i18n::format(
+                                    lang,
+                                    "GROUP_SLOWER_THAN",
+                                    &[
+                                        ("member", name.clone()),
+                                        ("ms", elapsed.as_millis().to_string()),
+                                    ],
+                                )),
+                                None,
+                            ));
+                        }
+                    }
+                    return Ok((response, self.report(index, members, lang)));
+                }
+                Err(e) => {
+                    members[index] = Some(member_result(
+                        name,
+                        constants::GROUP_MEMBER_FAILED,
+                        Some(e.localize(lang)),
+                        Some(elapsed),
+                    ));
+                }
+            }
+        }
+        Err(self.all_failed(&members))
+    }
+
+    //同时查询, quorum 个成员应答一致时返回, 其余的取消
+    async fn quorum(
+        &self,
+        quorum: usize,
+        request: DnsRequest,
+        options: &QueryOptions,
+        attempts: &mut Vec<QueryAttempt>,
+        lang: Lang,
+    ) -> Result<(DnsResponse, GroupReport), AppError> {
+        let mut pending: FuturesUnordered<_> = (0..self.members.len())
+            .map(|index| self.ask(index, request.clone(), options))
+            .collect();
+        let mut members = vec![None; self.members.len()];
+        let mut answers = vec![None; self.members.len()];
+        while let Some((index, result, elapsed, sent)) = pending.next().await {
+            attempts.extend(sent);
+            let name = &self.members[index].name;
+            let response = match result {
+                Ok(response) => response,
+                Err(e) => {
+                    members[index] = Some(member_result(
+                        name,
+                        constants::GROUP_MEMBER_FAILED,
+                        Some(e.localize(lang)),
+                        Some(elapsed),
+                    ));
+                    continue;
+                }
+            };
+            let key = answer_key(&response);
+            answers[index] = Some((key.clone(), elapsed));
+            let agreed = answers
+                .iter()
+                .flatten()
+                .filter(|(other, _)| *other == key)
+                .count();
+            if agreed < quorum {
+                continue;
+            }
+
+            //凑齐 quorum 的成员作为应答方
+            for (other, answer) in answers.iter().enumerate() {
+                let Some((other_key, elapsed)) = answer else {
+                    continue;
+                };
+                let (status, reason) = if other == index {
+                    (constants::GROUP_MEMBER_ANSWERED, None)
+                } else if *other_key == key {
+                    (constants::GROUP_MEMBER_AGREED, None)
+                } else {
+                    (
+                        constants::GROUP_MEMBER_DISAGREED,
+                        Some(i18n::format(
+                            lang,
+                            "GROUP_DISAGREED",
+                            &[("answer", answer_text(other_key))],
+                        )),
+                    )
+                };
+                members[other] = Some(member_result(
+                    &self.members[other].name,
+                    status,
+                    reason,
+                    Some(*elapsed),
+                ));
+            }
+            for (other, result) in members.iter_mut().enumerate() {
+                if result.is_none() {
+                    *result = Some(member_result(
+                        &self.members[other].name,
+                        constants::GROUP_MEMBER_SKIPPED,
+                        Some(i18n::format(
+                            lang,
+                            "GROUP_QUORUM_REACHED",
+                            &[("quorum", quorum.to_string())],
+                        )),
+                        None,
+                    ));
+                }
+            }
+            return Ok((response, self.report(index, members, lang)));
+        }
+
+        //未凑齐 quorum, 有应答的成员附上各自的应答
+        for (index, answer) in answers.iter().enumerate() {
+            if let Some((key, elapsed)) = answer {
+                members[index] = Some(member_result(
+                    &self.members[index].name,
+                    constants::GROUP_MEMBER_ANSWERED,
+                    Some(i18n::format(
+                        lang,
+                        "GROUP_ANSWER",
+                        &[("answer", answer_text(key))],
+                    )),
+                    Some(*elapsed),
+                ));
+            }
+        }
+        Err(AppError::new(ErrorCode::DnsQuorumFailed)
+            .arg("group", &self.name)
+            .arg("quorum", quorum)
+            .arg("members", member_summary(&members)))
+    }
+
+    //所有成员都失败, 错误中附上每个成员的原因
+    fn all_failed(&self, members: &[Option<GroupMemberResult>]) -> AppError {
+        AppError::new(ErrorCode::DnsGroupFailed)
+            .arg("group", &self.name)
+            .arg("members", member_summary(members))
+    }
+}
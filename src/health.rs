@@ -0,0 +1,244 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsQuery, QueryOptions, ReadyCheck, ReadyReport, SelftestReport, SelftestResult,
+};
+use crate::i18n::Lang;
+use crate::{anchor, dns, group, retry, update};
+use futures_util::future::{self, FutureExt};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tera::Tera;
+
+//页面与错误页都要用到的模板
+const REQUIRED_TEMPLATES: [&str; 8] = [
+    "index.html",
+    constants::MONITORS_HTML,
+    constants::PERMALINK_HTML,
+    constants::BULK_HTML,
+    constants::UPDATE_HTML,
+    constants::ZONE_SYNC_HTML,
+    constants::NOT_FOUND_HTML,
+    constants::INTERNAL_SERVER_HTML,
+];
+
+//SELFTEST_SERVERS, 为空时为全部内置 dns 服务器, 按 key 排序
+fn selftest_servers() -> Vec<String> {
+    if !constants::SELFTEST_SERVERS.is_empty() {
+        return constants::SELFTEST_SERVERS.clone();
+    }
+    let mut servers: Vec<String> = constants::DNS_MAP.keys().cloned().collect();
+    servers.sort();
+    servers
+}
+
+fn selftest_query(dns_server: &str) -> DnsQuery {
+    DnsQuery {
+        domain: constants::SELFTEST_DOMAIN.clone(),
+        record_type: constants::SELFTEST_RECORD_TYPE.clone(),
+        dns_server: dns_server.to_string(),
+        client_subnet: None,
+        options: QueryOptions::default(),
+    }
+}
+
+fn check(name: &str, problems: Vec<String>) -> ReadyCheck {
+    ReadyCheck {
+        name: name.to_string(),
+        ok: problems.is_empty(),
+        detail: if problems.is_empty() {
+            None
+        } else {
+            Some(problems.join("; "))
+        },
+    }
+}
+
+fn check_templates(tera: &Tera) -> ReadyCheck {
+    let loaded: Vec<&str> = tera.get_template_names().collect();
+    let problems = REQUIRED_TEMPLATES
+        .iter()
+        .filter(|name| !loaded.contains(name))
+        .map(|name| format!("缺少模板 {}", name))
+        .collect();
+    check(constants::READY_CHECK_TEMPLATES, problems)
+}
+
+fn check_config() -> ReadyCheck {
+    let mut problems = vec![];
+    if *constants::QUERY_TIMEOUT == 0 {
+        problems.push("QUERY_TIMEOUT 必须大于 0".to_string());
+    }
+    if *constants::READY_CANARY_TIMEOUT_MS == 0 {
+        problems.push("READY_CANARY_TIMEOUT_MS 必须大于 0".to_string());
+    }
+    if *constants::BULK_CONCURRENCY == 0 || *constants::BULK_MAX_RUNNING == 0 {
+        problems.push("BULK_CONCURRENCY / BULK_MAX_RUNNING 必须大于 0".to_string());
+    }
+    if constants::DNS_MAP.is_empty() {
+        problems.push("没有内置 dns 服务器".to_string());
+    }
+    for (key, addr) in constants::DNS_MAP.iter() {
+        if addr.parse::<IpAddr>().is_err() {
+            problems.push(format!("dns 服务器 {} 地址不正确: {}", key, addr));
+        }
+    }
+    for dns_server in constants::SELFTEST_SERVERS.iter() {
+        if let Err(e) = dns::resolve_dns_addr(dns_server) {
+            problems.push(format!("SELFTEST_SERVERS: {}", e.msg()));
+        }
+    }
+    if let Err(e) = dns::check_domain_and_type(
+        &constants::SELFTEST_DOMAIN,
+        &constants::SELFTEST_RECORD_TYPE,
+    ) {
+        problems.push(format!(
+            "SELFTEST_DOMAIN / SELFTEST_RECORD_TYPE: {}",
+            e.msg()
+        ));
+    }
+    problems.extend(anchor::ANCHORS.errors.iter().cloned());
+    problems.extend(group::GROUPS.errors.iter().cloned());
+    problems.extend(retry::TIMEOUTS.errors.iter().cloned());
+    problems.extend(update::KEYS.errors.iter().cloned());
+    check(constants::READY_CHECK_CONFIG, problems)
+}
+
+//任意一个自检 dns 服务器在超时时间内应答即可
+async fn check_resolver() -> ReadyCheck {
+    let timeout = Duration::from_millis(*constants::READY_CANARY_TIMEOUT_MS);
+    let queries: Vec<_> = selftest_servers()
+        .into_iter()
+        .map(|dns_server| {
+            async move {
+                let started = Instant::now();
+                match tokio::time::timeout(
+                    timeout,
+                    dns::v1_query(selftest_query(&dns_server), Lang::default()),
+                )
+                .await
+                {
+                    Ok(Ok(_)) => Ok((dns_server, started.elapsed().as_millis())),
+                    Ok(Err(e)) => Err(e.msg()),
+                    Err(_) => Err(format!("{} 超时", dns_server)),
+                }
+            }
+            .boxed()
+        })
+        .collect();
+    if queries.is_empty() {
+        return check(
+            constants::READY_CHECK_RESOLVER,
+            vec!["没有自检 dns 服务器".to_string()],
+        );
+    }
+    match future::select_ok(queries).await {
+        Ok(((dns_server, elapsed), _)) => ReadyCheck {
+            name: constants::READY_CHECK_RESOLVER.to_string(),
+            ok: true,
+            detail: Some(format!("{} {}ms", dns_server, elapsed)),
+        },
+        Err(_) => check(
+            constants::READY_CHECK_RESOLVER,
+            vec![format!(
+                "没有 dns 服务器在 {}ms 内应答",
+                *constants::READY_CANARY_TIMEOUT_MS
+            )],
+        ),
+    }
+}
+
+pub async fn ready(tera: &Tera) -> ReadyReport {
+    let checks = vec![
+        check_templates(tera),
+        check_config(),
+        check_resolver().await,
+    ];
+    ReadyReport {
+        ready: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+//未通过的检查项, 用于 NOT_READY 文案
+pub fn failed_checks(report: &ReadyReport) -> String {
+    report
+        .checks
+        .iter()
+        .filter(|check| !check.ok)
+        .map(|check| check.name.clone())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn selftest_one(dns_server: String, lang: Lang) -> SelftestResult {
+    let dns_addr = dns::resolve_dns_addr(&dns_server).unwrap_or_default();
+    let started = Instant::now();
+    let result = dns::v1_query(selftest_query(&dns_server), lang).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(result) => SelftestResult {
+            dns_server,
+            dns_addr,
+            reachable: true,
+            correct: result
+                .dns_record
+                .iter()
+                .any(|record| constants::SELFTEST_EXPECTED.contains(&record.value)),
+            duration_ms,
+            code: constants::OKAY_CODE.to_string(),
+            msg: constants::OKAY_MSG.to_string(),
+            dns_record: result.dns_record,
+        },
+        Err(e) => SelftestResult {
+            dns_server,
+            dns_addr,
+            reachable: false,
+            correct: false,
+            duration_ms,
+            code: e.code().to_string(),
+            msg: e.localize(lang),
+            dns_record: vec![],
+        },
+    }
+}
+
+//对每个自检 dns 服务器并发查询已知答案的域名
+pub async fn selftest(lang: Lang) -> SelftestReport {
+    let handles: Vec<_> = selftest_servers()
+        .into_iter()
+        .map(|dns_server| tokio::spawn(selftest_one(dns_server, lang)))
+        .collect();
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            Err(e) => log::error!("selftest task failed: {}", AppError::internal(e)),
+        }
+    }
+    SelftestReport {
+        domain: constants::SELFTEST_DOMAIN.clone(),
+        record_type: constants::SELFTEST_RECORD_TYPE.to_uppercase(),
+        expected: constants::SELFTEST_EXPECTED.clone(),
+        total: results.len(),
+        reachable: results.iter().filter(|result| result.reachable).count(),
+        correct: results.iter().filter(|result| result.correct).count(),
+        results,
+    }
+}
@@ -0,0 +1,555 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsUpdateRequest, HistoryQuery, HistoryRecord, UpdateAuditQuery, UpdateAuditRecord,
+};
+use crate::monitor::now_millis;
+use crate::sse;
+use actix_web::web;
+use actix_web::web::Bytes;
+use futures_util::{Stream, stream};
+use log::{error, info};
+use rand::Rng;
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Row, params, params_from_iter};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS query_history (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at    INTEGER NOT NULL,
+    requester     TEXT    NOT NULL,
+    kind          TEXT    NOT NULL,
+    domain        TEXT    NOT NULL,
+    record_type   TEXT    NOT NULL,
+    dns_server    TEXT    NOT NULL,
+    client_subnet TEXT,
+    duration_ms   INTEGER NOT NULL,
+    code          TEXT    NOT NULL,
+    msg           TEXT    NOT NULL,
+    result        TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_query_history_created_at ON query_history (created_at);
+CREATE INDEX IF NOT EXISTS idx_query_history_domain ON query_history (domain);
+CREATE TABLE IF NOT EXISTS update_audit (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at    INTEGER NOT NULL,
+    requester     TEXT    NOT NULL,
+    key_name      TEXT    NOT NULL,
+    zone          TEXT    NOT NULL,
+    dns_server    TEXT    NOT NULL,
+    action        TEXT    NOT NULL,
+    name          TEXT    NOT NULL,
+    record_type   TEXT    NOT NULL,
+    request       TEXT    NOT NULL,
+    response_code TEXT,
+    code          TEXT    NOT NULL,
+    msg           TEXT    NOT NULL,
+    duration_ms   INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_update_audit_zone ON update_audit (zone);
+";
+
+//旧库升级: 分享链接
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "share_id",
+        "ALTER TABLE query_history ADD COLUMN share_id TEXT",
+    ),
+    (
+        "expires_at",
+        "ALTER TABLE query_history ADD COLUMN expires_at INTEGER",
+    ),
+];
+
+//升级前的记录没有分享链接
+const INDEXES: &str = "
+CREATE UNIQUE INDEX IF NOT EXISTS idx_query_history_share_id ON query_history (share_id);
+CREATE INDEX IF NOT EXISTS idx_query_history_expires_at ON query_history (expires_at);
+";
+
+const COLUMNS: &str = "id, created_at, requester, kind, domain, record_type, dns_server, \
+                       client_subnet, duration_ms, code, msg, result, share_id, expires_at";
+
+const AUDIT_COLUMNS: &str = "id, created_at, requester, key_name, zone, dns_server, action, name, \
+                             record_type, request, response_code, code, msg, duration_ms";
+
+const SHARE_ID_CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+//查询历史, 保存在 sqlite, 新记录同时推送给实时订阅方
+pub struct HistoryStore {
+    conn: Arc<Mutex<Connection>>,
+    live: broadcast::Sender<HistoryRecord>,
+}
+
+//一次待保存的查询
+pub struct NewHistory {
+    pub requester: String,
+    pub kind: &'static str,
+    pub domain: String,
+    pub record_type: String,
+    pub dns_server: String,
+    pub client_subnet: Option<String>,
+}
+
+fn map_record(row: &Row<'_>) -> rusqlite::Result<HistoryRecord> {
+    let result: Option<String> = row.get(11)?;
+    Ok(HistoryRecord {
+        id: row.get(0)?,
+        created_at: row.get::<_, i64>(1)? as u64,
+        requester: row.get(2)?,
+        kind: row.get(3)?,
+        domain: row.get(4)?,
+        record_type: row.get(5)?,
+        dns_server: row.get(6)?,
+        client_subnet: row.get(7)?,
+        duration_ms: row.get::<_, i64>(8)? as u64,
+        code: row.get(9)?,
+        msg: row.get(10)?,
+        result: result.and_then(|json| serde_json::from_str(&json).ok()),
+        share_id: row.get(12)?,
+        expires_at: row.get::<_, Option<i64>>(13)?.map(|v| v as u64),
+    })
+}
+
+fn map_audit(row: &Row<'_>) -> rusqlite::Result<UpdateAuditRecord> {
+    let request: String = row.get(9)?;
+    Ok(UpdateAuditRecord {
+        id: row.get(0)?,
+        created_at: row.get::<_, i64>(1)? as u64,
+        requester: row.get(2)?,
+        key_name: row.get(3)?,
+        zone: row.get(4)?,
+        dns_server: row.get(5)?,
+        action: row.get(6)?,
+        name: row.get(7)?,
+        record_type: row.get(8)?,
+        request: serde_json::from_str(&request).unwrap_or_default(),
+        response_code: row.get(10)?,
+        code: row.get(11)?,
+        msg: row.get(12)?,
+        duration_ms: row.get::<_, i64>(13)? as u64,
+    })
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('query_history')")?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (column, sql) in MIGRATIONS {
+        if !columns.iter().any(|c| c == column) {
+            conn.execute(sql, [])?;
+        }
+    }
+    conn.execute_batch(INDEXES)
+}
+
+fn lock(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+//LIKE 参数转义, 用户输入的 % _ \ 按普通字符匹配, 配合 ESCAPE '\'
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+//随机短 id, 不可按顺序猜出其他人的查询
+fn new_share_id() -> String {
+    let mut rng = rand::rng();
+    (0..constants::SHARE_ID_LEN)
+        .map(|_| SHARE_ID_CHARS[rng.random_range(0..SHARE_ID_CHARS.len())] as char)
+        .collect()
+}
+
+impl HistoryStore {
+    pub fn open(path: &str) -> Result<Self, AppError> {
+        if let Some(parent) = Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::db(e.into()))?;
+        }
+        let conn = Connection::open(path).map_err(|e| AppError::db(e.into()))?;
+        conn.execute_batch(SCHEMA)
+            .and_then(|_| migrate(&conn))
+            .map_err(|e| AppError::db(e.into()))?;
+        Ok(HistoryStore {
+            conn: Arc::new(Mutex::new(conn)),
+            live: broadcast::channel(constants::HISTORY_STREAM_CAPACITY).0,
+        })
+    }
+
+    //sqlite 调用会阻塞, 放到阻塞线程池执行, 不占用 actix worker
+    async fn run<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, AppError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        web::block(move || f(&lock(&conn)))
+            .await
+            .map_err(AppError::internal)?
+    }
+
+    //订阅之后保存的记录
+    pub fn subscribe(&self) -> broadcast::Receiver<HistoryRecord> {
+        self.live.subscribe()
+    }
+
+    //保存失败只记录日志, 不影响查询本身, 成功返回分享链接 id
+    pub async fn save<T: Serialize>(
+        &self,
+        entry: NewHistory,
+        duration: Duration,
+        result: &Result<T, AppError>,
+    ) -> Option<String> {
+        let (code, msg, json) = match result {
+            Ok(data) => (
+                constants::OKAY_CODE.to_string(),
+                constants::OKAY_MSG.to_string(),
+                serde_json::to_string(data).ok(),
+            ),
+            Err(e) => (e.code().to_string(), e.msg(), None),
+        };
+        let created_at = now_millis();
+        //有效期按保存时的配置计算, 之后修改 PERMALINK_TTL_DAYS 不影响已有链接
+        let expires_at = match *constants::PERMALINK_TTL_DAYS {
+            0 => None,
+            days => Some(created_at + days * 24 * 60 * 60 * 1000),
+        };
+        let share_id = new_share_id();
+        let record = HistoryRecord {
+            id: 0,
+            created_at,
+            requester: entry.requester,
+            kind: entry.kind.to_string(),
+            domain: entry.domain,
+            record_type: entry.record_type.to_uppercase(),
+            dns_server: entry.dns_server,
+            client_subnet: entry.client_subnet,
+            duration_ms: duration.as_millis() as u64,
+            code,
+            msg,
+            result: json
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok()),
+            share_id: Some(share_id.clone()),
+            expires_at,
+        };
+        let row = record.clone();
+        let saved = self
+            .run(move |conn| {
+                conn.execute(
+                    "INSERT INTO query_history (created_at, requester, kind, domain, record_type, \
+                     dns_server, client_subnet, duration_ms, code, msg, result, share_id, \
+                     expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        row.created_at as i64,
+                        row.requester,
+                        row.kind,
+                        row.domain,
+                        row.record_type,
+                        row.dns_server,
+                        row.client_subnet,
+                        row.duration_ms as i64,
+                        row.code,
+                        row.msg,
+                        json,
+                        row.share_id,
+                        row.expires_at.map(|v| v as i64),
+                    ],
+                )
+                .map(|_| conn.last_insert_rowid())
+                .map_err(|e| AppError::db(e.into()))
+            })
+            .await;
+        match saved {
+            Ok(id) => {
+                //没有订阅方时发送失败, 忽略
+                let _ = self.live.send(HistoryRecord { id, ..record });
+                Some(share_id)
+            }
+            Err(e) => {
+                error!("history::save => {}", e.msg());
+                None
+            }
+        }
+    }
+
+    //按条件分页查询, 返回 (总数, 当前页), 新的在前
+    pub async fn search(
+        &self,
+        query: &HistoryQuery,
+    ) -> Result<(i64, Vec<HistoryRecord>), AppError> {
+        let mut conditions: Vec<&str> = vec![];
+        let mut values: Vec<Value> = vec![];
+        let mut text_filter = |value: &Option<String>, condition: &'static str, upper: bool| {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                conditions.push(condition);
+                values.push(Value::Text(if upper {
+                    value.to_uppercase()
+                } else {
+                    value.to_string()
+                }));
+            }
+        };
+        let domain = query.domain.as_deref().map(escape_like);
+        text_filter(&domain, "domain LIKE '%' || ? || '%' ESCAPE '\\'", false);
+        text_filter(&query.record_type, "record_type = ?", true);
+        text_filter(&query.dns_server, "dns_server = ?", false);
+        text_filter(&query.code, "code = ?", false);
+        if let Some(start) = query.start {
+            conditions.push("created_at >= ?");
+            values.push(Value::Integer(start as i64));
+        }
+        if let Some(end) = query.end {
+            conditions.push("created_at <= ?");
+            values.push(Value::Integer(end as i64));
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let size = query
+            .size
+            .unwrap_or(constants::HISTORY_PAGE_SIZE)
+            .clamp(1, constants::HISTORY_MAX_PAGE_SIZE);
+        let page = query.page.unwrap_or(1).max(1);
+
+        self.run(move |conn| {
+            let total: i64 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM query_history {}", where_clause),
+                    params_from_iter(values.iter()),
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::db(e.into()))?;
+
+            values.push(Value::Integer(size as i64));
+            values.push(Value::Integer((page as i64 - 1) * size as i64));
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM query_history {} ORDER BY id DESC LIMIT ? OFFSET ?",
+                    COLUMNS, where_clause
+                ))
+                .map_err(|e| AppError::db(e.into()))?;
+            let records = stmt
+                .query_map(params_from_iter(values.iter()), map_record)
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| AppError::db(e.into()))?;
+            Ok((total, records))
+        })
+        .await
+    }
+
+    //DNS UPDATE 发送前写入审计日志, 写入失败时不发送, zone 与 name 为规范化后的名称
+    pub async fn begin_audit(
+        &self,
+        requester: &str,
+        request: &DnsUpdateRequest,
+        zone: &str,
+        name: &str,
+    ) -> Result<i64, AppError> {
+        let json = serde_json::to_string(request).map_err(AppError::internal)?;
+        let row = (
+            requester.to_string(),
+            request.key_name.clone(),
+            zone.to_string(),
+            request.dns_server.clone(),
+            request.action.clone(),
+            name.to_string(),
+            request.record_type.to_uppercase(),
+        );
+        self.run(move |conn| {
+            let (requester, key_name, zone, dns_server, action, name, record_type) = row;
+            conn.execute(
+                "INSERT INTO update_audit (created_at, requester, key_name, zone, dns_server, \
+                 action, name, record_type, request, code, msg, duration_ms) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, 0)",
+                params![
+                    now_millis() as i64,
+                    requester,
+                    key_name,
+                    zone,
+                    dns_server,
+                    action,
+                    name,
+                    record_type,
+                    json,
+                    constants::UPDATE_AUDIT_PENDING,
+                    constants::UPDATE_AUDIT_PENDING,
+                ],
+            )
+            .map(|_| conn.last_insert_rowid())
+            .map_err(|e| AppError::db(e.into()))
+        })
+        .await
+    }
+
+    //收到应答或失败后更新审计日志的结果
+    pub async fn finish_audit(
+        &self,
+        id: i64,
+        response_code: Option<&str>,
+        duration: Duration,
+        result: &Result<(), AppError>,
+    ) {
+        let (code, msg) = match result {
+            Ok(()) => (
+                constants::OKAY_CODE.to_string(),
+                constants::OKAY_MSG.to_string(),
+            ),
+            Err(e) => (e.code().to_string(), e.msg()),
+        };
+        let response_code = response_code.map(str::to_string);
+        let duration_ms = duration.as_millis() as i64;
+        let updated = self
+            .run(move |conn| {
+                conn.execute(
+                    "UPDATE update_audit SET response_code = ?1, code = ?2, msg = ?3, \
+                     duration_ms = ?4 WHERE id = ?5",
+                    params![response_code, code, msg, duration_ms, id],
+                )
+                .map_err(|e| AppError::db(e.into()))
+            })
+            .await;
+        if let Err(e) = updated {
+            error!("history::finish_audit => {} {}", id, e.msg());
+        }
+    }
+
+    //审计日志分页, 新的在前
+    pub async fn audits(
+        &self,
+        query: &UpdateAuditQuery,
+    ) -> Result<(i64, Vec<UpdateAuditRecord>), AppError> {
+        let zone = query
+            .zone
+            .as_deref()
+            .map(|zone| zone.trim().trim_end_matches('.').to_lowercase())
+            .unwrap_or_default();
+        let size = query
+            .size
+            .unwrap_or(constants::HISTORY_PAGE_SIZE)
+            .clamp(1, constants::HISTORY_MAX_PAGE_SIZE);
+        let page = query.page.unwrap_or(1).max(1);
+
+        self.run(move |conn| {
+            let total: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM update_audit WHERE ?1 = '' OR zone = ?1",
+                    params![zone],
+                    |row| row.get(0),
+                )
+                .map_err(|e| AppError::db(e.into()))?;
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM update_audit WHERE ?1 = '' OR zone = ?1 \
+                     ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+                    AUDIT_COLUMNS
+                ))
+                .map_err(|e| AppError::db(e.into()))?;
+            let records = stmt
+                .query_map(
+                    params![zone, size as i64, (page as i64 - 1) * size as i64],
+                    map_audit,
+                )
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| AppError::db(e.into()))?;
+            Ok((total, records))
+        })
+        .await
+    }
+
+    //让过期的分享链接失效: 清空 share_id 与 expires_at, 查询历史本身保留, 返回失效条数
+    pub async fn expire_share_links(&self, now: u64) -> Result<usize, AppError> {
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE query_history SET share_id = NULL, expires_at = NULL \
+                 WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+                params![now as i64],
+            )
+            .map_err(|e| AppError::db(e.into()))
+        })
+        .await
+    }
+
+    //按分享链接 id 查找, 过期的视为不存在
+    pub async fn find_by_share_id(
+        &self,
+        share_id: &str,
+    ) -> Result<Option<HistoryRecord>, AppError> {
+        let share_id = share_id.to_string();
+        let record = self
+            .run(move |conn| {
+                conn.query_row(
+                    &format!("SELECT {} FROM query_history WHERE share_id = ?1", COLUMNS),
+                    params![share_id],
+                    map_record,
+                )
+                .optional()
+                .map_err(|e| AppError::db(e.into()))
+            })
+            .await?;
+        let now = now_millis();
+        Ok(record.filter(|record| record.expires_at.is_none_or(|expires_at| expires_at > now)))
+    }
+}
+
+//定期让过期的分享链接失效
+pub async fn run_expire_share_links(history: web::Data<HistoryStore>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(constants::SHARE_EXPIRE_INTERVAL));
+    loop {
+        ticker.tick().await;
+        match history.expire_share_links(now_millis()).await {
+            Ok(0) => {}
+            Ok(count) => info!("history::run_expire_share_links => {} expired", count),
+            Err(e) => error!("history::run_expire_share_links => {}", e.msg()),
+        }
+    }
+}
+
+//实时历史推送 (text/event-stream), 每保存一条推送一条, 处理不过来时跳过丢弃的部分
+pub fn live_stream(
+    receiver: broadcast::Receiver<HistoryRecord>,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(record) => return Some((Ok(sse::event("history", &record)), receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -0,0 +1,113 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::http::header::{self, HeaderMap};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+
+//文案目录, 接口错误、模板与前端 js 共用
+const MESSAGES_JSON: &str = include_str!("../html/i18n/messages.json");
+
+lazy_static! {
+    static ref CATALOG: HashMap<String, BTreeMap<String, String>> =
+        serde_json::from_str(MESSAGES_JSON).expect("html/i18n/messages.json 格式不正确");
+}
+
+//支持的语言, 默认中文
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Lang {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::Zh => "zh",
+            Lang::En => "en",
+        }
+    }
+
+    //只看主标签, zh-CN / zh-Hans => zh
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.split(['-', '_']).next().unwrap_or_default() {
+            "zh" => Some(Lang::Zh),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    //Accept-Language 中 q 值最高的支持语言
+    pub fn from_accept_language(value: &str) -> Self {
+        let mut best: Option<(f32, Lang)> = None;
+        for item in value.split(',') {
+            let mut parts = item.split(';');
+            let tag = parts.next().unwrap_or_default().trim().to_lowercase();
+            let q = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let Some(lang) = Self::from_tag(&tag) else {
+                continue;
+            };
+            if q > 0.0 && best.is_none_or(|(best_q, _)| q > best_q) {
+                best = Some((q, lang));
+            }
+        }
+        best.map(|(_, lang)| lang).unwrap_or_default()
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+}
+
+//某个语言的全部文案, 供模板和 js 使用
+pub fn catalog(lang: Lang) -> &'static BTreeMap<String, String> {
+    &CATALOG[lang.as_str()]
+}
+
+//按 key 取文案, 缺失时回退到默认语言, 再回退到 key 本身
+pub fn text(lang: Lang, key: &str) -> String {
+    catalog(lang)
+        .get(key)
+        .or_else(|| catalog(Lang::default()).get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+//替换文案中的 {name} 参数
+pub fn format(lang: Lang, key: &str, args: &[(&str, String)]) -> String {
+    let mut text = text(lang, key);
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+//页面模板的基础 context: lang 与文案目录 i18n
+pub fn context(lang: Lang) -> tera::Context {
+    let mut context = tera::Context::new();
+    context.insert("lang", lang.as_str());
+    context.insert("i18n", catalog(lang));
+    context
+}
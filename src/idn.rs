@@ -0,0 +1,141 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{AppError, ErrorCode, IdnInfo, IdnWarning};
+use idna::uts46::{AsciiDenyList, DnsLength, Hyphens, Uts46};
+use unicode_script::{Script, UnicodeScript};
+use unicode_security::{MixedScript, skeleton};
+
+const ACE_PREFIX: &str = "xn--";
+
+fn strict_to_ascii(domain: &str) -> Option<String> {
+    Uts46::new()
+        .to_ascii(
+            domain.as_bytes(),
+            AsciiDenyList::STD3,
+            Hyphens::Check,
+            DnsLength::Verify,
+        )
+        .ok()
+        .map(|ascii| ascii.into_owned())
+}
+
+//idna 的错误不带原因, 按标签逐个找出失败原因
+fn diagnose(domain: &str) -> AppError {
+    if domain.len() > 253 && domain.is_ascii() {
+        return AppError::new(ErrorCode::IdnaTooLong);
+    }
+    for label in domain.split('.') {
+        if label.is_empty() {
+            return AppError::new(ErrorCode::IdnaEmptyLabel);
+        }
+        if strict_to_ascii(label).is_some() {
+            continue;
+        }
+        let lower = label.to_lowercase();
+        let error = if label.starts_with('-') || label.ends_with('-') {
+            AppError::new(ErrorCode::IdnaHyphenEdge)
+        } else if lower.starts_with(ACE_PREFIX) {
+            AppError::new(ErrorCode::IdnaBadPunycode)
+        } else if lower.get(2..4) == Some("--") {
+            AppError::new(ErrorCode::IdnaHyphen34)
+        } else if let Some(c) = label
+            .chars()
+            .find(|c| strict_to_ascii(&c.to_string()).is_none())
+        {
+            AppError::new(ErrorCode::IdnaDisallowedChar)
+                .arg("char", c)
+                .arg("codepoint", format!("U+{:04X}", c as u32))
+        } else if idna::domain_to_ascii(label).is_ok_and(|ascii| ascii.len() > 63) {
+            AppError::new(ErrorCode::IdnaLabelTooLong)
+        } else {
+            AppError::new(ErrorCode::IdnaInvalid)
+        };
+        return error.arg("label", label);
+    }
+    AppError::new(ErrorCode::IdnaTooLong)
+}
+
+//标签中用到的文字, 不含数字、连字符等通用字符
+fn scripts(label: &str) -> Vec<&'static str> {
+    let mut scripts: Vec<&'static str> = vec![];
+    for c in label.chars() {
+        let script = c.script();
+        if script == Script::Common || script == Script::Inherited {
+            continue;
+        }
+        if !scripts.contains(&script.full_name()) {
+            scripts.push(script.full_name());
+        }
+    }
+    scripts
+}
+
+//混用多种文字, 或者外观与某个纯 ASCII 标签相似的标签, 可能是仿冒域名
+fn confusable_warnings(unicode: &str) -> Vec<IdnWarning> {
+    let mut warnings = vec![];
+    for label in unicode.split('.').filter(|label| !label.is_ascii()) {
+        if !label.is_single_script() {
+            warnings.push(IdnWarning::new(
+                "IDN_MIXED_SCRIPTS",
+                &[
+                    ("label", label.to_string()),
+                    ("scripts", scripts(label).join(", ")),
+                ],
+            ));
+        }
+        let prototype: String = skeleton(label).collect();
+        if prototype.is_ascii() && prototype != label {
+            warnings.push(IdnWarning::new(
+                "IDN_CONFUSABLE",
+                &[("label", label.to_string()), ("prototype", prototype)],
+            ));
+        }
+    }
+    warnings
+}
+
+//域名 => 查询用的 A-label 形式, 纯 ASCII 且没有 punycode 标签时返回 None
+pub fn normalize(domain: &str) -> Result<(String, Option<IdnInfo>), AppError> {
+    let domain = domain.trim();
+    let is_idn = !domain.is_ascii()
+        || domain
+            .split('.')
+            .any(|label| label.to_lowercase().starts_with(ACE_PREFIX));
+    if !is_idn {
+        if !constants::DOMAIN_REG.is_match(domain) {
+            return Err(AppError::new(ErrorCode::DomainInvalid).arg("domain", domain));
+        }
+        return Ok((domain.to_string(), None));
+    }
+
+    let ascii = strict_to_ascii(domain).ok_or_else(|| diagnose(domain))?;
+    if !constants::DOMAIN_REG.is_match(&ascii) {
+        return Err(AppError::new(ErrorCode::DomainInvalid).arg("domain", domain));
+    }
+    let (unicode, _) = idna::domain_to_unicode(&ascii);
+    let warnings = confusable_warnings(&unicode);
+    Ok((
+        ascii.clone(),
+        Some(IdnInfo {
+            unicode,
+            ascii,
+            warnings,
+        }),
+    ))
+}
@@ -0,0 +1,44 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod alert;
+pub mod anchor;
+pub mod bulk;
+pub mod chain;
+pub mod constants;
+pub mod controller;
+pub mod dns;
+pub mod dnssec;
+pub mod export;
+pub mod group;
+pub mod health;
+pub mod history;
+pub mod i18n;
+pub mod idn;
+pub mod monitor;
+pub mod notify;
+pub mod nsec;
+pub mod openapi;
+pub mod permalink;
+pub mod problem;
+pub mod resolver;
+pub mod retry;
+pub mod route;
+pub mod server;
+pub mod sse;
+pub mod update;
+pub mod zonesync;
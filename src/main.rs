@@ -15,20 +15,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod server;
-mod constants;
-mod controller;
-mod route;
-mod dns;
-
-use crate::constants::LOG4RS_INIT_FILE;
-use crate::server::create_server;
+use dns_tool::constants::LOG4RS_INIT_FILE;
+use dns_tool::server::create_server;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
     log4rs::init_file(LOG4RS_INIT_FILE, Default::default()).unwrap();
 
-    let _server = create_server().await?;
-    Ok(())
+    create_server().await
 }
@@ -0,0 +1,485 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::alert::AlertDispatcher;
+use crate::constants;
+use crate::constants::{
+    AppError, DnsQuery, DnsRecordResult, DnsSecQuery, ErrorCode, MonitorAnswer, MonitorChange,
+    MonitorDetail, MonitorJob, MonitorRequest, QueryOptions,
+};
+use crate::i18n::Lang;
+use crate::{alert, dns, group};
+use actix_web::web::Data;
+use log::{error, info};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+//监控任务存储, 所有数据只保存在内存中
+pub struct MonitorStore {
+    state: Mutex<MonitorState>,
+    alerts: AlertDispatcher,
+}
+
+#[derive(Default)]
+struct MonitorState {
+    next_id: u64,
+    entries: BTreeMap<u64, MonitorEntry>,
+    changes: VecDeque<MonitorChange>,
+}
+
+struct MonitorEntry {
+    job: MonitorJob,
+    next_run: Instant,
+    running: bool,
+    answers: VecDeque<MonitorAnswer>,
+    resolvers: HashMap<String, ResolverState>,
+}
+
+//某个 dns 服务器上一次的结果
+struct ResolverState {
+    last: MonitorAnswer,
+    //最近一次成功的应答, 查询失败期间的变更在恢复后与它比较
+    last_ok: Option<MonitorAnswer>,
+    //递归解析器的 TTL 随缓存倒计时递减, 记录观测到的最大值用于判断 TTL 是否被调大
+    ttl_ceiling: u32,
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn check_request(request: &MonitorRequest) -> Result<(), AppError> {
+    dns::check_domain_and_type(&request.domain, &request.record_type)?;
+    if request.resolvers.is_empty() {
+        return Err(AppError::new(ErrorCode::DnsServerEmpty));
+    }
+    for resolver in &request.resolvers {
+        dns::resolve_dns_addr(resolver)?;
+    }
+    if request.interval < *constants::MONITOR_MIN_INTERVAL {
+        return Err(AppError::new(ErrorCode::MonitorIntervalTooShort)
+            .arg("min", *constants::MONITOR_MIN_INTERVAL));
+    }
+    alert::check_channels(&request.alerts)
+}
+
+fn not_found(id: u64) -> AppError {
+    AppError::new(ErrorCode::MonitorNotFound).arg("id", id)
+}
+
+impl MonitorStore {
+    pub fn new() -> Self {
+        MonitorStore {
+            state: Mutex::new(MonitorState {
+                next_id: 1,
+                ..Default::default()
+            }),
+            alerts: AlertDispatcher::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MonitorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn create(&self, request: MonitorRequest) -> Result<MonitorJob, AppError> {
+        check_request(&request)?;
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        let job = MonitorJob {
+            id,
+            domain: request.domain,
+            record_type: request.record_type.to_uppercase(),
+            resolvers: request.resolvers,
+            interval: request.interval,
+            alerts: request.alerts,
+            created_at: now_millis(),
+            last_run_at: None,
+        };
+        state.entries.insert(
+            id,
+            MonitorEntry {
+                job: job.clone(),
+                next_run: Instant::now(),
+                running: false,
+                answers: VecDeque::new(),
+                resolvers: HashMap::new(),
+            },
+        );
+        info!(
+            "monitor::create => {} {} {}",
+            id, job.domain, job.record_type
+        );
+        Ok(job)
+    }
+
+    pub fn update(&self, id: u64, request: MonitorRequest) -> Result<MonitorJob, AppError> {
+        check_request(&request)?;
+        let mut state = self.lock();
+        let entry = state.entries.get_mut(&id).ok_or_else(|| not_found(id))?;
+        let record_type = request.record_type.to_uppercase();
+        //查询目标变了, 之前的结果不再可比
+        if entry.job.domain != request.domain || entry.job.record_type != record_type {
+            entry.resolvers.clear();
+            entry.answers.clear();
+        } else {
+            entry
+                .resolvers
+                .retain(|resolver, _| request.resolvers.contains(resolver));
+        }
+        entry.job.domain = request.domain;
+        entry.job.record_type = record_type;
+        entry.job.resolvers = request.resolvers;
+        entry.job.interval = request.interval;
+        entry.job.alerts = request.alerts;
+        entry.next_run = Instant::now();
+        Ok(entry.job.clone())
+    }
+
+    pub fn delete(&self, id: u64) -> Result<(), AppError> {
+        let mut state = self.lock();
+        state.entries.remove(&id).ok_or_else(|| not_found(id))?;
+        info!("monitor::delete => {}", id);
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<MonitorJob> {
+        self.lock()
+            .entries
+            .values()
+            .map(|entry| entry.job.clone())
+            .collect()
+    }
+
+    pub fn detail(&self, id: u64) -> Result<MonitorDetail, AppError> {
+        let state = self.lock();
+        let entry = state.entries.get(&id).ok_or_else(|| not_found(id))?;
+        Ok(MonitorDetail {
+            job: entry.job.clone(),
+            answers: entry.answers.iter().rev().cloned().collect(),
+            changes: state
+                .changes
+                .iter()
+                .rev()
+                .filter(|change| change.monitor_id == id)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    //最近的变更事件, 新的在前
+    pub fn changes(&self, limit: usize) -> Vec<MonitorChange> {
+        self.lock()
+            .changes
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    //取出到期的任务并标记为执行中, 避免同一任务重叠执行
+    fn take_due(&self, now: Instant) -> Vec<MonitorJob> {
+        let mut state = self.lock();
+        state
+            .entries
+            .values_mut()
+            .filter(|entry| !entry.running && entry.next_run <= now)
+            .map(|entry| {
+                entry.running = true;
+                entry.next_run = now + Duration::from_secs(entry.job.interval);
+                entry.job.clone()
+            })
+            .collect()
+    }
+
+    fn finish(&self, id: u64) {
+        if let Some(entry) = self.lock().entries.get_mut(&id) {
+            entry.running = false;
+            entry.job.last_run_at = Some(now_millis());
+        }
+    }
+
+    //保存一次查询结果, 与该 dns 服务器上一次结果对比, 返回新产生的变更事件
+    pub fn record(&self, job: &MonitorJob, answer: MonitorAnswer) -> Vec<MonitorChange> {
+        let mut state = self.lock();
+        let Some(entry) = state.entries.get_mut(&job.id) else {
+            //执行期间被删除
+            return vec![];
+        };
+        //执行期间被修改为其他查询目标
+        if entry.job.domain != job.domain || entry.job.record_type != job.record_type {
+            return vec![];
+        }
+
+        let mut changes = vec![];
+        match entry.resolvers.get_mut(&answer.dns_server) {
+            Some(previous) => {
+                for (kind, before, after) in diff_answer(previous, &answer) {
+                    changes.push(MonitorChange {
+                        monitor_id: job.id,
+                        domain: job.domain.clone(),
+                        record_type: job.record_type.clone(),
+                        dns_server: answer.dns_server.clone(),
+                        changed_at: answer.checked_at,
+                        kind: kind.to_string(),
+                        before,
+                        after,
+                    });
+                }
+                if answer.error.is_none() {
+                    let same = previous
+                        .last_ok
+                        .as_ref()
+                        .is_some_and(|last_ok| rrset(last_ok) == rrset(&answer));
+                    previous.ttl_ceiling = if same {
+                        previous.ttl_ceiling.max(max_ttl(&answer))
+                    } else {
+                        max_ttl(&answer)
+                    };
+                    previous.last_ok = Some(answer.clone());
+                }
+                previous.last = answer.clone();
+            }
+            None => {
+                entry.resolvers.insert(
+                    answer.dns_server.clone(),
+                    ResolverState {
+                        ttl_ceiling: max_ttl(&answer),
+                        last_ok: answer.error.is_none().then(|| answer.clone()),
+                        last: answer.clone(),
+                    },
+                );
+            }
+        }
+
+        entry.answers.push_back(answer);
+        while entry.answers.len() > *constants::MONITOR_HISTORY_SIZE {
+            entry.answers.pop_front();
+        }
+        for change in &changes {
+            info!(
+                "monitor::change => {} {} {} {}: {} -> {}",
+                change.monitor_id,
+                change.dns_server,
+                change.domain,
+                change.kind,
+                change.before,
+                change.after
+            );
+            state.changes.push_back(change.clone());
+        }
+        while state.changes.len() > *constants::MONITOR_MAX_CHANGES {
+            state.changes.pop_front();
+        }
+        changes
+    }
+}
+
+impl Default for MonitorStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//按值排序后的 RRset, 顺序不同不算变更
+fn rrset(answer: &MonitorAnswer) -> Vec<&str> {
+    let mut values: Vec<&str> = answer
+        .dns_record
+        .iter()
+        .map(|record| record.value.as_str())
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+fn max_ttl(answer: &MonitorAnswer) -> u32 {
+    answer
+        .dns_record
+        .iter()
+        .map(|record| record.ttl)
+        .max()
+        .unwrap_or(0)
+}
+
+fn ttls(records: &[DnsRecordResult]) -> String {
+    records
+        .iter()
+        .map(|record| record.ttl.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn diff_answer(
+    previous: &ResolverState,
+    answer: &MonitorAnswer,
+) -> Vec<(&'static str, String, String)> {
+    let mut diffs = vec![];
+    if previous.last.error != answer.error {
+        diffs.push((
+            constants::MONITOR_CHANGE_ERROR,
+            previous.last.error.clone().unwrap_or_default(),
+            answer.error.clone().unwrap_or_default(),
+        ));
+    }
+    //查询失败时没有可比较的记录, 恢复后与失败前最后一次成功的应答比较
+    if answer.error.is_some() {
+        return diffs;
+    }
+    let Some(last) = &previous.last_ok else {
+        return diffs;
+    };
+
+    let before = rrset(last);
+    let after = rrset(answer);
+    if before != after {
+        diffs.push((
+            constants::MONITOR_CHANGE_RRSET,
+            before.join(" "),
+            after.join(" "),
+        ));
+    } else if answer.authoritative && last.authoritative {
+        //权威应答的 TTL 是准确值, 直接比较
+        if ttls(&last.dns_record) != ttls(&answer.dns_record) {
+            diffs.push((
+                constants::MONITOR_CHANGE_TTL,
+                ttls(&last.dns_record),
+                ttls(&answer.dns_record),
+            ));
+        }
+    } else if max_ttl(answer) > previous.ttl_ceiling {
+        //缓存倒计时只会让 TTL 变小, 超过观测到的最大值说明 TTL 被调大了
+        diffs.push((
+            constants::MONITOR_CHANGE_TTL,
+            previous.ttl_ceiling.to_string(),
+            max_ttl(answer).to_string(),
+        ));
+    }
+
+    if last.dnssec != answer.dnssec {
+        diffs.push((
+            constants::MONITOR_CHANGE_DNSSEC,
+            last.dnssec.clone(),
+            answer.dnssec.clone(),
+        ));
+    }
+    diffs
+}
+
+//一次监控查询: 普通查询取 RRset 与 TTL, dnssec 查询取验证状态
+pub async fn check_resolver(job: MonitorJob, dns_server: String) -> MonitorAnswer {
+    let query = dns::v1_query(
+        DnsQuery {
+            domain: job.domain.clone(),
+            record_type: job.record_type.clone(),
+            dns_server: dns_server.clone(),
+            client_subnet: None,
+            options: QueryOptions::default(),
+        },
+        Lang::default(),
+    );
+    //只比较验证状态, 不使用否定证明的说明文字; 解析器组没有单独的地址, 不做 dnssec 查询
+    let is_group = group::GROUPS.find(&dns_server).is_some();
+    let sec_query = async {
+        if is_group {
+            return None;
+        }
+        let query = DnsSecQuery {
+            domain: job.domain.clone(),
+            dns_server: dns_server.clone(),
+            record_type: job.record_type.clone(),
+        };
+        Some(dns::v1_query_dnssec(query, Lang::default()).await)
+    };
+    let (result, sec_result) = tokio::join!(query, sec_query);
+
+    let dnssec = match sec_result {
+        None => "Unsupported".to_string(),
+        Some(Ok(sec)) => sec
+            .dns_record
+            .iter()
+            .filter(|record| record.record_type == job.record_type)
+            .map(|record| record.proof.clone())
+            .min_by_key(|proof| proof_rank(proof))
+            .unwrap_or_else(|| "None".to_string()),
+        Some(Err(_)) => "Error".to_string(),
+    };
+
+    match result {
+        Ok(result) => MonitorAnswer {
+            dns_server,
+            checked_at: now_millis(),
+            dns_record: result.dns_record,
+            authoritative: result.authoritative,
+            dnssec,
+            error: None,
+        },
+        Err(e) => MonitorAnswer {
+            dns_server,
+            checked_at: now_millis(),
+            dns_record: vec![],
+            authoritative: false,
+            dnssec,
+            error: Some(e.msg()),
+        },
+    }
+}
+
+//多条记录取最差的验证结果
+fn proof_rank(proof: &str) -> u8 {
+    match proof {
+        "Bogus" => 0,
+        "Indeterminate" => 1,
+        "Insecure" => 2,
+        _ => 3,
+    }
+}
+
+async fn run_job(store: Data<MonitorStore>, job: MonitorJob) {
+    let handles: Vec<_> = job
+        .resolvers
+        .iter()
+        .map(|dns_server| tokio::spawn(check_resolver(job.clone(), dns_server.clone())))
+        .collect();
+    for handle in handles {
+        match handle.await {
+            Ok(answer) => {
+                let changes = store.record(&job, answer.clone());
+                store.alerts.dispatch(&job, changes, &answer);
+            }
+            Err(e) => error!("monitor::run_job => {} {:?}", job.id, e),
+        }
+    }
+    store.finish(job.id);
+}
+
+//监控调度, 每秒检查一次到期任务
+pub async fn run_scheduler(store: Data<MonitorStore>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        for job in store.take_due(Instant::now()) {
+            tokio::spawn(run_job(store.clone(), job));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{AppError, ErrorCode, NotifyReport, NotifyRequest, NotifySecondary};
+use crate::dns;
+use crate::i18n::Lang;
+use crate::update;
+use futures_util::future;
+use hickory_client::proto::dnssec::tsig::TSigner;
+use hickory_client::proto::op::{Message, MessageType, OpCode, Query};
+use hickory_client::proto::rr::{Name, RData, Record, RecordType};
+use hickory_client::proto::xfer::DnsResponse;
+use log::info;
+use std::time::{Duration, Instant};
+
+//应答中 zone 的 SOA 记录
+pub fn soa_record(response: &DnsResponse, zone: &Name) -> Option<Record> {
+    response
+        .answers()
+        .iter()
+        .find(|record| matches!(record.data(), RData::SOA(_)) && record.name() == zone)
+        .cloned()
+}
+
+pub fn soa_serial(record: &Record) -> Option<u32> {
+    match record.data() {
+        RData::SOA(soa) => Some(soa.serial()),
+        _ => None,
+    }
+}
+
+//RFC 1982 序号比较, serial 等于或新于 target
+pub fn serial_reached(serial: u32, target: u32) -> bool {
+    (serial.wrapping_sub(target) as i32) >= 0
+}
+
+//查询 dns 服务器上 zone 的 SOA, 没有 SOA 时返回 SOA_NOT_FOUND
+pub async fn query_soa(dns_server: &str, zone: &Name) -> Result<Record, AppError> {
+    let response = dns::query_raw(dns_server, zone.clone(), RecordType::SOA, false).await?;
+    dns::check_response_code(dns_server, &response)?;
+    soa_record(&response, zone).ok_or_else(|| {
+        AppError::new(ErrorCode::SoaNotFound)
+            .arg("dnsServer", dns_server)
+            .arg("zone", zone)
+    })
+}
+
+//RFC 1996: AA 置位, 问题为 zone SOA, 应答区带上主服务器当前的 SOA 作为提示
+fn notify_message(zone: &Name, soa: &Record) -> Message {
+    let mut message = Message::new();
+    message
+        .set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Notify)
+        .set_authoritative(true)
+        .set_recursion_desired(false)
+        .add_query(Query::query(zone.clone(), RecordType::SOA))
+        .add_answer(soa.clone());
+    message
+}
+
+struct Poll<'a> {
+    zone: &'a Name,
+    soa: &'a Record,
+    target: u32,
+    signer: Option<&'a TSigner>,
+    started: Instant,
+    deadline: Instant,
+    interval: Duration,
+    lang: Lang,
+}
+
+async fn notify_secondary(poll: &Poll<'_>, dns_server: String) -> NotifySecondary {
+    let mut secondary = NotifySecondary {
+        dns_server,
+        response_code: None,
+        authoritative: false,
+        notify_ms: None,
+        error: None,
+        serial: None,
+        synced: false,
+        polls: 0,
+        poll_error: None,
+        synced_ms: None,
+    };
+    let dns_server = secondary.dns_server.as_str();
+    let sent = Instant::now();
+    //地址已在 notify 中校验
+    let result = match dns::dns_socket_addr(dns_server) {
+        Ok(socket_addr) => {
+            let message = notify_message(poll.zone, poll.soa);
+            update::send(dns_server, socket_addr, poll.signer, message).await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(response) => {
+            secondary.response_code = Some(dns::response_code_name(&response));
+            secondary.authoritative = response.authoritative();
+            secondary.notify_ms = Some(sent.elapsed().as_millis() as u64);
+        }
+        Err(e) => secondary.error = Some(e.localize(poll.lang)),
+    }
+    info!(
+        "notify::notify_secondary => {} @{} {:?}",
+        poll.zone, dns_server, secondary.response_code
+    );
+
+    //NOTIFY 失败也轮询, 从服务器可能按 refresh 自行同步
+    while Instant::now() < poll.deadline {
+        secondary.polls += 1;
+        let remaining = poll.deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(remaining, query_soa(dns_server, poll.zone)).await {
+            Ok(Ok(record)) => {
+                secondary.serial = soa_serial(&record);
+                secondary.poll_error = None;
+            }
+            Ok(Err(e)) => secondary.poll_error = Some(e.localize(poll.lang)),
+            Err(_) => {
+                let e = AppError::new(ErrorCode::DnsTimeout).arg("dnsServer", dns_server);
+                secondary.poll_error = Some(e.localize(poll.lang));
+            }
+        }
+        if secondary
+            .serial
+            .is_some_and(|serial| serial_reached(serial, poll.target))
+        {
+            secondary.synced = true;
+            secondary.synced_ms = Some(poll.started.elapsed().as_millis() as u64);
+            break;
+        }
+        if Instant::now() + poll.interval >= poll.deadline {
+            break;
+        }
+        tokio::time::sleep(poll.interval).await;
+    }
+    secondary
+}
+
+//向全部从服务器并发发送 NOTIFY, 再轮询各自的 SOA, 返回时全部从服务器已同步或超过 deadline
+pub async fn notify(request: NotifyRequest, lang: Lang) -> Result<NotifyReport, AppError> {
+    let zone = update::zone_name(&request.zone)?;
+    dns::dns_socket_addr(&request.primary)?;
+    let secondaries: Vec<String> = request
+        .secondaries
+        .iter()
+        .map(|server| server.trim().to_string())
+        .filter(|server| !server.is_empty())
+        .collect();
+    if secondaries.is_empty() {
+        return Err(AppError::new(ErrorCode::NotifySecondariesEmpty));
+    }
+    if secondaries.len() > constants::MAX_NOTIFY_SECONDARIES {
+        return Err(
+            AppError::new(ErrorCode::TooManyItems).arg("max", constants::MAX_NOTIFY_SECONDARIES)
+        );
+    }
+    for server in &secondaries {
+        dns::dns_socket_addr(server)?;
+    }
+    let invalid = |name: &str, min: u64, max: u64| {
+        AppError::new(ErrorCode::QueryOptionInvalid)
+            .arg("name", name)
+            .arg("min", min)
+            .arg("max", max)
+    };
+    let deadline_ms = request
+        .deadline_ms
+        .unwrap_or(constants::NOTIFY_DEFAULT_DEADLINE_MS);
+    if deadline_ms > constants::MAX_NOTIFY_DEADLINE_MS {
+        return Err(invalid("deadlineMs", 0, constants::MAX_NOTIFY_DEADLINE_MS));
+    }
+    //间隔超过最大轮询时长时最多只能轮询一次
+    let interval_ms = request
+        .interval_ms
+        .unwrap_or(constants::NOTIFY_DEFAULT_INTERVAL_MS);
+    if !(constants::MIN_NOTIFY_INTERVAL_MS..=constants::MAX_NOTIFY_DEADLINE_MS)
+        .contains(&interval_ms)
+    {
+        return Err(invalid(
+            "intervalMs",
+            constants::MIN_NOTIFY_INTERVAL_MS,
+            constants::MAX_NOTIFY_DEADLINE_MS,
+        ));
+    }
+    let signer = match request.key_name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => Some(
+            update::KEYS
+                .find(name)
+                .ok_or_else(|| AppError::new(ErrorCode::TsigKeyNotFound).arg("keyName", name))?,
+        ),
+        _ => None,
+    };
+
+    let soa = query_soa(&request.primary, &zone).await?;
+    let target = soa_serial(&soa).unwrap_or_default();
+    info!(
+        "notify::notify => {} serial {} @{} -> {:?}",
+        zone, target, request.primary, secondaries
+    );
+    let started = Instant::now();
+    let poll = Poll {
+        zone: &zone,
+        soa: &soa,
+        target,
+        signer,
+        started,
+        deadline: started + Duration::from_millis(deadline_ms),
+        interval: Duration::from_millis(interval_ms),
+        lang,
+    };
+    let secondaries = future::join_all(
+        secondaries
+            .into_iter()
+            .map(|server| notify_secondary(&poll, server)),
+    )
+    .await;
+
+    Ok(NotifyReport {
+        zone: zone.to_string().trim_end_matches('.').to_lowercase(),
+        primary: request.primary,
+        primary_serial: target,
+        secondaries,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
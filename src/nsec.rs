@@ -0,0 +1,588 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{DenialOfExistence, DenialRecord};
+use crate::dnssec;
+use crate::i18n::{self, Lang};
+use hickory_client::proto::dnssec::Nsec3HashAlgorithm;
+use hickory_client::proto::dnssec::rdata::{DNSSECRData, NSEC, NSEC3};
+use hickory_client::proto::op::ResponseCode;
+use hickory_client::proto::rr::{Name, RData, Record, RecordType};
+use hickory_client::proto::xfer::DnsResponse;
+
+//RFC 4648 base32hex, NSEC3 owner 的第一个标签就是这种编码的哈希
+const BASE32_HEX: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+fn base32_hex(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_HEX[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_HEX[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+//owner < name < next, 区域最后一条记录的 next 回到区域开头
+fn covers<T: Ord + ?Sized>(owner: &T, next: &T, name: &T) -> bool {
+    if owner < next {
+        owner < name && name < next
+    } else {
+        name > owner || name < next
+    }
+}
+
+fn type_names<'a>(types: impl Iterator<Item = RecordType> + 'a) -> Vec<String> {
+    types.map(|record_type| record_type.to_string()).collect()
+}
+
+//否定证明的说明, 按请求语言输出
+struct Explanation {
+    lang: Lang,
+    lines: Vec<String>,
+}
+
+impl Explanation {
+    fn text(&self, key: &str, args: &[(&str, String)]) -> String {
+        i18n::format(self.lang, key, args)
+    }
+
+    fn push(&mut self, key: &str, args: &[(&str, String)]) {
+        let line = self.text(key, args);
+        self.lines.push(line);
+    }
+}
+
+//名称存在时, 类型位图里既没有查询类型也没有 CNAME 才能证明 NODATA
+fn check_bitmap(
+    source: String,
+    types: &[RecordType],
+    query_type: RecordType,
+    explanation: &mut Explanation,
+) -> bool {
+    if types.contains(&query_type) {
+        explanation.push(
+            "DENIAL_BITMAP_HAS_TYPE",
+            &[("source", source), ("recordType", query_type.to_string())],
+        );
+        false
+    } else if types.contains(&RecordType::CNAME) {
+        explanation.push("DENIAL_BITMAP_HAS_CNAME", &[("source", source)]);
+        false
+    } else {
+        explanation.push(
+            "DENIAL_BITMAP_PROVES",
+            &[("source", source), ("recordType", query_type.to_string())],
+        );
+        true
+    }
+}
+
+fn wildcard_of(encloser: &Name) -> Option<Name> {
+    encloser.prepend_label("*").ok()
+}
+
+struct NsecProof<'a> {
+    records: Vec<(&'a Name, &'a NSEC)>,
+}
+
+impl NsecProof<'_> {
+    fn matching(&self, name: &Name) -> Option<&NSEC> {
+        self.records
+            .iter()
+            .find(|(owner, _)| *owner == name)
+            .map(|(_, nsec)| *nsec)
+    }
+
+    fn covering(&self, name: &Name) -> Option<(&Name, &NSEC)> {
+        self.records
+            .iter()
+            .find(|(owner, nsec)| covers(*owner, nsec.next_domain_name(), name))
+            .copied()
+    }
+
+    //closest encloser: 查询名称与 owner / next 共同的最长祖先
+    fn closest_encloser(qname: &Name, owner: &Name, next: &Name) -> Name {
+        (0..qname.num_labels() as usize)
+            .rev()
+            .map(|labels| qname.trim_to(labels))
+            .find(|ancestor| ancestor.zone_of(owner) || ancestor.zone_of(next))
+            .unwrap_or_else(Name::root)
+    }
+
+    fn nodata(&self, qname: &Name, query_type: RecordType, explanation: &mut Explanation) -> bool {
+        if let Some(nsec) = self.matching(qname) {
+            let types: Vec<RecordType> = nsec.type_bit_maps().collect();
+            return check_bitmap(format!("NSEC {}", qname), &types, query_type, explanation);
+        }
+        let Some((owner, nsec)) = self.covering(qname) else {
+            explanation.push("DENIAL_NSEC_NO_MATCH", &[("name", qname.to_string())]);
+            return false;
+        };
+        let next = nsec.next_domain_name();
+        let covering = [
+            ("owner", owner.to_string()),
+            ("next", next.to_string()),
+            ("name", qname.to_string()),
+        ];
+        if qname.zone_of(next) {
+            explanation.push("DENIAL_NSEC_EMPTY_NON_TERMINAL", &covering);
+            return true;
+        }
+        //通配符展开后的 NODATA
+        let encloser = Self::closest_encloser(qname, owner, next);
+        match wildcard_of(&encloser)
+            .and_then(|wildcard| self.matching(&wildcard).map(|nsec| (wildcard, nsec)))
+        {
+            Some((wildcard, nsec)) => {
+                let mut args = covering.to_vec();
+                args.push(("wildcard", wildcard.to_string()));
+                explanation.push("DENIAL_NSEC_WILDCARD_ANSWER", &args);
+                let types: Vec<RecordType> = nsec.type_bit_maps().collect();
+                check_bitmap(
+                    format!("NSEC {}", wildcard),
+                    &types,
+                    query_type,
+                    explanation,
+                )
+            }
+            None => {
+                explanation.push("DENIAL_NSEC_NAME_MISSING", &covering);
+                false
+            }
+        }
+    }
+
+    fn nxdomain(&self, qname: &Name, explanation: &mut Explanation) -> bool {
+        let name = ("name", qname.to_string());
+        if self.matching(qname).is_some() {
+            explanation.push("DENIAL_NSEC_NAME_EXISTS", &[name]);
+            return false;
+        }
+        let Some((owner, nsec)) = self.covering(qname) else {
+            explanation.push("DENIAL_NSEC_NO_COVER", &[name]);
+            return false;
+        };
+        let next = nsec.next_domain_name();
+        explanation.push(
+            "DENIAL_NSEC_COVERS",
+            &[
+                ("owner", owner.to_string()),
+                ("next", next.to_string()),
+                name,
+            ],
+        );
+        let encloser = Self::closest_encloser(qname, owner, next);
+        explanation.push("DENIAL_CLOSEST_ENCLOSER", &[("name", encloser.to_string())]);
+        let Some(wildcard) = wildcard_of(&encloser) else {
+            return false;
+        };
+        if let Some((owner, nsec)) = self.covering(&wildcard) {
+            explanation.push(
+                "DENIAL_NSEC_NO_WILDCARD",
+                &[
+                    ("owner", owner.to_string()),
+                    ("next", nsec.next_domain_name().to_string()),
+                    ("wildcard", wildcard.to_string()),
+                ],
+            );
+            true
+        } else if self.matching(&wildcard).is_some() {
+            explanation.push(
+                "DENIAL_WILDCARD_EXISTS",
+                &[("wildcard", wildcard.to_string())],
+            );
+            false
+        } else {
+            explanation.push(
+                "DENIAL_NSEC_WILDCARD_MISSING",
+                &[("wildcard", wildcard.to_string())],
+            );
+            false
+        }
+    }
+}
+
+struct Nsec3Entry<'a> {
+    owner_hash: String,
+    next_hash: String,
+    nsec3: &'a NSEC3,
+}
+
+//同一区域的 NSEC3 使用相同的哈希参数, 以第一条为准
+struct Nsec3Proof<'a> {
+    zone: Name,
+    algorithm: Nsec3HashAlgorithm,
+    salt: &'a [u8],
+    iterations: u16,
+    records: Vec<Nsec3Entry<'a>>,
+}
+
+impl<'a> Nsec3Proof<'a> {
+    fn new(records: &[(&'a Name, &'a NSEC3)]) -> Option<Self> {
+        let (owner, first) = records.first()?;
+        let entries = records
+            .iter()
+            .map(|(owner, nsec3)| Nsec3Entry {
+                owner_hash: owner
+                    .iter()
+                    .next()
+                    .map(|label| String::from_utf8_lossy(label).to_lowercase())
+                    .unwrap_or_default(),
+                next_hash: base32_hex(nsec3.next_hashed_owner_name()),
+                nsec3,
+            })
+            .collect();
+        Some(Nsec3Proof {
+            zone: owner.base_name(),
+            algorithm: first.hash_algorithm(),
+            salt: first.salt(),
+            iterations: first.iterations(),
+            records: entries,
+        })
+    }
+
+    fn hash(&self, name: &Name) -> Option<String> {
+        self.algorithm
+            .hash(self.salt, name, self.iterations)
+            .ok()
+            .map(|digest| base32_hex(digest.as_ref()))
+    }
+
+    fn matching(&self, hash: &str) -> Option<&Nsec3Entry<'a>> {
+        self.records.iter().find(|entry| entry.owner_hash == hash)
+    }
+
+    fn covering(&self, hash: &str) -> Option<&Nsec3Entry<'a>> {
+        self.records
+            .iter()
+            .find(|entry| covers(entry.owner_hash.as_str(), entry.next_hash.as_str(), hash))
+    }
+
+    //从长到短找哈希有匹配记录的祖先, 返回 closest encloser 与 next closer name
+    fn closest_encloser(&self, qname: &Name) -> Option<(Name, Name)> {
+        let zone_labels = self.zone.num_labels() as usize;
+        (zone_labels..qname.num_labels() as usize)
+            .rev()
+            .map(|labels| (qname.trim_to(labels), qname.trim_to(labels + 1)))
+            .find(|(ancestor, _)| {
+                self.hash(ancestor)
+                    .is_some_and(|hash| self.matching(&hash).is_some())
+            })
+    }
+
+    fn describe(entry: &Nsec3Entry) -> String {
+        format!("NSEC3 {} -> {}", entry.owner_hash, entry.next_hash)
+    }
+
+    fn nodata(&self, qname: &Name, query_type: RecordType, explanation: &mut Explanation) -> bool {
+        let Some(hash) = self.hash(qname) else {
+            explanation.push("DENIAL_NSEC3_HASH_FAILED", &[("name", qname.to_string())]);
+            return false;
+        };
+        let hashed = [("name", qname.to_string()), ("hash", hash.clone())];
+        if let Some(entry) = self.matching(&hash) {
+            let types: Vec<RecordType> = entry.nsec3.type_bit_maps().collect();
+            let source = explanation.text("DENIAL_NSEC3_MATCH_SOURCE", &hashed);
+            return check_bitmap(source, &types, query_type, explanation);
+        }
+        let Some((encloser, next_closer)) = self.closest_encloser(qname) else {
+            explanation.push("DENIAL_NSEC3_NO_MATCH", &hashed);
+            return false;
+        };
+        explanation.push("DENIAL_CLOSEST_ENCLOSER", &[("name", encloser.to_string())]);
+        //DS 查询可以由 opt-out 证明是未签名的委派
+        if query_type == RecordType::DS
+            && let Some(entry) = self
+                .hash(&next_closer)
+                .and_then(|hash| self.covering(&hash))
+                .filter(|entry| entry.nsec3.opt_out())
+        {
+            explanation.push(
+                "DENIAL_NSEC3_OPT_OUT_DS",
+                &[
+                    ("nsec3", Self::describe(entry)),
+                    ("nextCloser", next_closer.to_string()),
+                    ("name", qname.to_string()),
+                ],
+            );
+            return true;
+        }
+        //通配符展开后的 NODATA
+        match wildcard_of(&encloser).and_then(|wildcard| {
+            let hash = self.hash(&wildcard)?;
+            self.matching(&hash).map(|entry| (wildcard, entry))
+        }) {
+            Some((wildcard, entry)) => {
+                let types: Vec<RecordType> = entry.nsec3.type_bit_maps().collect();
+                let source = explanation.text(
+                    "DENIAL_NSEC3_WILDCARD_SOURCE",
+                    &[("wildcard", wildcard.to_string())],
+                );
+                check_bitmap(source, &types, query_type, explanation)
+            }
+            None => {
+                explanation.push("DENIAL_NSEC3_NO_MATCH", &hashed);
+                false
+            }
+        }
+    }
+
+    fn nxdomain(&self, qname: &Name, explanation: &mut Explanation) -> bool {
+        let Some(hash) = self.hash(qname) else {
+            explanation.push("DENIAL_NSEC3_HASH_FAILED", &[("name", qname.to_string())]);
+            return false;
+        };
+        if self.matching(&hash).is_some() {
+            explanation.push(
+                "DENIAL_NSEC3_NAME_EXISTS",
+                &[("name", qname.to_string()), ("hash", hash)],
+            );
+            return false;
+        }
+        let Some((encloser, next_closer)) = self.closest_encloser(qname) else {
+            explanation.push("DENIAL_NSEC3_NO_ENCLOSER", &[("name", qname.to_string())]);
+            return false;
+        };
+        explanation.push("DENIAL_NSEC3_ENCLOSER", &[("name", encloser.to_string())]);
+
+        let next_closer_hash = self.hash(&next_closer).unwrap_or_default();
+        let next_closer_args = [
+            ("nextCloser", next_closer.to_string()),
+            ("hash", next_closer_hash.clone()),
+        ];
+        let Some(entry) = self.covering(&next_closer_hash) else {
+            explanation.push("DENIAL_NSEC3_NO_NEXT_CLOSER", &next_closer_args);
+            return false;
+        };
+        let mut args = next_closer_args.to_vec();
+        args.push(("nsec3", Self::describe(entry)));
+        explanation.push("DENIAL_NSEC3_NEXT_CLOSER", &args);
+        if entry.nsec3.opt_out() {
+            explanation.push(
+                "DENIAL_NSEC3_OPT_OUT",
+                &[("nextCloser", next_closer.to_string())],
+            );
+        }
+
+        let Some(wildcard) = wildcard_of(&encloser) else {
+            return false;
+        };
+        let wildcard_hash = self.hash(&wildcard).unwrap_or_default();
+        let mut args = vec![
+            ("wildcard", wildcard.to_string()),
+            ("hash", wildcard_hash.clone()),
+        ];
+        match self.covering(&wildcard_hash) {
+            Some(entry) => {
+                args.push(("nsec3", Self::describe(entry)));
+                explanation.push("DENIAL_NSEC3_NO_WILDCARD", &args);
+                true
+            }
+            None => {
+                explanation.push("DENIAL_NSEC3_WILDCARD_MISSING", &args);
+                false
+            }
+        }
+    }
+
+    //RFC 9276 对 NSEC3 参数的建议
+    fn warnings(&self, lang: Lang) -> Vec<String> {
+        let mut warnings = vec![];
+        let iterations = ("iterations", self.iterations.to_string());
+        if self.iterations > constants::NSEC3_ITERATIONS_LIMIT {
+            warnings.push(i18n::format(
+                lang,
+                "NSEC3_ITERATIONS_OVER_LIMIT",
+                &[
+                    iterations,
+                    ("limit", constants::NSEC3_ITERATIONS_LIMIT.to_string()),
+                ],
+            ));
+        } else if self.iterations > 0 {
+            warnings.push(i18n::format(
+                lang,
+                "NSEC3_ITERATIONS_NONZERO",
+                &[iterations],
+            ));
+        }
+        if !self.salt.is_empty() {
+            warnings.push(i18n::format(
+                lang,
+                "NSEC3_SALT",
+                &[("salt", dnssec::hex(self.salt))],
+            ));
+        }
+        if self.records.iter().any(|entry| entry.nsec3.opt_out()) {
+            warnings.push(i18n::text(lang, "NSEC3_OPT_OUT"));
+        }
+        if self.records.iter().any(|entry| {
+            entry.nsec3.hash_algorithm() != self.algorithm
+                || entry.nsec3.salt() != self.salt
+                || entry.nsec3.iterations() != self.iterations
+        }) {
+            warnings.push(i18n::text(lang, "NSEC3_PARAMS_MISMATCH"));
+        }
+        warnings
+    }
+}
+
+fn nsec_record(record: &Record, nsec: &NSEC) -> DenialRecord {
+    DenialRecord {
+        owner: record.name().to_string(),
+        record_type: RecordType::NSEC.to_string(),
+        ttl: record.ttl(),
+        next: nsec.next_domain_name().to_string(),
+        types: type_names(nsec.type_bit_maps()),
+        proof: record.proof().to_string(),
+        hash_algorithm: None,
+        iterations: None,
+        salt: None,
+        opt_out: None,
+    }
+}
+
+fn nsec3_record(record: &Record, nsec3: &NSEC3) -> DenialRecord {
+    let salt = nsec3.salt();
+    DenialRecord {
+        owner: record.name().to_string(),
+        record_type: RecordType::NSEC3.to_string(),
+        ttl: record.ttl(),
+        next: format!(
+            "{}.{}",
+            base32_hex(nsec3.next_hashed_owner_name()),
+            record.name().base_name()
+        ),
+        types: type_names(nsec3.type_bit_maps()),
+        proof: record.proof().to_string(),
+        hash_algorithm: Some(format!("{:?}", nsec3.hash_algorithm())),
+        iterations: Some(nsec3.iterations()),
+        salt: Some(if salt.is_empty() {
+            "-".to_string()
+        } else {
+            dnssec::hex(salt)
+        }),
+        opt_out: Some(nsec3.opt_out()),
+    }
+}
+
+//NXDOMAIN / NODATA 应答: 解析权威段中的 NSEC / NSEC3, 检查否定证明是否完整
+pub fn inspect(
+    qname: &Name,
+    query_type: RecordType,
+    response: &DnsResponse,
+    lang: Lang,
+) -> Option<DenialOfExistence> {
+    if !response.answers().is_empty() {
+        return None;
+    }
+    let kind = match response.response_code() {
+        ResponseCode::NXDomain => constants::DENIAL_NXDOMAIN,
+        ResponseCode::NoError => constants::DENIAL_NODATA,
+        _ => return None,
+    };
+
+    let mut records = vec![];
+    let mut nsecs = vec![];
+    let mut nsec3s = vec![];
+    for record in response.name_servers() {
+        match record.data() {
+            RData::DNSSEC(DNSSECRData::NSEC(nsec)) => {
+                records.push(nsec_record(record, nsec));
+                nsecs.push((record.name(), nsec));
+            }
+            RData::DNSSEC(DNSSECRData::NSEC3(nsec3)) => {
+                records.push(nsec3_record(record, nsec3));
+                nsec3s.push((record.name(), nsec3));
+            }
+            _ => {}
+        }
+    }
+
+    let mut explanation = Explanation {
+        lang,
+        lines: vec![],
+    };
+    let mut warnings = vec![];
+    let nxdomain = kind == constants::DENIAL_NXDOMAIN;
+    let (denial_type, complete) = if let Some(proof) = Nsec3Proof::new(&nsec3s) {
+        warnings = proof.warnings(lang);
+        let complete = if nxdomain {
+            proof.nxdomain(qname, &mut explanation)
+        } else {
+            proof.nodata(qname, query_type, &mut explanation)
+        };
+        (Some(RecordType::NSEC3), complete)
+    } else if !nsecs.is_empty() {
+        let proof = NsecProof { records: nsecs };
+        let complete = if nxdomain {
+            proof.nxdomain(qname, &mut explanation)
+        } else {
+            proof.nodata(qname, query_type, &mut explanation)
+        };
+        (Some(RecordType::NSEC), complete)
+    } else {
+        explanation.push("DENIAL_NO_RECORDS", &[]);
+        (None, false)
+    };
+
+    //证明记录本身的签名也要通过验证
+    let unverified: Vec<String> = records
+        .iter()
+        .filter(|record| record.proof != "Secure")
+        .map(|record| format!("{} {} {}", record.record_type, record.owner, record.proof))
+        .collect();
+    if denial_type.is_some() {
+        if unverified.is_empty() {
+            explanation.push("DENIAL_RECORDS_SECURE", &[]);
+        } else {
+            explanation.push(
+                "DENIAL_RECORDS_UNVERIFIED",
+                &[("records", unverified.join("; "))],
+            );
+        }
+    }
+
+    let proven = complete && unverified.is_empty();
+    let summary = explanation.text(
+        if proven {
+            "DENIAL_PROVEN"
+        } else {
+            "DENIAL_NOT_PROVEN"
+        },
+        &[("kind", kind.to_string())],
+    );
+    explanation.lines.insert(0, summary);
+
+    Some(DenialOfExistence {
+        kind: kind.to_string(),
+        denial_type: denial_type.map(|record_type| record_type.to_string()),
+        records,
+        proven,
+        explanation: explanation.lines,
+        warnings,
+    })
+}
@@ -0,0 +1,170 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{ErrorCode, Problem};
+use crate::controller;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use utoipa::openapi::header::HeaderBuilder;
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::schema::{ObjectBuilder, Type};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+//错误响应的 ApiResponse, code 为错误码
+const ERROR_RESPONSE_SCHEMA: &str = "ErrorResponse";
+
+//接口与 schema 都由代码中的类型生成, 新增接口需要加到 paths 中
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        license(name = "Apache-2.0"),
+        description = "dns 查询工具接口, 错误响应见 ErrorCode, Accept: application/problem+json 时返回 RFC 7807 格式"
+    ),
+    paths(
+        controller::v1_query,
+        controller::v1_query_dnssec,
+        controller::v1_query_subnets,
+        controller::v1_diagnose_dnssec,
+        controller::v1_dnssec_health,
+        controller::v1_zone_sync,
+        controller::v1_trust_anchors,
+        controller::v1_resolver_groups,
+        controller::v1_query_stream,
+        controller::v1_query_dnssec_stream,
+        controller::v1_query_subnets_stream,
+        controller::v1_history,
+        controller::v1_history_stream,
+        controller::v1_resolver,
+        controller::v1_export,
+        controller::v1_bulk_create,
+        controller::v1_bulk_detail,
+        controller::v1_bulk_events,
+        controller::v1_bulk_report,
+        controller::v1_update,
+        controller::v1_update_keys,
+        controller::v1_update_audit,
+        controller::v1_notify,
+        controller::v1_monitor_list,
+        controller::v1_monitor_create,
+        controller::v1_monitor_changes,
+        controller::v1_monitor_detail,
+        controller::v1_monitor_update,
+        controller::v1_monitor_delete,
+        controller::v1_selftest,
+        controller::healthz,
+        controller::readyz,
+    ),
+    components(schemas(ErrorCode, Problem)),
+    modifiers(&ErrorResponses),
+    tags(
+        (name = "query", description = "dns 查询"),
+        (name = "history", description = "查询历史"),
+        (name = "export", description = "导出保存的结果"),
+        (name = "bulk", description = "批量查询"),
+        (name = "monitor", description = "监控与告警"),
+        (name = "update", description = "DNS UPDATE、NOTIFY 与审计日志"),
+        (name = "health", description = "健康检查与自检"),
+    )
+)]
+pub struct ApiDoc;
+
+//按 http 状态码给每个 /v1 接口加上错误响应, 状态码与错误码来自 ErrorCode::status
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.schemas.insert(
+            ERROR_RESPONSE_SCHEMA.to_string(),
+            ObjectBuilder::new()
+                .property("code", Ref::from_schema_name("ErrorCode"))
+                .required("code")
+                .property("msg", ObjectBuilder::new().schema_type(Type::String))
+                .required("msg")
+                .property("data", ObjectBuilder::new().schema_type(Type::String))
+                .into(),
+        );
+        //DNS UPDATE 接口使用 UPDATE_TOKEN
+        components.add_security_scheme(
+            "bearerAuth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+
+        let mut groups: BTreeMap<u16, Vec<&'static str>> = BTreeMap::new();
+        for &code in ErrorCode::ALL {
+            groups
+                .entry(code.status().as_u16())
+                .or_default()
+                .push(code.as_str());
+        }
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !path.starts_with(constants::API_PATH_PREFIX) {
+                continue;
+            }
+            let operations = [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                add_error_responses(operation, &groups);
+            }
+        }
+    }
+}
+
+fn add_error_responses(operation: &mut Operation, groups: &BTreeMap<u16, Vec<&'static str>>) {
+    for (status, codes) in groups {
+        let mut response = ResponseBuilder::new()
+            .description(codes.join(", "))
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name(ERROR_RESPONSE_SCHEMA)))
+                    .build(),
+            )
+            .content(
+                constants::CONTENT_TYPE_PROBLEM_JSON,
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("Problem")))
+                    .build(),
+            );
+        if *status == ErrorCode::RateLimited.status().as_u16() {
+            response = response.header(
+                "Retry-After",
+                HeaderBuilder::new()
+                    .schema(ObjectBuilder::new().schema_type(Type::Integer))
+                    .description(Some("秒"))
+                    .build(),
+            );
+        }
+        operation
+            .responses
+            .responses
+            .insert(status.to_string(), response.build().into());
+    }
+}
+
+lazy_static! {
+    pub static ref OPENAPI_JSON: String = ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("openapi 文档序列化失败");
+}
@@ -0,0 +1,167 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsQuery, DnsResult, DnsSecQuery, DnsSecResult, HistoryRecord, PermalinkDiff,
+    PermalinkRecord, PermalinkView, QueryOptions,
+};
+use crate::dns;
+use crate::history::HistoryStore;
+use crate::i18n::Lang;
+
+pub fn from_dns_result(result: &DnsResult) -> Vec<PermalinkRecord> {
+    result
+        .dns_record
+        .iter()
+        .map(|record| PermalinkRecord {
+            name: record.name.clone(),
+            record_type: result.record_type.clone(),
+            value: record.value.clone(),
+            ttl: record.ttl,
+            proof: None,
+        })
+        .collect()
+}
+
+pub fn from_dns_sec_result(result: &DnsSecResult) -> Vec<PermalinkRecord> {
+    result
+        .dns_record
+        .iter()
+        .map(|record| PermalinkRecord {
+            name: record.name.clone(),
+            record_type: record.record_type.clone(),
+            value: record.value.clone(),
+            ttl: record.ttl,
+            proof: Some(record.proof.clone()),
+        })
+        .collect()
+}
+
+//保存的 json 按查询类型还原
+pub fn saved_records(history: &HistoryRecord) -> Vec<PermalinkRecord> {
+    let Some(json) = history.result.clone() else {
+        return vec![];
+    };
+    if history.kind == constants::HISTORY_KIND_DNSSEC {
+        serde_json::from_value::<DnsSecResult>(json)
+            .map(|result| from_dns_sec_result(&result))
+            .unwrap_or_default()
+    } else {
+        serde_json::from_value::<DnsResult>(json)
+            .map(|result| from_dns_result(&result))
+            .unwrap_or_default()
+    }
+}
+
+//用保存的参数重新查询
+async fn rerun(history: &HistoryRecord, lang: Lang) -> Result<Vec<PermalinkRecord>, AppError> {
+    if history.kind == constants::HISTORY_KIND_DNSSEC {
+        let result = dns::v1_query_dnssec(
+            DnsSecQuery {
+                domain: history.domain.clone(),
+                dns_server: history.dns_server.clone(),
+                record_type: history.record_type.clone(),
+            },
+            lang,
+        )
+        .await?;
+        Ok(from_dns_sec_result(&result))
+    } else {
+        let result = dns::v1_query(
+            DnsQuery {
+                domain: history.domain.clone(),
+                record_type: history.record_type.clone(),
+                dns_server: history.dns_server.clone(),
+                client_subnet: history.client_subnet.clone(),
+                options: QueryOptions::default(),
+            },
+            lang,
+        )
+        .await?;
+        Ok(from_dns_result(&result))
+    }
+}
+
+//按 (记录类型, 值) 对比, 先列出保存的记录, 再列出新增的记录
+fn diff(saved: &[PermalinkRecord], live: &[PermalinkRecord]) -> Vec<PermalinkDiff> {
+    let same_key = |a: &PermalinkRecord, b: &PermalinkRecord| {
+        a.record_type == b.record_type && a.value == b.value
+    };
+    let mut diffs: Vec<PermalinkDiff> = saved
+        .iter()
+        .map(|record| {
+            let live_record = live.iter().find(|l| same_key(l, record));
+            PermalinkDiff {
+                record_type: record.record_type.clone(),
+                value: record.value.clone(),
+                status: if live_record.is_some() {
+                    constants::DIFF_SAME
+                } else {
+                    constants::DIFF_REMOVED
+                }
+                .to_string(),
+                saved_ttl: Some(record.ttl),
+                live_ttl: live_record.map(|l| l.ttl),
+            }
+        })
+        .collect();
+    diffs.extend(
+        live.iter()
+            .filter(|record| !saved.iter().any(|s| same_key(s, record)))
+            .map(|record| PermalinkDiff {
+                record_type: record.record_type.clone(),
+                value: record.value.clone(),
+                status: constants::DIFF_ADDED.to_string(),
+                saved_ttl: None,
+                live_ttl: Some(record.ttl),
+            }),
+    );
+    diffs
+}
+
+//分享页面数据, 链接不存在或已过期返回 None
+pub async fn load(
+    history: &HistoryStore,
+    share_id: &str,
+    rerun_live: bool,
+    lang: Lang,
+) -> Result<Option<PermalinkView>, AppError> {
+    let Some(record) = history.find_by_share_id(share_id).await? else {
+        return Ok(None);
+    };
+    let saved = saved_records(&record);
+    let (live, live_error) = if rerun_live {
+        match rerun(&record, lang).await {
+            Ok(live) => (Some(live), None),
+            Err(e) => (None, Some(e.localize(lang))),
+        }
+    } else {
+        (None, None)
+    };
+    let diff = live
+        .as_ref()
+        .map(|live| diff(&saved, live))
+        .unwrap_or_default();
+    Ok(Some(PermalinkView {
+        history: record,
+        saved,
+        live,
+        live_error,
+        diff,
+    }))
+}
@@ -0,0 +1,100 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{AppError, ErrorCode};
+use crate::i18n::Lang;
+use actix_web::body::{BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{HttpRequest, HttpResponse};
+
+//Accept 中明确要求 application/problem+json
+fn wants_problem(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .split(',')
+        .any(|item| {
+            item.split(';').next().unwrap_or_default().trim()
+                == constants::CONTENT_TYPE_PROBLEM_JSON
+        })
+}
+
+pub fn is_api(req: &HttpRequest) -> bool {
+    req.path().starts_with(constants::API_PATH_PREFIX)
+}
+
+//按请求输出错误: problem+json 或原有的 ApiResponse, 文案按 Accept-Language
+pub fn error_response(error: &AppError, req: &HttpRequest) -> HttpResponse {
+    let lang = Lang::from_headers(req.headers());
+    let (content_type, body) = if wants_problem(req) {
+        (
+            constants::CONTENT_TYPE_PROBLEM_JSON,
+            serde_json::to_string(&error.problem(lang, req.path())),
+        )
+    } else {
+        (
+            constants::CONTENT_TYPE_JSON_VALUE,
+            serde_json::to_string(&error.api_response(lang)),
+        )
+    };
+    //from_error 带上状态码、Retry-After 与原始错误, 之后的 error_handler 据此判断
+    let mut response = HttpResponse::from_error(error.clone());
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response.set_body(BoxBody::new(body.unwrap_or_default()))
+}
+
+//接口上非 AppError 的错误 (路由不存在、json 解析失败等) 转为对应错误码
+pub fn from_status(status: StatusCode, req: &HttpRequest, detail: Option<String>) -> AppError {
+    match status {
+        StatusCode::NOT_FOUND => AppError::new(ErrorCode::NotFound).arg("path", req.path()),
+        StatusCode::METHOD_NOT_ALLOWED => {
+            AppError::new(ErrorCode::MethodNotAllowed).arg("method", req.method())
+        }
+        StatusCode::PAYLOAD_TOO_LARGE => AppError::new(ErrorCode::PayloadTooLarge),
+        StatusCode::TOO_MANY_REQUESTS => AppError::new(ErrorCode::RateLimited),
+        status if status.is_client_error() => AppError::new(ErrorCode::RequestInvalid)
+            .arg("detail", detail.unwrap_or_else(|| status.to_string())),
+        status => AppError::internal(detail.unwrap_or_else(|| status.to_string())),
+    }
+}
+
+//AppError 按 Accept 与 Accept-Language 重新输出, 需要在 Compress 内层
+pub async fn render_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody, BoxBody>>, actix_web::Error> {
+    let res = next.call(req).await?;
+    let rendered = res
+        .response()
+        .error()
+        .and_then(|e| e.as_error::<AppError>())
+        .map(|error| error_response(error, res.request()));
+    Ok(match rendered {
+        Some(response) => {
+            let (request, _) = res.into_parts();
+            ServiceResponse::new(request, response).map_into_right_body()
+        }
+        None => res.map_into_left_body(),
+    })
+}
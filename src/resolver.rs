@@ -0,0 +1,454 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsRecordResult, DnsResult, ErrorCode, ResolverStatus, ResolverUpstream,
+};
+use crate::dns;
+use crate::history::{HistoryStore, NewHistory};
+use actix_web::web::Data;
+use hickory_client::proto::op::{Message, ResponseCode};
+use hickory_client::proto::rr::{DNSClass, Name, Record, RecordType};
+use hickory_client::proto::xfer::{DnsRequest, DnsRequestOptions};
+use log::{error, info};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+//缓存命中时历史中的 dnsServer
+const CACHE_SERVER: &str = "cache";
+
+//名称(小写), 类型, class, DO, CD
+type CacheKey = (Name, RecordType, DNSClass, bool, bool);
+
+struct CacheEntry {
+    response: Message,
+    stored_at: Instant,
+    ttl: u32,
+}
+
+#[derive(Default)]
+struct UpstreamStats {
+    queries: u64,
+    failures: u64,
+    total_ms: u64,
+    last_error: Option<String>,
+}
+
+struct Upstream {
+    name: String,
+    addr: SocketAddr,
+    stats: Mutex<UpstreamStats>,
+}
+
+impl Upstream {
+    fn record(&self, result: &Result<Message, AppError>, elapsed: Duration) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.queries += 1;
+        match result {
+            Ok(_) => stats.total_ms += elapsed.as_millis() as u64,
+            Err(e) => {
+                stats.failures += 1;
+                stats.last_error = Some(e.msg());
+            }
+        }
+    }
+
+    fn info(&self) -> ResolverUpstream {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let answered = stats.queries - stats.failures;
+        ResolverUpstream {
+            name: self.name.clone(),
+            address: self.addr.to_string(),
+            queries: stats.queries,
+            failures: stats.failures,
+            avg_ms: stats.total_ms.checked_div(answered).unwrap_or(0),
+            last_error: stats.last_error.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    cache_hits: u64,
+    cache_misses: u64,
+    serv_fail: u64,
+}
+
+//本地转发解析器, 按 RESOLVER_UPSTREAMS 顺序转发, 失败时换下一个
+pub struct Resolver {
+    upstreams: Vec<Upstream>,
+    cache: Mutex<HashMap<CacheKey, CacheEntry>>,
+    counters: Mutex<Counters>,
+    history: Data<HistoryStore>,
+    listen: Mutex<Option<String>>,
+    errors: Vec<String>,
+}
+
+//可缓存的应答及缓存时间: 肯定应答取最小 TTL, 否定应答取 SOA 的 TTL 与 minimum 中较小的
+fn cache_ttl(response: &Message) -> Option<u32> {
+    if response.truncated()
+        || !matches!(
+            response.response_code(),
+            ResponseCode::NoError | ResponseCode::NXDomain
+        )
+    {
+        return None;
+    }
+    let ttl = if response.answers().is_empty() {
+        response.name_servers().iter().find_map(|record| {
+            record
+                .data()
+                .as_soa()
+                .map(|soa| record.ttl().min(soa.minimum()))
+        })
+    } else {
+        response
+            .answers()
+            .iter()
+            .chain(response.name_servers())
+            .chain(response.additionals())
+            .map(|record| record.ttl())
+            .min()
+    };
+    ttl.filter(|ttl| *ttl > 0)
+}
+
+//应答的 id 与问题部分与请求一致
+fn reply_to(request: &Message, mut response: Message) -> Message {
+    response.set_id(request.id());
+    response.take_queries();
+    response.add_queries(request.queries().to_vec());
+    response
+}
+
+fn serv_fail(request: &Message) -> Message {
+    let mut response = Message::error_msg(request.id(), request.op_code(), ResponseCode::ServFail);
+    response
+        .add_queries(request.queries().to_vec())
+        .set_recursion_desired(request.recursion_desired())
+        .set_recursion_available(true);
+    response
+}
+
+//历史中的结果, 记录全部应答记录
+fn history_result(server: &str, record_type: RecordType, response: &Message) -> DnsResult {
+    DnsResult {
+        dns_server: server.to_string(),
+        record_type: record_type.to_string().to_uppercase(),
+        dns_record: response
+            .answers()
+            .iter()
+            .map(DnsRecordResult::new)
+            .collect(),
+        client_subnet: None,
+        scope_prefix: None,
+        authoritative: response.authoritative(),
+        share_id: None,
+        idn: None,
+        group: None,
+        attempts: vec![],
+        chain: None,
+    }
+}
+
+impl Resolver {
+    pub fn new(history: Data<HistoryStore>) -> Self {
+        let mut upstreams = vec![];
+        let mut errors = vec![];
+        for name in constants::RESOLVER_UPSTREAMS.iter() {
+            match dns::dns_socket_addr(name) {
+                Ok(addr) => upstreams.push(Upstream {
+                    name: name.clone(),
+                    addr,
+                    stats: Mutex::new(UpstreamStats::default()),
+                }),
+                Err(e) => {
+                    error!("resolver::new => invalid upstream {}: {}", name, e.msg());
+                    errors.push(format!("RESOLVER_UPSTREAMS: {}", e.msg()));
+                }
+            }
+        }
+        Resolver {
+            upstreams,
+            cache: Mutex::new(HashMap::new()),
+            counters: Mutex::new(Counters::default()),
+            history,
+            listen: Mutex::new(None),
+            errors,
+        }
+    }
+
+    fn cache(&self) -> MutexGuard<'_, HashMap<CacheKey, CacheEntry>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn counters(&self) -> MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn status(&self) -> ResolverStatus {
+        let counters = self.counters();
+        ResolverStatus {
+            enabled: *constants::RESOLVER_PORT != 0,
+            listen: self
+                .listen
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            upstreams: self.upstreams.iter().map(Upstream::info).collect(),
+            cache_size: self.cache().len(),
+            cache_hits: counters.cache_hits,
+            cache_misses: counters.cache_misses,
+            serv_fail: counters.serv_fail,
+            errors: self.errors.clone(),
+        }
+    }
+
+    //缓存中的应答, TTL 减去已缓存的时间
+    fn cached(&self, key: &CacheKey) -> Option<Message> {
+        let mut cache = self.cache();
+        let entry = cache.get(key)?;
+        let elapsed = entry.stored_at.elapsed().as_secs() as u32;
+        if elapsed >= entry.ttl {
+            cache.remove(key);
+            return None;
+        }
+        let mut response = entry.response.clone();
+        let age = |records: &mut Vec<Record>| {
+            for record in records {
+                record.set_ttl(record.ttl().saturating_sub(elapsed));
+            }
+        };
+        age(response.answers_mut());
+        age(response.name_servers_mut());
+        age(response.additionals_mut());
+        Some(response)
+    }
+
+    //缓存已满时先清理过期的, 仍然满时随便淘汰一条
+    fn store(&self, key: CacheKey, response: &Message) {
+        let size = *constants::RESOLVER_CACHE_SIZE;
+        let Some(ttl) = cache_ttl(response).filter(|_| size > 0) else {
+            return;
+        };
+        let mut cache = self.cache();
+        if cache.len() >= size {
+            cache.retain(|_, entry| entry.stored_at.elapsed().as_secs() < entry.ttl as u64);
+        }
+        if cache.len() >= size
+            && let Some(evict) = cache.keys().next().cloned()
+        {
+            cache.remove(&evict);
+        }
+        cache.insert(
+            key,
+            CacheEntry {
+                response: response.clone(),
+                stored_at: Instant::now(),
+                ttl,
+            },
+        );
+    }
+
+    //按顺序尝试上游, 超时、连接失败、SERVFAIL 与 REFUSED 时换下一个
+    async fn forward(&self, request: &Message) -> Result<(&str, Message), AppError> {
+        let mut last_error = AppError::new(ErrorCode::DnsServerEmpty);
+        for upstream in &self.upstreams {
+            let started = Instant::now();
+            let dns_request = DnsRequest::new(request.clone(), DnsRequestOptions::default());
+            let result = dns::exchange(&upstream.name, upstream.addr, dns_request)
+                .await
+                .and_then(|response| {
+                    dns::check_response_code(&upstream.name, &response)?;
+                    Ok(response.into_message())
+                });
+            upstream.record(&result, started.elapsed());
+            match result {
+                Ok(response) => return Ok((&upstream.name, response)),
+                Err(e) => {
+                    info!("resolver::forward => {} failed: {}", upstream.name, e.msg());
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    //处理一次查询并写入历史, 所有上游都失败时返回 SERVFAIL
+    pub async fn resolve(&self, requester: String, request: &Message) -> Message {
+        let Some(query) = request.queries().first() else {
+            return Message::error_msg(request.id(), request.op_code(), ResponseCode::FormErr);
+        };
+        let key = (
+            query.name().to_lowercase(),
+            query.query_type(),
+            query.query_class(),
+            request
+                .extensions()
+                .as_ref()
+                .is_some_and(|edns| edns.flags().dnssec_ok),
+            request.checking_disabled(),
+        );
+        let entry = NewHistory {
+            requester,
+            kind: constants::HISTORY_KIND_RESOLVER,
+            domain: query.name().to_string().trim_end_matches('.').to_string(),
+            record_type: query.query_type().to_string(),
+            dns_server: CACHE_SERVER.to_string(),
+            client_subnet: None,
+        };
+        let started = Instant::now();
+
+        if let Some(response) = self.cached(&key) {
+            self.counters().cache_hits += 1;
+            let result = Ok(history_result(CACHE_SERVER, query.query_type(), &response));
+            self.history.save(entry, started.elapsed(), &result).await;
+            return reply_to(request, response);
+        }
+        self.counters().cache_misses += 1;
+
+        match self.forward(request).await {
+            Ok((server, response)) => {
+                let result = Ok(history_result(server, query.query_type(), &response));
+                let entry = NewHistory {
+                    dns_server: server.to_string(),
+                    ..entry
+                };
+                self.history.save(entry, started.elapsed(), &result).await;
+                self.store(key, &response);
+                reply_to(request, response)
+            }
+            Err(e) => {
+                self.counters().serv_fail += 1;
+                //记录尝试过的全部上游
+                let entry = NewHistory {
+                    dns_server: constants::RESOLVER_UPSTREAMS.join(","),
+                    ..entry
+                };
+                self.history
+                    .save::<DnsResult>(entry, started.elapsed(), &Err(e))
+                    .await;
+                serv_fail(request)
+            }
+        }
+    }
+}
+
+//监听 UDP 与 TCP, RESOLVER_PORT 为 0 时不启动
+pub async fn run(resolver: Data<Resolver>) {
+    let addr = format!(
+        "{}:{}",
+        *constants::RESOLVER_ADDRESS,
+        *constants::RESOLVER_PORT
+    );
+    let udp = match UdpSocket::bind(&addr).await {
+        Ok(socket) => Arc::new(socket),
+        Err(e) => {
+            error!("!!! FAILED TO BIND RESOLVER (udp) !!! {} {}", addr, e);
+            return;
+        }
+    };
+    let tcp = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("!!! FAILED TO BIND RESOLVER (tcp) !!! {} {}", addr, e);
+            return;
+        }
+    };
+    info!("📢 Resolver listening on: {} (udp/tcp)", addr);
+    *resolver.listen.lock().unwrap_or_else(|e| e.into_inner()) = Some(addr);
+    tokio::spawn(serve_tcp(tcp, resolver.clone()));
+    serve_udp(udp, resolver).await;
+}
+
+async fn serve_udp(socket: Arc<UdpSocket>, resolver: Data<Resolver>) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                error!("resolver::serve_udp => {}", e);
+                continue;
+            }
+        };
+        let Ok(request) = Message::from_vec(&buf[..len]) else {
+            continue;
+        };
+        let socket = socket.clone();
+        let resolver = resolver.clone();
+        tokio::spawn(async move {
+            let mut response = resolver.resolve(peer.ip().to_string(), &request).await;
+            //超过客户端的 UDP 大小时截断, 客户端改用 TCP
+            let Ok(mut bytes) = response.to_vec() else {
+                return;
+            };
+            if bytes.len() > request.max_payload().max(512) as usize {
+                response.take_answers();
+                response.take_name_servers();
+                response.take_additionals();
+                response.set_truncated(true);
+                bytes = response.to_vec().unwrap_or_default();
+            }
+            let _ = socket.send_to(&bytes, peer).await;
+        });
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, resolver: Data<Resolver>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(serve_connection(stream, peer, resolver.clone()));
+            }
+            Err(e) => error!("resolver::serve_tcp => {}", e),
+        }
+    }
+}
+
+//TCP 消息前两个字节为长度
+async fn serve_connection(mut stream: TcpStream, peer: SocketAddr, resolver: Data<Resolver>) {
+    loop {
+        let mut len = [0u8; 2];
+        if stream.read_exact(&mut len).await.is_err() {
+            return;
+        }
+        let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+        if stream.read_exact(&mut buf).await.is_err() {
+            return;
+        }
+        let Ok(request) = Message::from_vec(&buf) else {
+            return;
+        };
+        let response = resolver.resolve(peer.ip().to_string(), &request).await;
+        let Ok(bytes) = response.to_vec() else {
+            return;
+        };
+        if stream
+            .write_all(&(bytes.len() as u16).to_be_bytes())
+            .await
+            .is_err()
+            || stream.write_all(&bytes).await.is_err()
+        {
+            return;
+        }
+    }
+}
@@ -0,0 +1,141 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{AppError, ErrorCode, QueryOptions};
+use crate::dns;
+use lazy_static::lazy_static;
+use log::error;
+use std::collections::HashMap;
+use std::time::Duration;
+
+lazy_static! {
+    pub static ref TIMEOUTS: ServerTimeouts = ServerTimeouts::load();
+}
+
+//DNS_SERVER_TIMEOUTS, 按 ip:port 保存, dns服务商 key 与对应的 ip 共用一个超时
+pub struct ServerTimeouts {
+    timeouts: HashMap<String, u64>,
+    pub errors: Vec<String>,
+}
+
+impl ServerTimeouts {
+    fn load() -> Self {
+        let mut timeouts = ServerTimeouts {
+            timeouts: HashMap::new(),
+            errors: vec![],
+        };
+        for item in constants::DNS_SERVER_TIMEOUTS.iter() {
+            match parse_timeout(item) {
+                Ok((addr, timeout_ms)) => {
+                    timeouts.timeouts.insert(addr, timeout_ms);
+                }
+                Err(e) => {
+                    error!("retry::load => {}", e);
+                    timeouts.errors.push(format!("DNS_SERVER_TIMEOUTS {}", e));
+                }
+            }
+        }
+        timeouts
+    }
+
+    fn get(&self, dns_server: &str) -> Option<u64> {
+        let addr = dns::dns_socket_addr(dns_server).ok()?;
+        self.timeouts.get(&addr.to_string()).copied()
+    }
+}
+
+//dns服务器=毫秒
+fn parse_timeout(item: &str) -> Result<(String, u64), String> {
+    let (dns_server, timeout_ms) = item
+        .split_once('=')
+        .ok_or_else(|| format!("{}: 格式应为 dns服务器=毫秒", item))?;
+    let addr = dns::dns_socket_addr(dns_server.trim())
+        .map_err(|e| format!("{}: {}", item, e.msg()))?
+        .to_string();
+    match timeout_ms.trim().parse() {
+        Ok(timeout_ms) if timeout_ms > 0 && timeout_ms <= constants::MAX_QUERY_TIMEOUT_MS => {
+            Ok((addr, timeout_ms))
+        }
+        _ => Err(format!(
+            "{}: 超时应为 1 到 {} 毫秒",
+            item,
+            constants::MAX_QUERY_TIMEOUT_MS
+        )),
+    }
+}
+
+//一个 dns 服务器的超时与重试: 请求中的值优先, 其次 DNS_SERVER_TIMEOUTS, 最后是全局配置
+#[derive(Debug, Clone, Copy)]
+pub struct QueryPolicy {
+    pub timeout: Duration,
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+impl QueryPolicy {
+    pub fn new(dns_server: &str, options: &QueryOptions) -> Self {
+        let timeout_ms = options
+            .timeout_ms
+            .or_else(|| TIMEOUTS.get(dns_server))
+            .unwrap_or(*constants::QUERY_TIMEOUT * 1000);
+        QueryPolicy {
+            timeout: Duration::from_millis(timeout_ms),
+            retries: options.retries.unwrap_or(*constants::QUERY_RETRIES),
+            backoff: Duration::from_millis(
+                options.backoff_ms.unwrap_or(*constants::QUERY_BACKOFF_MS),
+            ),
+        }
+    }
+
+    //第 attempt 次尝试前的等待, 第一次不等待
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match attempt {
+            0 | 1 => Duration::ZERO,
+            _ => self.backoff.saturating_mul(1 << (attempt - 2).min(16)),
+        }
+    }
+}
+
+//请求中的超时与重试
+pub fn check_options(options: &QueryOptions) -> Result<(), AppError> {
+    let invalid = |name: &str, min: u64, max: u64| {
+        AppError::new(ErrorCode::QueryOptionInvalid)
+            .arg("name", name)
+            .arg("min", min)
+            .arg("max", max)
+    };
+    if options
+        .timeout_ms
+        .is_some_and(|timeout_ms| timeout_ms == 0 || timeout_ms > constants::MAX_QUERY_TIMEOUT_MS)
+    {
+        return Err(invalid("timeoutMs", 1, constants::MAX_QUERY_TIMEOUT_MS));
+    }
+    if options
+        .retries
+        .is_some_and(|retries| retries > constants::MAX_QUERY_RETRIES)
+    {
+        return Err(invalid("retries", 0, constants::MAX_QUERY_RETRIES as u64));
+    }
+    if options
+        .backoff_ms
+        .is_some_and(|backoff_ms| backoff_ms > constants::MAX_QUERY_BACKOFF_MS)
+    {
+        return Err(invalid("backoffMs", 0, constants::MAX_QUERY_BACKOFF_MS));
+    }
+    Ok(())
+}
@@ -17,12 +17,48 @@
 
 use crate::controller;
 use actix_web::web;
-use actix_web::web::{get, post};
+use actix_web::web::{delete, get, post, put};
 
 //路由配置
 pub fn route(config: &mut web::ServiceConfig) {
     config
         .route("/", get().to(controller::index))
+        .route("/monitors", get().to(controller::monitors))
+        .route("/bulk", get().to(controller::bulk_page))
+        .route("/update", get().to(controller::update_page))
+        .route("/zone_sync", get().to(controller::zone_sync_page))
+        .route("/r/{share_id}", get().to(controller::permalink))
+        .route("/openapi.json", get().to(controller::openapi_json))
+        .route("/healthz", get().to(controller::healthz))
+        .route("/readyz", get().to(controller::readyz))
+        .route("/v1/selftest", get().to(controller::v1_selftest))
         .route("/v1/query", post().to(controller::v1_query))
-        .route("/v1/query_dnssec", post().to(controller::v1_query_dnssec));
+        .route("/v1/query_dnssec", post().to(controller::v1_query_dnssec))
+        .route("/v1/query_subnets", post().to(controller::v1_query_subnets))
+        .route("/v1/diagnose_dnssec", post().to(controller::v1_diagnose_dnssec))
+        .route("/v1/dnssec_health", post().to(controller::v1_dnssec_health))
+        .route("/v1/zone_sync", post().to(controller::v1_zone_sync))
+        .route("/v1/trust_anchors", get().to(controller::v1_trust_anchors))
+        .route("/v1/resolver_groups", get().to(controller::v1_resolver_groups))
+        .route("/v1/query/stream", get().to(controller::v1_query_stream))
+        .route("/v1/query_dnssec/stream", get().to(controller::v1_query_dnssec_stream))
+        .route("/v1/query_subnets/stream", get().to(controller::v1_query_subnets_stream))
+        .route("/v1/history", get().to(controller::v1_history))
+        .route("/v1/history/stream", get().to(controller::v1_history_stream))
+        .route("/v1/resolver", get().to(controller::v1_resolver))
+        .route("/v1/export", get().to(controller::v1_export))
+        .route("/v1/bulk", post().to(controller::v1_bulk_create))
+        .route("/v1/bulk/{id}", get().to(controller::v1_bulk_detail))
+        .route("/v1/bulk/{id}/events", get().to(controller::v1_bulk_events))
+        .route("/v1/bulk/{id}/report", get().to(controller::v1_bulk_report))
+        .route("/v1/update", post().to(controller::v1_update))
+        .route("/v1/update/keys", get().to(controller::v1_update_keys))
+        .route("/v1/update/audit", get().to(controller::v1_update_audit))
+        .route("/v1/notify", post().to(controller::v1_notify))
+        .route("/v1/monitors", get().to(controller::v1_monitor_list))
+        .route("/v1/monitors", post().to(controller::v1_monitor_create))
+        .route("/v1/monitors/changes", get().to(controller::v1_monitor_changes))
+        .route("/v1/monitors/{id}", get().to(controller::v1_monitor_detail))
+        .route("/v1/monitors/{id}", put().to(controller::v1_monitor_update))
+        .route("/v1/monitors/{id}", delete().to(controller::v1_monitor_delete));
 }
@@ -17,10 +17,16 @@
 
 use actix_cors::Cors;
 use crate::constants::AppError;
-use crate::{constants, route};
-use actix_web::dev::ServiceResponse;
+use crate::bulk::BulkStore;
+use crate::history::HistoryStore;
+use crate::monitor::MonitorStore;
+use crate::resolver::Resolver;
+use crate::i18n::Lang;
+use crate::{constants, history, i18n, monitor, problem, resolver, route};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
 use actix_web::middleware::TrailingSlash::Trim;
-use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers, Logger};
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers, Logger, from_fn};
 use actix_web::mime::TEXT_HTML_UTF_8;
 use actix_web::web::Data;
 use actix_web::{App, HttpResponse, HttpServer};
@@ -29,32 +35,35 @@ use tera::Tera;
 
 pub async fn create_server() -> std::io::Result<()> {
     let bind_address = (*constants::BIND_ADDRESS).clone();
-    let bind_port = (*constants::BIND_PORT).clone();
+    let bind_port = *constants::BIND_PORT;
 
+    //查询历史
+    let history = match HistoryStore::open(&constants::HISTORY_DB) {
+        Ok(history) => Data::new(history),
+        Err(e) => {
+            error!("!!! FAILED TO OPEN HISTORY DB !!! {} {}", *constants::HISTORY_DB, e);
+            return Err(std::io::Error::other(e.to_string()));
+        }
+    };
+
+    tokio::spawn(history::run_expire_share_links(history.clone()));
+
+    //监控任务, 所有 worker 共享
+    let monitors = Data::new(MonitorStore::new());
+    tokio::spawn(monitor::run_scheduler(monitors.clone()));
 
+    //批量查询任务
+    let bulk = Data::new(BulkStore::new());
+
+    //本地转发解析器, 查询写入历史
+    let resolver = Data::new(Resolver::new(history.clone()));
+    if *constants::RESOLVER_PORT != 0 {
+        tokio::spawn(resolver::run(resolver.clone()));
+    }
 
     //服务
     let server = match HttpServer::new(move || {
-        App::new()
-            .wrap(
-                Cors::default().allowed_origin("http://127.0.0.1:5500")
-                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"]) // 允许所有常用方法
-                    .allowed_headers(vec!["Content-Type", "Authorization", "X-Requested-With", "Accept"]) // 允许常用请求头
-                    .max_age(3600),  // 缓存预检请求的时间，单位秒
-            )
-            .app_data(Data::new((*constants::TEMPLATES).clone()))
-            .wrap(Logger::default())
-            .wrap(actix_web::middleware::Compress::default())
-            .wrap(actix_web::middleware::NormalizePath::new(Trim))
-            .configure(route::route)
-            .wrap(ErrorHandlers::new().default_handler(error_handler))
-            .service(
-                actix_files::Files::new(
-                    constants::STATIC_MOUNT_PREFIX,
-                    constants::STATIC_SERVE_FROM,
-                )
-                .use_last_modified(true),
-            )
+        app(history.clone(), monitors.clone(), bulk.clone(), resolver.clone())
     })
     .bind((bind_address, bind_port))
     {
@@ -80,15 +89,80 @@ pub async fn create_server() -> std::io::Result<()> {
     Ok(())
 }
 
+//路由与中间件, 集成测试通过 actix_web::test 使用同一个 App
+pub fn app(
+    history: Data<HistoryStore>,
+    monitors: Data<MonitorStore>,
+    bulk: Data<BulkStore>,
+    resolver: Data<Resolver>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .wrap(
+            Cors::default().allowed_origin("http://127.0.0.1:5500")
+                .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"]) // 允许所有常用方法
+                .allowed_headers(vec!["Content-Type", "Authorization", "X-Requested-With", "Accept"]) // 允许常用请求头
+                .max_age(3600),  // 缓存预检请求的时间，单位秒
+        )
+        .app_data(Data::new((*constants::TEMPLATES).clone()))
+        .app_data(monitors)
+        .app_data(history)
+        .app_data(bulk)
+        .app_data(resolver)
+        //错误按 Accept / Accept-Language 输出, 需要在 Compress 内层
+        .wrap(from_fn(problem::render_errors))
+        .wrap(Logger::default())
+        .wrap(actix_web::middleware::Compress::default())
+        .wrap(actix_web::middleware::NormalizePath::new(Trim))
+        .configure(route::route)
+        .wrap(ErrorHandlers::new().default_handler(error_handler))
+        .service(
+            actix_files::Files::new(
+                constants::STATIC_MOUNT_PREFIX,
+                constants::STATIC_SERVE_FROM,
+            )
+            .use_last_modified(true),
+        )
+}
+
 fn error_handler<B>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    //健康检查未就绪时返回 503 与检查结果, 原样输出
+    if constants::PROBE_PATHS.contains(&res.request().path()) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+    //接口始终返回 json, AppError 已由 problem::render_errors 输出, 其他错误转为对应错误码
+    if problem::is_api(res.request()) {
+        if res
+            .response()
+            .error()
+            .is_some_and(|e| e.as_error::<AppError>().is_some())
+        {
+            return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+        }
+        let (request, response) = res.into_parts();
+        let detail = response.error().map(|e| e.to_string());
+        let error = problem::from_status(response.status(), &request, detail);
+        let response = problem::error_response(&error, &request);
+        return Ok(ErrorHandlerResponse::Response(
+            ServiceResponse::new(request, response).map_into_right_body(),
+        ));
+    }
+
     let (request, response) = res.into_parts();
 
     let tera = request
         .app_data::<Data<Tera>>()
         .cloned()
-        .ok_or_else(|| AppError::bus("error_handler: 从 app_data 中获取 Tera 失败!"))?;
+        .ok_or_else(|| AppError::internal("error_handler: 从 app_data 中获取 Tera 失败!"))?;
 
-    let mut context = tera::Context::new();
+    let mut context = i18n::context(Lang::from_headers(request.headers()));
     context.insert("path", request.path());
     context.insert("method", request.method().as_str());
     context.insert("uri", request.uri().to_string().as_str());
@@ -0,0 +1,78 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::HttpResponse;
+use actix_web::http::header;
+use actix_web::mime;
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt, stream};
+use serde::Serialize;
+use std::future::Future;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+//缓冲的 result 事件数, 客户端读取慢时任务等待
+const CHANNEL_SIZE: usize = 16;
+
+//一条 Server-Sent Events 消息
+pub fn event<T: Serialize>(event: &str, data: &T) -> Bytes {
+    let json = serde_json::to_string(data).unwrap_or_default();
+    Bytes::from(format!("event: {}\ndata: {}\n\n", event, json))
+}
+
+//text/event-stream 响应
+pub fn response<S>(stream: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, actix_web::Error>> + 'static,
+{
+    HttpResponse::Ok()
+        .content_type(mime::TEXT_EVENT_STREAM)
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        //不压缩, 否则消息会被缓冲
+        .insert_header(header::ContentEncoding::Identity)
+        .streaming(stream)
+}
+
+//并发执行所有任务, 每个完成后立即推送 result 事件, 全部完成后推送 done
+//任务由响应流持有, 客户端断开时随 JoinSet 一起取消
+pub fn spawn_all<T, F>(tasks: Vec<F>) -> HttpResponse
+where
+    T: Serialize + Send + 'static,
+    F: Future<Output = T> + Send + 'static,
+{
+    let total = tasks.len();
+    let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+    let mut running = JoinSet::new();
+    for task in tasks {
+        let sender = sender.clone();
+        running.spawn(async move {
+            let _ = sender.send(event("result", &task.await)).await;
+        });
+    }
+    let done = event("done", &serde_json::json!({ "total": total }));
+    let results = stream::unfold((receiver, running), |(mut receiver, running)| async move {
+        receiver
+            .recv()
+            .await
+            .map(|bytes| (bytes, (receiver, running)))
+    });
+    response(
+        results
+            .chain(stream::once(async move { done }))
+            .map(Ok::<_, actix_web::Error>),
+    )
+}
@@ -0,0 +1,402 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants;
+use crate::constants::{
+    AppError, DnsUpdateRequest, DnsUpdateResult, ErrorCode, QueryOptions, UpdatePrerequisite,
+};
+use crate::dns;
+use crate::history::HistoryStore;
+use crate::retry::QueryPolicy;
+use actix_web::HttpRequest;
+use actix_web::http::header;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hickory_client::client::Client;
+use hickory_client::proto::DnsHandle;
+use hickory_client::proto::dnssec::rdata::tsig::TsigAlgorithm;
+use hickory_client::proto::dnssec::tsig::TSigner;
+use hickory_client::proto::op::{Message, MessageType, OpCode, Query, ResponseCode, UpdateMessage};
+use hickory_client::proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_client::proto::runtime::TokioRuntimeProvider;
+use hickory_client::proto::serialize::txt::RDataParser;
+use hickory_client::proto::udp::UdpClientStream;
+use hickory_client::proto::xfer::{DnsRequest, DnsRequestOptions, DnsResponse, FirstAnswer};
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+lazy_static! {
+    pub static ref KEYS: TsigKeys = TsigKeys::load();
+}
+
+//TSIG_KEYS, 密钥只用于签名, 不会出现在接口与日志中
+pub struct TsigKeys {
+    keys: Vec<(String, TSigner)>,
+    pub errors: Vec<String>,
+}
+
+//名称:算法:base64 密钥, 算法为 hmac-sha256 / hmac-sha384 / hmac-sha512
+fn parse_key(item: &str) -> Result<(String, TSigner), String> {
+    let mut parts = item.splitn(3, ':').map(str::trim);
+    let (Some(name), Some(algorithm), Some(secret)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("格式应为 名称:算法:base64 密钥".to_string());
+    };
+    let signer_name = Name::from_ascii(name).map_err(|e| format!("{}: {}", name, e))?;
+    let algorithm = Name::from_ascii(algorithm.trim_end_matches('.').to_lowercase())
+        .map(TsigAlgorithm::from_name)
+        .map_err(|e| format!("{}: {}", name, e))?;
+    let secret = STANDARD
+        .decode(secret)
+        .map_err(|e| format!("{}: 密钥不是 base64: {}", name, e))?;
+    let signer =
+        TSigner::new(secret, algorithm, signer_name, constants::TSIG_FUDGE).map_err(|e| {
+            format!(
+                "{}: {}, 可选 hmac-sha256 / hmac-sha384 / hmac-sha512",
+                name, e
+            )
+        })?;
+    Ok((name.trim_end_matches('.').to_string(), signer))
+}
+
+impl TsigKeys {
+    fn load() -> Self {
+        let mut keys = TsigKeys {
+            keys: vec![],
+            errors: vec![],
+        };
+        for item in constants::TSIG_KEYS.iter() {
+            match parse_key(item) {
+                Ok(key) => keys.keys.push(key),
+                Err(e) => {
+                    error!("update::load => {}", e);
+                    keys.errors.push(format!("TSIG_KEYS {}", e));
+                }
+            }
+        }
+        keys
+    }
+
+    pub fn find(&self, name: &str) -> Option<&TSigner> {
+        let name = name.trim().trim_end_matches('.');
+        self.keys
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, signer)| signer)
+    }
+
+    //密钥名称与算法
+    pub fn names(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .map(|(name, signer)| format!("{} ({})", name, signer.algorithm()))
+            .collect()
+    }
+}
+
+//Authorization: Bearer <UPDATE_TOKEN>, 没有配置令牌时不启用
+pub fn authorize(req: &HttpRequest) -> Result<(), AppError> {
+    let expected = constants::UPDATE_TOKEN.as_bytes();
+    if expected.is_empty() {
+        return Err(AppError::new(ErrorCode::UpdateDisabled));
+    }
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .trim()
+        .as_bytes();
+    //按字节异或比较, 耗时与令牌内容无关
+    let matched = token.len() == expected.len()
+        && token
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if matched {
+        Ok(())
+    } else {
+        Err(AppError::new(ErrorCode::Unauthorized))
+    }
+}
+
+pub fn zone_name(zone: &str) -> Result<Name, AppError> {
+    let zone = zone.trim().trim_end_matches('.');
+    if zone.is_empty() {
+        return Err(AppError::new(ErrorCode::DomainEmpty));
+    }
+    Name::from_ascii(format!("{}.", zone))
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", zone))
+}
+
+//相对 zone 的名称, 以 . 结尾时为完整名称, @ 或为空时为 zone 本身
+fn owner_name(zone: &Name, name: &str) -> Result<Name, AppError> {
+    let name = name.trim();
+    let owner = if name.is_empty() || name == "@" {
+        Ok(zone.clone())
+    } else if name.ends_with('.') {
+        Name::from_ascii(name)
+    } else {
+        Name::from_ascii(name).and_then(|name| name.append_domain(zone))
+    }
+    .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", name))?;
+    if !zone.zone_of(&owner) {
+        return Err(AppError::new(ErrorCode::UpdateNameOutsideZone)
+            .arg("name", owner)
+            .arg("zone", zone));
+    }
+    Ok(owner)
+}
+
+fn record_type(record_type: &str) -> Result<RecordType, AppError> {
+    let unsupported =
+        || AppError::new(ErrorCode::RecordTypeUnsupported).arg("recordType", record_type);
+    match RecordType::from_str(&record_type.trim().to_uppercase()) {
+        Ok(
+            RecordType::ANY
+            | RecordType::AXFR
+            | RecordType::IXFR
+            | RecordType::OPT
+            | RecordType::TSIG
+            | RecordType::Unknown(_),
+        )
+        | Err(_) => Err(unsupported()),
+        Ok(record_type) => Ok(record_type),
+    }
+}
+
+//zone 文件格式的记录值
+fn records(
+    name: &Name,
+    record_type: RecordType,
+    ttl: u32,
+    values: &[String],
+) -> Result<Vec<Record>, AppError> {
+    values
+        .iter()
+        .map(|value| {
+            RData::try_from_str(record_type, value.trim())
+                .map(|rdata| Record::from_rdata(name.clone(), ttl, rdata))
+                .map_err(|e| {
+                    AppError::new(ErrorCode::UpdateRecordInvalid)
+                        .arg("recordType", record_type)
+                        .arg("value", value)
+                        .arg("cause", e)
+                })
+        })
+        .collect()
+}
+
+//没有 rdata 的记录, class 为 ANY / NONE 时表示 RRset 或名称 (RFC 2136 2.4 / 2.5)
+fn empty_record(name: &Name, record_type: RecordType, class: DNSClass) -> Record {
+    let mut record = Record::update0(name.clone(), 0, record_type);
+    record.set_dns_class(class);
+    record
+}
+
+fn prerequisite_records(
+    prerequisite: &UpdatePrerequisite,
+    zone: &Name,
+    name: &Name,
+    default_type: RecordType,
+) -> Result<Vec<Record>, AppError> {
+    let name = match prerequisite.name.as_deref() {
+        Some(value) if !value.trim().is_empty() => owner_name(zone, value)?,
+        _ => name.clone(),
+    };
+    let rr_type = match prerequisite.record_type.as_deref() {
+        Some(value) if !value.trim().is_empty() => record_type(value)?,
+        _ => default_type,
+    };
+    match prerequisite.kind.as_str() {
+        constants::PREREQ_NAME_EXISTS => {
+            Ok(vec![empty_record(&name, RecordType::ANY, DNSClass::ANY)])
+        }
+        constants::PREREQ_NAME_NOT_EXISTS => {
+            Ok(vec![empty_record(&name, RecordType::ANY, DNSClass::NONE)])
+        }
+        constants::PREREQ_RRSET_EXISTS => Ok(vec![empty_record(&name, rr_type, DNSClass::ANY)]),
+        constants::PREREQ_RRSET_NOT_EXISTS => {
+            Ok(vec![empty_record(&name, rr_type, DNSClass::NONE)])
+        }
+        constants::PREREQ_RRSET_EQUALS if prerequisite.values.is_empty() => {
+            Err(AppError::new(ErrorCode::UpdateValuesRequired)
+                .arg("action", constants::PREREQ_RRSET_EQUALS))
+        }
+        constants::PREREQ_RRSET_EQUALS => records(&name, rr_type, 0, &prerequisite.values),
+        kind => Err(AppError::new(ErrorCode::UpdatePrerequisiteUnsupported).arg("kind", kind)),
+    }
+}
+
+//zone 区为 SOA 查询, 前提条件区与更新区按 RFC 2136 的 class 约定
+fn update_message(
+    request: &DnsUpdateRequest,
+    zone: &Name,
+    name: &Name,
+    rr_type: RecordType,
+) -> Result<Message, AppError> {
+    let mut message = Message::new();
+    message
+        .set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Update)
+        .set_recursion_desired(false);
+    message.add_zone(Query::query(zone.clone(), RecordType::SOA));
+    for prerequisite in &request.prerequisites {
+        message.add_pre_requisites(prerequisite_records(prerequisite, zone, name, rr_type)?);
+    }
+
+    let ttl = request.ttl.unwrap_or(constants::UPDATE_DEFAULT_TTL);
+    let values: Vec<String> = request
+        .values
+        .iter()
+        .filter(|value| !value.trim().is_empty())
+        .cloned()
+        .collect();
+    match request.action.as_str() {
+        constants::UPDATE_ADD | constants::UPDATE_REPLACE if values.is_empty() => {
+            return Err(
+                AppError::new(ErrorCode::UpdateValuesRequired).arg("action", &request.action)
+            );
+        }
+        constants::UPDATE_ADD => message.add_updates(records(name, rr_type, ttl, &values)?),
+        constants::UPDATE_REPLACE => {
+            message.add_update(empty_record(name, rr_type, DNSClass::ANY));
+            message.add_updates(records(name, rr_type, ttl, &values)?);
+        }
+        constants::UPDATE_DELETE if values.is_empty() => {
+            message.add_update(empty_record(name, rr_type, DNSClass::ANY));
+        }
+        constants::UPDATE_DELETE => {
+            for mut record in records(name, rr_type, 0, &values)? {
+                record.set_dns_class(DNSClass::NONE);
+                message.add_update(record);
+            }
+        }
+        action => {
+            return Err(AppError::new(ErrorCode::UpdateActionUnsupported).arg("action", action));
+        }
+    }
+    Ok(message)
+}
+
+//UPDATE / NOTIFY 通过 UDP 发送, 有密钥时签名并校验应答的 TSIG
+pub async fn send(
+    dns_server: &str,
+    socket_addr: SocketAddr,
+    signer: Option<&TSigner>,
+    message: Message,
+) -> Result<DnsResponse, AppError> {
+    let policy = QueryPolicy::new(dns_server, &QueryOptions::default());
+    let conn = UdpClientStream::builder(socket_addr, TokioRuntimeProvider::default())
+        .with_timeout(Some(policy.timeout))
+        .with_signer(signer.map(|signer| Arc::new(signer.clone()) as _))
+        .build();
+    let (client, bg) = Client::connect(conn)
+        .await
+        .map_err(|e| dns::proto_error(dns_server, &e))?;
+    tokio::spawn(bg);
+    client
+        .send(DnsRequest::new(message, DnsRequestOptions::default()))
+        .first_answer()
+        .await
+        .map_err(|e| dns::proto_error(dns_server, &e))
+}
+
+//YXDOMAIN / YXRRSET / NXDOMAIN / NXRRSET 为前提条件不满足, 其余非 NOERROR 为拒绝
+fn check_update_response(dns_server: &str, response: &DnsResponse) -> Result<(), AppError> {
+    let response_code = dns::response_code_name(response);
+    match response.response_code() {
+        ResponseCode::NoError => Ok(()),
+        ResponseCode::YXDomain
+        | ResponseCode::YXRRSet
+        | ResponseCode::NXDomain
+        | ResponseCode::NXRRSet => Err(AppError::new(ErrorCode::DnsUpdatePrerequisiteFailed)
+            .arg("responseCode", response_code)),
+        _ => Err(AppError::new(ErrorCode::DnsUpdateRejected)
+            .arg("dnsServer", dns_server)
+            .arg("responseCode", response_code)),
+    }
+}
+
+//校验请求, 写入审计日志后发送, 结果同时更新到审计日志
+pub async fn update(
+    history: &HistoryStore,
+    requester: &str,
+    request: DnsUpdateRequest,
+) -> Result<DnsUpdateResult, AppError> {
+    let zone = zone_name(&request.zone)?;
+    let name = owner_name(&zone, &request.name)?;
+    let rr_type = record_type(&request.record_type)?;
+    let socket_addr = dns::dns_socket_addr(&request.dns_server)?;
+    let signer = KEYS.find(&request.key_name).ok_or_else(|| {
+        AppError::new(ErrorCode::TsigKeyNotFound).arg("keyName", &request.key_name)
+    })?;
+    let message = update_message(&request, &zone, &name, rr_type)?;
+
+    let zone_text = zone.to_string().trim_end_matches('.').to_lowercase();
+    let audit_id = history
+        .begin_audit(requester, &request, &zone_text, &name.to_string())
+        .await?;
+    info!(
+        "update::update => #{} {} {} {} {} @{} key={} requester={}",
+        audit_id,
+        request.action,
+        name,
+        rr_type,
+        zone_text,
+        socket_addr,
+        request.key_name,
+        requester
+    );
+    let started = Instant::now();
+    let response = send(&request.dns_server, socket_addr, Some(signer), message).await;
+    let response_code = response.as_ref().ok().map(dns::response_code_name);
+    let result = response
+        .as_ref()
+        .map_err(Clone::clone)
+        .and_then(|response| check_update_response(&request.dns_server, response));
+    history
+        .finish_audit(
+            audit_id,
+            response_code.as_deref(),
+            started.elapsed(),
+            &result,
+        )
+        .await;
+    if let Err(e) = &result {
+        info!("update::update => #{} failed: {}", audit_id, e.msg());
+    }
+    result?;
+
+    Ok(DnsUpdateResult {
+        zone: zone_text,
+        dns_server: request.dns_server,
+        action: request.action,
+        name: name.to_string(),
+        record_type: rr_type.to_string(),
+        response_code: response_code.unwrap_or_default(),
+        audit_id,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,215 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::constants::{AppError, ErrorCode, ZoneSyncQuery, ZoneSyncReport, ZoneSyncServer};
+use crate::i18n::Lang;
+use crate::{dns, idn, notify};
+use futures_util::future;
+use hickory_client::proto::rr::{Name, RData, RecordType};
+use log::info;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Instant;
+
+fn server(ns: &Name, address: Option<IpAddr>) -> ZoneSyncServer {
+    ZoneSyncServer {
+        ns: ns.to_string(),
+        address: address.map(|ip| ip.to_string()).unwrap_or_default(),
+        family: match address {
+            Some(IpAddr::V4(_)) => "ipv4",
+            Some(IpAddr::V6(_)) => "ipv6",
+            None => "",
+        }
+        .to_string(),
+        serial: None,
+        mname: None,
+        refresh: None,
+        retry: None,
+        expire: None,
+        minimum: None,
+        authoritative: false,
+        duration_ms: None,
+        lag: None,
+        behind: false,
+        error: None,
+    }
+}
+
+//NS 的 A 与 AAAA, 查询失败时返回空, 由调用方记录
+async fn addresses(dns_server: &str, ns: &Name) -> Result<Vec<IpAddr>, AppError> {
+    let (a, aaaa) = tokio::join!(
+        dns::query_raw(dns_server, ns.clone(), RecordType::A, false),
+        dns::query_raw(dns_server, ns.clone(), RecordType::AAAA, false),
+    );
+    let mut addresses = vec![];
+    let mut error = None;
+    for response in [a, aaaa] {
+        match response {
+            Ok(response) => {
+                addresses.extend(response.answers().iter().filter_map(
+                    |record| match record.data() {
+                        RData::A(a) => Some(IpAddr::V4(a.0)),
+                        RData::AAAA(aaaa) => Some(IpAddr::V6(aaaa.0)),
+                        _ => None,
+                    },
+                ))
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    match error {
+        Some(e) if addresses.is_empty() => Err(e),
+        _ => Ok(addresses),
+    }
+}
+
+//向一个 NS 地址直接查询 SOA
+async fn check_server(zone: &Name, ns: &Name, ip: IpAddr, port: u16, lang: Lang) -> ZoneSyncServer {
+    let mut result = server(ns, Some(ip));
+    let socket_addr = SocketAddr::new(ip, port);
+    let label = format!("{}/{}", ns, socket_addr);
+    let started = Instant::now();
+    let response =
+        dns::query_authoritative(&label, socket_addr, zone.clone(), RecordType::SOA).await;
+    match response {
+        Ok(response) => {
+            result.duration_ms = Some(started.elapsed().as_millis() as u64);
+            result.authoritative = response.authoritative();
+            let soa = dns::check_response_code(&label, &response).and_then(|_| {
+                notify::soa_record(&response, zone).ok_or_else(|| {
+                    AppError::new(ErrorCode::SoaNotFound)
+                        .arg("dnsServer", &label)
+                        .arg("zone", zone)
+                })
+            });
+            match soa.as_ref().map(|record| record.data()) {
+                Ok(RData::SOA(soa)) => {
+                    result.serial = Some(soa.serial());
+                    result.mname = Some(soa.mname().to_string());
+                    result.refresh = Some(soa.refresh());
+                    result.retry = Some(soa.retry());
+                    result.expire = Some(soa.expire());
+                    result.minimum = Some(soa.minimum());
+                }
+                Ok(_) => {}
+                Err(e) => result.error = Some(e.localize(lang)),
+            }
+        }
+        Err(e) => result.error = Some(e.localize(lang)),
+    }
+    result
+}
+
+//RFC 1982 下最新的 serial
+fn max_serial(serials: impl Iterator<Item = u32>) -> Option<u32> {
+    serials.reduce(|max, serial| {
+        if notify::serial_reached(serial, max) {
+            serial
+        } else {
+            max
+        }
+    })
+}
+
+//查询 zone 的 NS 与每个 NS 的 IPv4 / IPv6 地址, 再直接向每个地址查询 SOA, 标记落后于最大 serial 的服务器
+pub async fn check(query: ZoneSyncQuery, lang: Lang) -> Result<ZoneSyncReport, AppError> {
+    if query.zone.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::DomainEmpty));
+    }
+    let (ascii_zone, _) = idn::normalize(&query.zone)?;
+    dns::resolve_dns_addr(&query.dns_server)?;
+    let zone = Name::from_str(&format!("{}.", ascii_zone.trim_end_matches('.')))
+        .map_err(|_| AppError::new(ErrorCode::DomainInvalid).arg("domain", &query.zone))?;
+    let port = query.port.filter(|port| *port != 0).unwrap_or(53);
+    let started = Instant::now();
+
+    let response = dns::query_raw(&query.dns_server, zone.clone(), RecordType::NS, false).await?;
+    let mut nameservers: Vec<Name> = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::NS(ns) => Some(ns.0.to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    nameservers.sort();
+    nameservers.dedup();
+    if nameservers.is_empty() {
+        return Err(AppError::new(ErrorCode::NsNotFound)
+            .arg("dnsServer", &query.dns_server)
+            .arg("zone", &zone));
+    }
+    info!(
+        "zonesync::check => {} NS {:?} @{}",
+        zone, nameservers, query.dns_server
+    );
+
+    let resolved = future::join_all(
+        nameservers
+            .iter()
+            .map(|ns| addresses(&query.dns_server, ns)),
+    )
+    .await;
+    let mut servers = vec![];
+    let mut checks = vec![];
+    for (ns, addresses) in nameservers.iter().zip(resolved) {
+        match addresses {
+            Ok(addresses) if !addresses.is_empty() => {
+                checks.extend(
+                    addresses
+                        .into_iter()
+                        .map(|ip| check_server(&zone, ns, ip, port, lang)),
+                );
+            }
+            Ok(_) => {
+                let mut result = server(ns, None);
+                let error = AppError::new(ErrorCode::NsAddressNotFound)
+                    .arg("dnsServer", &query.dns_server)
+                    .arg("ns", ns);
+                result.error = Some(error.localize(lang));
+                servers.push(result);
+            }
+            Err(e) => {
+                let mut result = server(ns, None);
+                result.error = Some(e.localize(lang));
+                servers.push(result);
+            }
+        }
+    }
+    servers.extend(future::join_all(checks).await);
+    servers.sort_by(|a, b| (&a.ns, &a.family, &a.address).cmp(&(&b.ns, &b.family, &b.address)));
+
+    let max = max_serial(servers.iter().filter_map(|server| server.serial));
+    for server in servers.iter_mut() {
+        if let (Some(max), Some(serial)) = (max, server.serial) {
+            server.lag = Some(max.wrapping_sub(serial));
+            server.behind = serial != max;
+        }
+    }
+    let consistent = servers
+        .iter()
+        .all(|server| server.serial.is_some() && !server.behind);
+
+    Ok(ZoneSyncReport {
+        zone: zone.to_string().trim_end_matches('.').to_string(),
+        dns_server: query.dns_server,
+        max_serial: max,
+        consistent,
+        servers,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
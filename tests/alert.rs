@@ -0,0 +1,292 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//告警发送: 本地 http / smtp 服务器接收, 验证去重、失败重试与发送内容
+use dns_tool::alert::AlertDispatcher;
+use dns_tool::constants::{
+    AlertChannel, DnsRecordResult, MONITOR_CHANGE_RRSET, MonitorAnswer, MonitorChange, MonitorJob,
+};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+static INIT: Once = Once::new();
+
+//smtp 服务器地址在 SMTP_HOST / SMTP_PORT 第一次读取前写入
+fn init(smtp_port: Option<u16>) {
+    INIT.call_once(|| unsafe {
+        env::set_var("ALERT_RETRY_BACKOFF_MS", "50");
+        env::set_var("ALERT_LANG", "en");
+    });
+    if let Some(port) = smtp_port {
+        unsafe {
+            env::set_var("SMTP_HOST", "127.0.0.1");
+            env::set_var("SMTP_PORT", port.to_string());
+        }
+    }
+}
+
+//http 服务器, 按顺序返回给定的状态码 (用完后返回 200), 收到的请求体写入 channel
+async fn http_server(statuses: Vec<u16>) -> (u16, mpsc::UnboundedReceiver<Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let statuses = Arc::new(Mutex::new(VecDeque::from(statuses)));
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).await.unwrap();
+            tx.send(serde_json::from_slice(&body).unwrap()).unwrap();
+
+            let status = statuses.lock().unwrap().pop_front().unwrap_or(200);
+            let response = format!(
+                "HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                status
+            );
+            reader
+                .into_inner()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+    (port, rx)
+}
+
+//只实现告警用到的 smtp 命令, 收到的邮件内容写入 channel
+async fn smtp_server() -> (u16, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut reader = BufReader::new(read);
+            write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut data: Option<String> = None;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                if let Some(mail) = data.as_mut() {
+                    if line == ".\r\n" {
+                        tx.send(data.take().unwrap()).unwrap();
+                        write.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        mail.push_str(&line);
+                    }
+                    continue;
+                }
+                let command = line.to_uppercase();
+                if command.starts_with("DATA") {
+                    data = Some(String::new());
+                    write.write_all(b"354 go ahead\r\n").await.unwrap();
+                } else if command.starts_with("QUIT") {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    write.write_all(b"250 ok\r\n").await.unwrap();
+                }
+            }
+        }
+    });
+    (port, rx)
+}
+
+fn job(id: u64, alerts: Vec<AlertChannel>) -> MonitorJob {
+    MonitorJob {
+        id,
+        domain: "example.test".to_string(),
+        record_type: "A".to_string(),
+        resolvers: vec!["127.0.0.1".to_string()],
+        interval: 300,
+        alerts,
+        created_at: 0,
+        last_run_at: None,
+    }
+}
+
+fn answer(value: &str) -> MonitorAnswer {
+    MonitorAnswer {
+        dns_server: "127.0.0.1".to_string(),
+        checked_at: 1,
+        dns_record: vec![DnsRecordResult {
+            name: "example.test.".to_string(),
+            value: value.to_string(),
+            ttl: 300,
+        }],
+        authoritative: false,
+        dnssec: "Insecure".to_string(),
+        error: None,
+    }
+}
+
+fn change(job: &MonitorJob, before: &str, after: &str) -> MonitorChange {
+    MonitorChange {
+        monitor_id: job.id,
+        domain: job.domain.clone(),
+        record_type: job.record_type.clone(),
+        dns_server: "127.0.0.1".to_string(),
+        changed_at: 1,
+        kind: MONITOR_CHANGE_RRSET.to_string(),
+        before: before.to_string(),
+        after: after.to_string(),
+    }
+}
+
+async fn next<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("alert not delivered")
+        .unwrap()
+}
+
+async fn nothing_more<T>(rx: &mut mpsc::UnboundedReceiver<T>) {
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), rx.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[actix_web::test]
+async fn webhook_body_is_alert_payload_and_flapping_is_suppressed() {
+    init(None);
+    let (port, mut rx) = http_server(vec![]).await;
+    let dispatcher = AlertDispatcher::new();
+    let job = job(
+        1,
+        vec![AlertChannel::Webhook {
+            url: format!("http://127.0.0.1:{}/hook", port),
+        }],
+    );
+
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "192.0.2.1", "192.0.2.2")],
+        &answer("192.0.2.2"),
+    );
+    let body = next(&mut rx).await;
+    assert_eq!(body["monitorId"], 1);
+    assert_eq!(body["domain"], "example.test");
+    assert_eq!(body["kind"], MONITOR_CHANGE_RRSET);
+    assert_eq!(body["before"], "192.0.2.1");
+    assert_eq!(body["after"], "192.0.2.2");
+    assert_eq!(body["result"]["dnsServer"], "127.0.0.1");
+    assert_eq!(body["result"]["recordType"], "A");
+    assert_eq!(body["result"]["record"][0]["value"], "192.0.2.2");
+    assert_eq!(body["result"]["record"][0]["ttl"], 300);
+
+    //解析器在两个结果之间来回切换, 去重时间内每个结果只告警一次
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "192.0.2.2", "192.0.2.1")],
+        &answer("192.0.2.1"),
+    );
+    assert_eq!(next(&mut rx).await["after"], "192.0.2.1");
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "192.0.2.1", "192.0.2.2")],
+        &answer("192.0.2.2"),
+    );
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "192.0.2.2", "192.0.2.1")],
+        &answer("192.0.2.1"),
+    );
+    nothing_more(&mut rx).await;
+}
+
+#[actix_web::test]
+async fn failed_webhook_is_retried() {
+    init(None);
+    let (port, mut rx) = http_server(vec![500]).await;
+    let dispatcher = AlertDispatcher::new();
+    let job = job(
+        2,
+        vec![AlertChannel::Slack {
+            url: format!("http://127.0.0.1:{}/slack", port),
+        }],
+    );
+
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "", "192.0.2.3")],
+        &answer("192.0.2.3"),
+    );
+    let first = next(&mut rx).await;
+    let second = next(&mut rx).await;
+    assert_eq!(first, second);
+    assert_eq!(
+        first["text"],
+        "[dns-tool] Monitor #2 example.test A @ 127.0.0.1: RRSET changed\nBefore: -\nAfter: 192.0.2.3"
+    );
+    //第二次返回 200, 不再重试
+    nothing_more(&mut rx).await;
+}
+
+#[actix_web::test]
+async fn email_is_delivered_over_smtp() {
+    let (port, mut rx) = smtp_server().await;
+    init(Some(port));
+    let dispatcher = AlertDispatcher::new();
+    let job = job(
+        3,
+        vec![AlertChannel::Email {
+            to: vec!["ops@example.test".to_string()],
+        }],
+    );
+
+    dispatcher.dispatch(
+        &job,
+        vec![change(&job, "192.0.2.1", "192.0.2.4")],
+        &answer("192.0.2.4"),
+    );
+    let mail = next(&mut rx).await;
+    assert!(mail.contains("To: ops@example.test"));
+    assert!(
+        mail.contains("Subject: [dns-tool] Monitor #3 example.test A @ 127.0.0.1: RRSET changed")
+    );
+    assert!(mail.contains("Before: 192.0.2.1"));
+    assert!(mail.contains("After: 192.0.2.4"));
+    assert!(mail.contains("\"monitorId\": 3"));
+}
@@ -0,0 +1,240 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//批量查询: 文本/csv 与 json 提交、查询次数上限、同时运行的任务数上限、csv 与 json 报告
+mod common;
+
+use actix_web::test;
+use actix_web::web::Data;
+use common::MockServer;
+use dns_tool::bulk::{self, BulkStore};
+use dns_tool::constants::{BulkTextQuery, ErrorCode};
+use dns_tool::history::HistoryStore;
+use dns_tool::monitor::MonitorStore;
+use dns_tool::resolver::Resolver;
+use dns_tool::server;
+use serde_json::{Value, json};
+use std::env;
+use std::sync::Once;
+use std::time::Duration;
+
+static INIT: Once = Once::new();
+
+//上限在第一次读取前写入
+fn init() {
+    common::init();
+    INIT.call_once(|| unsafe {
+        env::set_var("BULK_MAX_QUERIES", "4");
+        env::set_var("BULK_MAX_RUNNING", "2");
+    });
+}
+
+fn defaults(dns_server: &str) -> BulkTextQuery {
+    BulkTextQuery {
+        record_type: None,
+        dns_server: Some(dns_server.to_string()),
+    }
+}
+
+//同一个测试内共享 BulkStore
+async fn call(store: &Data<BulkStore>, request: test::TestRequest) -> (u16, String) {
+    let history = Data::new(HistoryStore::open(":memory:").unwrap());
+    let app = test::init_service(server::app(
+        history.clone(),
+        Data::new(MonitorStore::new()),
+        store.clone(),
+        Data::new(Resolver::new(history)),
+    ))
+    .await;
+    let response = test::call_service(&app, request.to_request()).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+async fn call_json(store: &Data<BulkStore>, request: test::TestRequest) -> (u16, Value) {
+    let (status, body) = call(store, request).await;
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+//等待任务完成
+async fn wait(store: &Data<BulkStore>, id: u64) {
+    for _ in 0..100 {
+        if store.job(id).unwrap().progress.finished {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("bulk job {id} not finished");
+}
+
+#[test]
+async fn text_input() {
+    init();
+    let text = "# 注释\n\
+                domain,recordType,dnsServer\n\
+                \n\
+                \"www.example.test\",\"AAAA\",\"127.0.0.1:53\"\n\
+                v6.example.test\n\
+                \u{20}\u{20}# 缩进的注释\n\
+                alias.example.test,CNAME,";
+    let tasks = bulk::tasks_from_text(text, &defaults("google")).unwrap();
+    let tasks: Vec<_> = tasks
+        .iter()
+        .map(|task| {
+            (
+                task.domain.as_str(),
+                task.record_type.as_str(),
+                task.dns_server.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        tasks,
+        [
+            ("www.example.test", "AAAA", "127.0.0.1:53"),
+            ("v6.example.test", "A", "google"),
+            ("alias.example.test", "CNAME", "google"),
+        ]
+    );
+
+    //tab 分隔
+    let tasks = bulk::tasks_from_text("www.example.test\tTXT\n", &defaults("google")).unwrap();
+    assert_eq!(tasks[0].record_type, "TXT");
+
+    //引号中的逗号属于同一个字段, 不拆成多列
+    let error = bulk::tasks_from_text("www.example.test\n\"a.test,AAAA\"\n", &defaults("google"))
+        .unwrap_err();
+    assert_eq!(error.error_code(), ErrorCode::BulkLineInvalid);
+    assert!(error.msg().starts_with("第 2 行: "), "{}", error.msg());
+
+    let error = bulk::tasks_from_text("# 只有注释\n\n", &defaults("google")).unwrap_err();
+    assert_eq!(error.error_code(), ErrorCode::BulkEmpty);
+}
+
+#[test]
+async fn text_and_json_jobs_with_reports() {
+    init();
+    let server = MockServer::start().await;
+    let store = Data::new(BulkStore::new());
+
+    let request = test::TestRequest::post()
+        .uri(&format!("/v1/bulk?dnsServer={}", server.addr()))
+        .insert_header(("Content-Type", "text/csv"))
+        .set_payload("www.example.test\n\"v6.example.test\",AAAA\n");
+    let (status, body) = call_json(&store, request).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["total"], 2);
+    let id = body["data"]["id"].as_u64().unwrap();
+    wait(&store, id).await;
+
+    let request = test::TestRequest::get().uri(&format!("/v1/bulk/{id}/report?format=csv"));
+    let (status, csv) = call(&store, request).await;
+    assert_eq!(status, 200, "{csv}");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "domain,recordType,dnsServer,code,msg,values,ttls,durationMs"
+    );
+    assert!(
+        lines[1].starts_with(&format!(
+            "www.example.test,A,{},1,操作成功!,192.0.2.1;192.0.2.2,300;300,",
+            server.addr()
+        )),
+        "{csv}"
+    );
+    assert!(lines[2].starts_with("v6.example.test,AAAA,"), "{csv}");
+    assert_eq!(lines.len(), 3);
+
+    let request = test::TestRequest::post().uri("/v1/bulk").set_json(json!({
+        "domains": ["www.example.test", "nothing.example.test"],
+        "recordTypes": ["A"],
+        "resolvers": [server.addr()],
+    }));
+    let (status, body) = call_json(&store, request).await;
+    assert_eq!(status, 200, "{body}");
+    let id = body["data"]["id"].as_u64().unwrap();
+    wait(&store, id).await;
+
+    let request = test::TestRequest::get().uri(&format!("/v1/bulk/{id}/report?format=json"));
+    let (status, items) = call_json(&store, request).await;
+    assert_eq!(status, 200, "{items}");
+    let items = items.as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["domain"], "www.example.test");
+    assert_eq!(items[0]["record"].as_array().unwrap().len(), 2);
+    assert_eq!(items[1]["domain"], "nothing.example.test");
+
+    let request = test::TestRequest::get().uri(&format!("/v1/bulk/{id}/report?format=xml"));
+    let (status, body) = call_json(&store, request).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "FORMAT_UNSUPPORTED");
+}
+
+#[test]
+async fn max_queries() {
+    init();
+    let store = Data::new(BulkStore::new());
+    //2 个域名 x 2 种类型 = 4 次, 等于上限
+    let request = json!({
+        "domains": ["a.example.test", "b.example.test"],
+        "recordTypes": ["A", "AAAA"],
+        "resolvers": ["127.0.0.1:53"],
+    });
+    let tasks = bulk::tasks_from_request(&serde_json::from_value(request).unwrap()).unwrap();
+    assert_eq!(tasks.len(), 4);
+
+    let request = test::TestRequest::post()
+        .uri("/v1/bulk?recordType=A,AAAA&dnsServer=127.0.0.1:53")
+        .set_payload("a.example.test\nb.example.test\nc.example.test\n");
+    let (status, body) = call_json(&store, request).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "BULK_LINE_INVALID");
+    assert!(
+        body["msg"]
+            .as_str()
+            .unwrap()
+            .contains("单个批量任务最多查询 4 次"),
+        "{body}"
+    );
+}
+
+#[test]
+async fn max_running() {
+    init();
+    let server = MockServer::start().await;
+    let store = Data::new(BulkStore::new());
+    //不应答的名称, 任务在超时前一直运行
+    let request = || {
+        test::TestRequest::post().uri("/v1/bulk").set_json(json!({
+            "domains": [format!("{}.example.test", common::TIMEOUT_LABEL)],
+            "resolvers": [server.addr()],
+        }))
+    };
+    for _ in 0..2 {
+        let (status, body) = call_json(&store, request()).await;
+        assert_eq!(status, 200, "{body}");
+    }
+    let (status, body) = call_json(&store, request()).await;
+    assert_eq!(status, 429, "{body}");
+    assert_eq!(body["code"], "RATE_LIMITED");
+
+    //完成后可以再提交
+    wait(&store, 1).await;
+    let (status, body) = call_json(&store, request()).await;
+    assert_eq!(status, 200, "{body}");
+}
@@ -0,0 +1,203 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//别名链: 每一跳的 TTL、跨 zone 继续查询、DNAME、循环与跳数限制、CNAME 在 zone 顶点
+mod common;
+
+use actix_web::test;
+use common::MockServer;
+use hickory_client::proto::op::{Message, MessageType};
+use hickory_client::proto::rr::rdata::{A, CNAME, NULL};
+use hickory_client::proto::rr::{Name, RData, Record, RecordType};
+use hickory_client::proto::serialize::binary::BinEncodable;
+use serde_json::{Value, json};
+use std::env;
+use std::str::FromStr;
+use tokio::net::UdpSocket;
+
+async fn query(dns_server: &str, domain: &str) -> Value {
+    common::init();
+    unsafe {
+        env::set_var("CNAME_CHAIN_LIMIT", "4");
+    }
+    let body = json!({"domain": domain, "recordType": "A", "dnsServer": dns_server});
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 200, "{body}");
+    body["data"].clone()
+}
+
+fn values(data: &Value) -> Vec<&str> {
+    data["record"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["value"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn chain_hops_and_follow_up() {
+    let server = MockServer::start().await;
+    let data = query(&server.addr(), "alias.example.test").await;
+    let chain = &data["chain"];
+    assert_eq!(chain["hops"].as_array().unwrap().len(), 1);
+    assert_eq!(chain["hops"][0]["recordType"], "CNAME");
+    assert_eq!(chain["hops"][0]["target"], "www.example.test.");
+    assert_eq!(chain["hops"][0]["ttl"], 300);
+    assert_eq!(chain["canonical"], "www.example.test.");
+    assert_eq!(chain["minTtl"], 300);
+    assert_eq!(values(&data).len(), 2);
+
+    //权威服务器只给出 CNAME, 目标需要再查询一次
+    let data = query(&server.addr(), "ext.example.test").await;
+    assert_eq!(data["chain"]["canonical"], "www.signed.test.");
+    assert_eq!(values(&data), ["192.0.2.10"]);
+
+    let data = query(&server.addr(), "www.example.test").await;
+    assert!(data["chain"].is_null());
+}
+
+#[actix_web::test]
+async fn chain_loop_and_limit() {
+    let server = MockServer::start().await;
+    let data = query(&server.addr(), "loop1.example.test").await;
+    assert_eq!(data["chain"]["loopDetected"], true);
+    assert_eq!(data["chain"]["hops"].as_array().unwrap().len(), 2);
+    assert!(values(&data).is_empty());
+
+    let data = query(&server.addr(), "c1.example.test").await;
+    assert_eq!(data["chain"]["tooLong"], true);
+    assert_eq!(data["chain"]["hops"].as_array().unwrap().len(), 4);
+    assert!(values(&data).is_empty());
+}
+
+#[actix_web::test]
+async fn chain_cname_at_apex() {
+    let server = MockServer::start().await;
+    let data = query(&server.addr(), "apex.example.test").await;
+    assert_eq!(data["chain"]["cnameAtApex"], json!(["apex.example.test."]));
+    assert_eq!(
+        data["chain"]["coexistence"],
+        json!(["apex.example.test. SOA"])
+    );
+    assert_eq!(values(&data).len(), 2);
+}
+
+//应答 DNAME 与解析器合成的 CNAME, legacy.test. -> example.test.
+async fn serve_dname(socket: UdpSocket) {
+    let name = |name: &str| Name::from_str(name).unwrap();
+    let mut buf = [0u8; 4096];
+    loop {
+        let Ok((len, peer)) = socket.recv_from(&mut buf).await else {
+            return;
+        };
+        let Ok(request) = Message::from_vec(&buf[..len]) else {
+            continue;
+        };
+        let Some(query) = request.queries().first() else {
+            continue;
+        };
+        let dname = RData::Unknown {
+            code: RecordType::Unknown(39),
+            rdata: NULL::with(name("example.test.").to_bytes().unwrap()),
+        };
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .add_query(query.clone())
+            .add_answer(Record::from_rdata(name("legacy.test."), 3600, dname))
+            .add_answer(Record::from_rdata(
+                name("www.legacy.test."),
+                3600,
+                RData::CNAME(CNAME(name("www.example.test."))),
+            ))
+            .add_answer(Record::from_rdata(
+                name("www.example.test."),
+                60,
+                RData::A(A::new(192, 0, 2, 1)),
+            ));
+        let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+    }
+}
+
+#[actix_web::test]
+async fn chain_dname() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+    let task = tokio::spawn(serve_dname(socket));
+    let data = query(&addr, "www.legacy.test").await;
+    task.abort();
+    let hop = &data["chain"]["hops"][0];
+    assert_eq!(data["chain"]["hops"].as_array().unwrap().len(), 1);
+    assert_eq!(hop["recordType"], "DNAME");
+    assert_eq!(hop["name"], "www.legacy.test.");
+    assert_eq!(hop["owner"], "legacy.test.");
+    assert_eq!(hop["target"], "www.example.test.");
+    assert_eq!(hop["ttl"], 3600);
+    assert_eq!(data["chain"]["minTtl"], 60);
+    assert_eq!(values(&data), ["192.0.2.1"]);
+}
+
+//导出时记录写在各自的所有者下, 而不是查询的别名下
+#[actix_web::test]
+async fn chain_export_uses_record_owner() {
+    common::init();
+    let server = MockServer::start().await;
+    let body =
+        json!({"domain": "alias.example.test", "recordType": "A", "dnsServer": server.addr()});
+    let (status, zone) = common::call_text(
+        test::TestRequest::post()
+            .uri("/v1/query?format=zone")
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{zone}");
+    assert!(
+        zone.contains("www.example.test.\t300\tIN\tA\t192.0.2.1\n"),
+        "{zone}"
+    );
+    assert!(!zone.contains("alias.example.test.\t"), "{zone}");
+
+    let (status, dig) = common::call_text(
+        test::TestRequest::post()
+            .uri("/v1/query?format=dig")
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{dig}");
+    assert!(dig.contains(";alias.example.test.\t\tIN\tA\n"), "{dig}");
+    assert!(
+        dig.contains("www.example.test.\t\t300\tIN\tA\t192.0.2.1\n"),
+        "{dig}"
+    );
+    assert!(
+        dig.contains(&format!(
+            "; <<>> dns-tool <<>> @127.0.0.1 -p {} alias.example.test A\n",
+            server.port
+        )),
+        "{dig}"
+    );
+    assert!(
+        dig.contains(&format!(
+            ";; SERVER: 127.0.0.1#{}({})\n",
+            server.port,
+            server.addr()
+        )),
+        "{dig}"
+    );
+}
@@ -0,0 +1,460 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//集成测试公共部分: 进程内的权威 dns 服务器与 actix 测试请求
+//权威服务器从 tests/fixtures/*.zone 加载数据, 监听 127.0.0.1 的随机端口, 同时提供 UDP 与 TCP
+#![allow(dead_code)]
+
+use actix_web::test;
+use actix_web::web::Data;
+use dns_tool::bulk::BulkStore;
+use dns_tool::history::HistoryStore;
+use dns_tool::monitor::MonitorStore;
+use dns_tool::resolver::Resolver;
+use dns_tool::server;
+use hickory_client::proto::dnssec::crypto::EcdsaSigningKey;
+use hickory_client::proto::dnssec::rdata::{DNSKEY, DNSSECRData, NSEC, RRSIG};
+use hickory_client::proto::dnssec::{Algorithm, SigningKey, TBS};
+use hickory_client::proto::op::{Edns, Message, MessageType, ResponseCode};
+use hickory_client::proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use hickory_client::proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_client::proto::serialize::txt::Parser;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::task::JoinHandle;
+
+//以此标签开头的名称不应答, 用于测试超时
+pub const TIMEOUT_LABEL: &str = "timeout";
+
+//签名 zone 使用的密钥, 同一个测试进程内共享, 信任锚文件也由它生成
+static SIGNING_KEY: LazyLock<EcdsaSigningKey> = LazyLock::new(|| {
+    let pkcs8 = EcdsaSigningKey::generate_pkcs8(Algorithm::ECDSAP256SHA256).unwrap();
+    EcdsaSigningKey::from_pkcs8(&pkcs8, Algorithm::ECDSAP256SHA256).unwrap()
+});
+
+static INIT: Once = Once::new();
+
+//配置在第一次读取前写入环境变量: 查询超时 1 秒, 信任锚为签名 zone 的 DNSKEY
+pub fn init() {
+    INIT.call_once(|| {
+        let dir = env::temp_dir().join(format!("dns-tool-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let anchors = dir.join("trust-anchors.txt");
+        fs::write(&anchors, format!("signed.test. IN DNSKEY {}\n", dnskey())).unwrap();
+        unsafe {
+            env::set_var("QUERY_TIMEOUT", "1");
+            env::set_var("TRUST_ANCHOR_FILE", &anchors);
+        }
+    });
+}
+
+fn dnskey() -> DNSKEY {
+    //zone key + SEP
+    DNSKEY::with_flags(257, SIGNING_KEY.to_public_key().unwrap())
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32
+}
+
+type RrKey = (Name, RecordType);
+
+struct Zone {
+    origin: Name,
+    rrsets: BTreeMap<RrKey, Vec<Record>>,
+    rrsigs: BTreeMap<RrKey, Record>,
+}
+
+impl Zone {
+    fn load(file: &str, signed: bool) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(file);
+        let text = fs::read_to_string(&path).unwrap();
+        let (origin, records) = Parser::new(text, Some(path), None).parse().unwrap();
+        let mut zone = Zone {
+            origin: origin.to_lowercase(),
+            rrsets: BTreeMap::new(),
+            rrsigs: BTreeMap::new(),
+        };
+        for rrset in records.values() {
+            for record in rrset.records_without_rrsigs() {
+                zone.insert(record.clone());
+            }
+        }
+        if signed {
+            zone.sign();
+        }
+        zone
+    }
+
+    fn insert(&mut self, mut record: Record) {
+        let name = record.name().to_lowercase();
+        record.set_name(name.clone());
+        self.rrsets
+            .entry((name, record.record_type()))
+            .or_default()
+            .push(record);
+    }
+
+    fn minimum_ttl(&self) -> u32 {
+        self.rrsets
+            .get(&(self.origin.clone(), RecordType::SOA))
+            .and_then(|records| records[0].data().as_soa().map(|soa| soa.minimum()))
+            .unwrap_or(300)
+    }
+
+    //加入 DNSKEY 与 NSEC 链, 再为每个记录集签名
+    fn sign(&mut self) {
+        let ttl = self.minimum_ttl();
+        let rdata = RData::DNSSEC(DNSSECRData::DNSKEY(dnskey()));
+        self.insert(Record::from_rdata(self.origin.clone(), ttl, rdata));
+
+        let names: Vec<Name> = self
+            .rrsets
+            .keys()
+            .map(|(name, _)| name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            let next = names[(i + 1) % names.len()].clone();
+            let mut types: Vec<RecordType> = self
+                .rrsets
+                .keys()
+                .filter(|(owner, _)| owner == name)
+                .map(|(_, record_type)| *record_type)
+                .collect();
+            types.extend([RecordType::NSEC, RecordType::RRSIG]);
+            let nsec = RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(next, types)));
+            self.insert(Record::from_rdata(name.clone(), ttl, nsec));
+        }
+
+        let key_tag = dnskey().calculate_key_tag().unwrap();
+        let rrsigs = self
+            .rrsets
+            .iter()
+            .map(|(key, records)| (key.clone(), self.rrsig(key_tag, key, records)))
+            .collect();
+        self.rrsigs = rrsigs;
+    }
+
+    fn rrsig(&self, key_tag: u16, (name, record_type): &RrKey, records: &[Record]) -> Record {
+        let ttl = records[0].ttl();
+        let rrsig = |sig: Vec<u8>| {
+            RRSIG::new(
+                *record_type,
+                Algorithm::ECDSAP256SHA256,
+                name.num_labels(),
+                ttl,
+                now() + 30 * 86400,
+                now() - 3600,
+                key_tag,
+                self.origin.clone(),
+                sig,
+            )
+        };
+        let tbs = TBS::from_sig(name, DNSClass::IN, &rrsig(Vec::new()), records.iter()).unwrap();
+        let sig = SIGNING_KEY.sign(&tbs).unwrap();
+        let rdata = RData::DNSSEC(DNSSECRData::RRSIG(rrsig(sig)));
+        Record::from_rdata(name.clone(), ttl, rdata)
+    }
+
+    //记录集, DO 时附带签名
+    fn rrset(&self, name: &Name, record_type: RecordType, dnssec_ok: bool) -> Vec<Record> {
+        let key = (name.clone(), record_type);
+        let mut records = self.rrsets.get(&key).cloned().unwrap_or_default();
+        if dnssec_ok && let Some(rrsig) = self.rrsigs.get(&key) {
+            records.push(rrsig.clone());
+        }
+        records
+    }
+
+    //覆盖 name 的 NSEC 记录所在的名称
+    fn covering_nsec(&self, name: &Name) -> Option<Name> {
+        self.rrsets
+            .iter()
+            .filter(|((_, record_type), _)| *record_type == RecordType::NSEC)
+            .find(|((owner, _), records)| {
+                let next = records[0]
+                    .data()
+                    .as_dnssec()
+                    .and_then(DNSSECRData::as_nsec)
+                    .map(|nsec| nsec.next_domain_name().to_lowercase());
+                next.is_some_and(|next| {
+                    if owner < &next {
+                        owner < name && name < &next
+                    } else {
+                        owner < name || name < &next
+                    }
+                })
+            })
+            .map(|((owner, _), _)| owner.clone())
+    }
+
+    fn exists(&self, name: &Name) -> bool {
+        //包括空非终端
+        self.rrsets.keys().any(|(owner, _)| name.zone_of(owner))
+    }
+
+    fn answer(&self, name: &Name, query_type: RecordType, dnssec_ok: bool, response: &mut Message) {
+        let mut target = name.clone();
+        let mut answers = Vec::new();
+        //区内 CNAME
+        loop {
+            let records = self.rrset(&target, query_type, dnssec_ok);
+            if !records.is_empty() {
+                answers.extend(records);
+                response.add_answers(answers);
+                return;
+            }
+            let cname = self.rrset(&target, RecordType::CNAME, dnssec_ok);
+            let next = cname
+                .first()
+                .and_then(|record| record.data().as_cname())
+                .map(|cname| cname.0.to_lowercase());
+            answers.extend(cname);
+            match next {
+                Some(next) if self.origin.zone_of(&next) && answers.len() < 16 => target = next,
+                _ => break,
+            }
+        }
+        if !answers.is_empty() {
+            response.add_answers(answers);
+            return;
+        }
+
+        let mut authority = self.rrset(&self.origin, RecordType::SOA, dnssec_ok);
+        let signed = dnssec_ok && !self.rrsigs.is_empty();
+        if self.exists(&target) {
+            if signed {
+                match self.covering_nsec(&target) {
+                    //空非终端由覆盖它的 NSEC 证明
+                    Some(owner)
+                        if !self
+                            .rrsets
+                            .contains_key(&(target.clone(), RecordType::NSEC)) =>
+                    {
+                        authority.extend(self.rrset(&owner, RecordType::NSEC, true))
+                    }
+                    _ => authority.extend(self.rrset(&target, RecordType::NSEC, true)),
+                }
+            }
+        } else {
+            response.set_response_code(ResponseCode::NXDomain);
+            if signed {
+                let mut encloser = target.base_name();
+                while !self.exists(&encloser) {
+                    encloser = encloser.base_name();
+                }
+                let wildcard = encloser.prepend_label("*").unwrap();
+                let owners: BTreeSet<Name> = [&target, &wildcard]
+                    .into_iter()
+                    .filter_map(|name| self.covering_nsec(name))
+                    .collect();
+                for owner in owners {
+                    authority.extend(self.rrset(&owner, RecordType::NSEC, true));
+                }
+            }
+        }
+        response.add_name_servers(authority);
+    }
+}
+
+//返回 None 表示不应答
+fn respond(zones: &[Zone], request: &Message) -> Option<Message> {
+    let query = request.queries().first()?;
+    let name = query.name().to_lowercase();
+    if name
+        .iter()
+        .next()
+        .is_some_and(|label| label == TIMEOUT_LABEL.as_bytes())
+    {
+        return None;
+    }
+    let dnssec_ok = request
+        .extensions()
+        .as_ref()
+        .is_some_and(|edns| edns.flags().dnssec_ok);
+
+    let mut response = Message::new();
+    response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(request.op_code())
+        .set_recursion_desired(request.recursion_desired())
+        .set_authoritative(true)
+        .add_query(query.clone());
+    if let Some(request_edns) = request.extensions() {
+        let mut edns = Edns::new();
+        edns.set_max_payload(1232).set_dnssec_ok(dnssec_ok);
+        //ECS: 原样返回子网, scope prefix 与 source prefix 相同
+        if let Some(EdnsOption::Subnet(subnet)) = request_edns.option(EdnsCode::Subnet) {
+            let scope = ClientSubnet::new(
+                subnet.addr(),
+                subnet.source_prefix(),
+                subnet.source_prefix(),
+            );
+            edns.options_mut().insert(EdnsOption::Subnet(scope));
+        }
+        response.set_edns(edns);
+    }
+    match zones
+        .iter()
+        .filter(|zone| zone.origin.zone_of(&name))
+        .max_by_key(|zone| zone.origin.num_labels())
+    {
+        Some(zone) => zone.answer(&name, query.query_type(), dnssec_ok, &mut response),
+        None => {
+            response.set_response_code(ResponseCode::Refused);
+        }
+    }
+    Some(response)
+}
+
+pub struct MockServer {
+    pub port: u16,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockServer {
+    //加载 example.test (未签名) 与 signed.test (签名)
+    pub async fn start() -> Self {
+        let zones = Arc::new(vec![
+            Zone::load("example.test.zone", false),
+            Zone::load("signed.test.zone", true),
+        ]);
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = udp.local_addr().unwrap().port();
+        let tcp = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        MockServer {
+            port,
+            tasks: vec![
+                tokio::spawn(serve_udp(udp, zones.clone())),
+                tokio::spawn(serve_tcp(tcp, zones)),
+            ],
+        }
+    }
+
+    //接口参数 dnsServer
+    pub fn addr(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_udp(socket: UdpSocket, zones: Arc<Vec<Zone>>) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let Ok((len, peer)) = socket.recv_from(&mut buf).await else {
+            return;
+        };
+        let Ok(request) = Message::from_vec(&buf[..len]) else {
+            continue;
+        };
+        let Some(mut response) = respond(&zones, &request) else {
+            continue;
+        };
+        //超过请求方的 UDP 大小时截断, 客户端需要改用 TCP
+        let limit = request
+            .extensions()
+            .as_ref()
+            .map_or(512, |edns| edns.max_payload().max(512)) as usize;
+        if response.to_vec().unwrap().len() > limit {
+            response.take_answers();
+            response.take_name_servers();
+            response.take_additionals();
+            response.set_truncated(true);
+        }
+        let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, zones: Arc<Vec<Zone>>) {
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            return;
+        };
+        let zones = zones.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut len = [0u8; 2];
+                if stream.read_exact(&mut len).await.is_err() {
+                    return;
+                }
+                let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+                if stream.read_exact(&mut buf).await.is_err() {
+                    return;
+                }
+                let Ok(request) = Message::from_vec(&buf) else {
+                    return;
+                };
+                let Some(response) = respond(&zones, &request) else {
+                    continue;
+                };
+                let bytes = response.to_vec().unwrap();
+                let _ = stream.write_all(&(bytes.len() as u16).to_be_bytes()).await;
+                let _ = stream.write_all(&bytes).await;
+            }
+        });
+    }
+}
+
+pub async fn call(request: test::TestRequest) -> (u16, Value) {
+    let (status, body) = call_text(request).await;
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+//导出等非 json 的响应
+pub async fn call_text(request: test::TestRequest) -> (u16, String) {
+    let history = Data::new(HistoryStore::open(":memory:").unwrap());
+    let app = test::init_service(server::app(
+        history.clone(),
+        Data::new(MonitorStore::new()),
+        Data::new(BulkStore::new()),
+        Data::new(Resolver::new(history)),
+    ))
+    .await;
+    let response = test::call_service(&app, request.to_request()).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+pub async fn post(uri: &str, body: Value) -> (u16, Value) {
+    call(test::TestRequest::post().uri(uri).set_json(body)).await
+}
+
+pub async fn get(uri: &str) -> (u16, Value) {
+    call(test::TestRequest::get().uri(uri)).await
+}
@@ -0,0 +1,191 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//dnssec 查询: 签名 zone 以测试密钥作为信任锚, mail 与 www 配置了未过期与已过期的否定信任锚
+mod common;
+
+use actix_web::test;
+use common::MockServer;
+use serde_json::{Value, json};
+use std::env;
+use std::sync::Once;
+
+static INIT: Once = Once::new();
+
+//否定信任锚在第一次读取前写入
+fn init() {
+    common::init();
+    INIT.call_once(|| unsafe {
+        env::set_var(
+            "NEGATIVE_TRUST_ANCHORS",
+            "mail.signed.test:2999-12-31,www.signed.test.:2000-01-01,bad.signed.test:2026-13-01",
+        );
+    });
+}
+
+async fn query_dnssec(server: &MockServer, domain: &str, record_type: &str) -> (u16, Value) {
+    init();
+    let body = json!({"domain": domain, "recordType": record_type, "dnsServer": server.addr()});
+    common::post("/v1/query_dnssec", body).await
+}
+
+#[actix_web::test]
+async fn dnssec_secure_answer() {
+    let server = MockServer::start().await;
+    let (status, body) = query_dnssec(&server, "www.signed.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    let record = &body["data"]["record"][0];
+    assert_eq!(record["value"], "192.0.2.10");
+    assert_eq!(record["proof"], "Secure");
+    assert_eq!(body["data"]["trustAnchor"]["zone"], "signed.test.");
+    assert_eq!(body["data"]["trustAnchor"]["anchorType"], "DNSKEY");
+}
+
+#[actix_web::test]
+async fn dnssec_nxdomain_proven() {
+    let server = MockServer::start().await;
+    let (status, body) = query_dnssec(&server, "missing.signed.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    let denial = &body["data"]["denial"];
+    assert_eq!(denial["kind"], "NXDOMAIN");
+    assert_eq!(denial["denialType"], "NSEC");
+    assert_eq!(denial["proven"], true, "{denial}");
+}
+
+#[actix_web::test]
+async fn denial_explanation_localized() {
+    let server = MockServer::start().await;
+    init();
+    let body =
+        json!({"domain": "missing.signed.test", "recordType": "A", "dnsServer": server.addr()});
+    let request = test::TestRequest::post()
+        .uri("/v1/query_dnssec")
+        .insert_header(("Accept-Language", "en"))
+        .set_json(body);
+    let (status, body) = common::call(request).await;
+    assert_eq!(status, 200, "{body}");
+    let explanation = &body["data"]["denial"]["explanation"];
+    assert_eq!(
+        explanation[0],
+        "The NXDOMAIN denial of existence is complete"
+    );
+    assert_eq!(
+        explanation[1],
+        "NSEC mail.signed.test. -> ns1.signed.test. covers missing.signed.test., proving the name does not exist"
+    );
+    assert_eq!(explanation[2], "The closest encloser is signed.test.");
+}
+
+#[actix_web::test]
+async fn dnssec_nodata_proven() {
+    let server = MockServer::start().await;
+    let (status, body) = query_dnssec(&server, "www.signed.test", "AAAA").await;
+    assert_eq!(status, 200, "{body}");
+    let denial = &body["data"]["denial"];
+    assert_eq!(denial["kind"], "NODATA");
+    assert_eq!(denial["proven"], true, "{denial}");
+}
+
+#[actix_web::test]
+async fn diagnose_explanation_localized() {
+    let server = MockServer::start().await;
+    init();
+    let body = json!({"domain": "www.signed.test", "recordType": "A", "dnsServer": server.addr()});
+    let request = test::TestRequest::post()
+        .uri("/v1/diagnose_dnssec")
+        .insert_header(("Accept-Language", "en"))
+        .set_json(body);
+    let (status, body) = common::call(request).await;
+    assert_eq!(status, 200, "{body}");
+    let explanation = &body["data"]["explanation"];
+    assert_eq!(
+        explanation[0],
+        "The resolver answers with validation enabled; there is no DNSSEC problem"
+    );
+    assert_eq!(explanation[1], "CD=0: NOERROR, answer records: 1");
+    assert_eq!(explanation[3], "Local DNSSEC validation: Secure 2");
+}
+
+#[actix_web::test]
+async fn health_warnings_localized() {
+    let server = MockServer::start().await;
+    init();
+    let body = json!({"zone": "signed.test", "dnsServer": server.addr(), "warnDays": 60});
+    let request = test::TestRequest::post()
+        .uri("/v1/dnssec_health")
+        .insert_header(("Accept-Language", "en"))
+        .set_json(body);
+    let (status, body) = common::call(request).await;
+    assert_eq!(status, 200, "{body}");
+    let warnings = &body["data"]["warnings"];
+    assert_eq!(
+        warnings[0],
+        "The zone is signed but the parent zone has no DS; the chain of trust is not established"
+    );
+    let expiring = warnings[1].as_str().unwrap_or_default();
+    assert!(
+        expiring.starts_with("The DNSKEY RRSIG (keyTag ") && expiring.contains("expires within"),
+        "{warnings}"
+    );
+}
+
+#[actix_web::test]
+async fn trust_anchor_listed() {
+    init();
+    let (status, body) = common::get("/v1/trust_anchors").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["anchors"][0]["zone"], "signed.test.");
+    assert_eq!(
+        body["data"]["errors"],
+        json!(["NEGATIVE_TRUST_ANCHORS bad.signed.test:2026-13-01: 到期日期应为 YYYY-MM-DD"])
+    );
+}
+
+//到期日期当天结束后失效, 日期不正确的条目记入 errors
+#[actix_web::test]
+async fn negative_trust_anchor_expiry() {
+    init();
+    let (status, body) = common::get("/v1/trust_anchors").await;
+    assert_eq!(status, 200, "{body}");
+    let negative = body["data"]["negativeAnchors"].as_array().unwrap();
+    assert_eq!(negative.len(), 2, "{body}");
+    assert_eq!(negative[0]["zone"], "mail.signed.test.");
+    assert_eq!(negative[0]["anchorType"], "NEGATIVE");
+    assert_eq!(negative[0]["expires"], "2999-12-31");
+    assert!(negative[0]["expired"].is_null(), "{body}");
+    assert_eq!(negative[1]["zone"], "www.signed.test.");
+    assert_eq!(negative[1]["expires"], "2000-01-01");
+    assert_eq!(negative[1]["expired"], true);
+}
+
+//否定信任锚覆盖的名称不验证, 记录按 Insecure 返回
+#[actix_web::test]
+async fn negative_trust_anchor_skips_validation() {
+    let server = MockServer::start().await;
+    let (status, body) = query_dnssec(&server, "mail.signed.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["record"][0]["value"], "192.0.2.25");
+    assert_eq!(body["data"]["record"][0]["proof"], "Insecure");
+    assert_eq!(body["data"]["trustAnchor"]["zone"], "mail.signed.test.");
+    assert_eq!(body["data"]["trustAnchor"]["anchorType"], "NEGATIVE");
+
+    //已过期的否定信任锚不再生效, 照常验证
+    let (status, body) = query_dnssec(&server, "www.signed.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["record"][0]["proof"], "Secure");
+    assert_eq!(body["data"]["trustAnchor"]["anchorType"], "DNSKEY");
+}
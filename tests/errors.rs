@@ -0,0 +1,68 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//错误码: code 唯一, 每个错误码都有中英文文案; 页面文案走语言包
+mod common;
+
+use dns_tool::constants::ErrorCode;
+use dns_tool::i18n::{self, Lang};
+use std::collections::HashSet;
+
+#[test]
+fn error_codes_are_unique_and_translated() {
+    let mut codes = HashSet::new();
+    for &code in ErrorCode::ALL {
+        assert!(codes.insert(code.as_str()), "duplicate {}", code.as_str());
+        for lang in [Lang::Zh, Lang::En] {
+            assert!(
+                i18n::catalog(lang).contains_key(code.as_str()),
+                "{} missing in {}",
+                code.as_str(),
+                lang.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn catalogs_have_the_same_keys() {
+    let zh: Vec<_> = i18n::catalog(Lang::Zh).keys().collect();
+    let en: Vec<_> = i18n::catalog(Lang::En).keys().collect();
+    assert_eq!(zh, en);
+}
+
+#[actix_web::test]
+async fn pages_render_in_both_languages() {
+    common::init();
+    for uri in ["/", "/bulk", "/monitors", "/update", "/zone_sync"] {
+        let (status, zh) = common::call_text(actix_web::test::TestRequest::get().uri(uri)).await;
+        assert_eq!(status, 200, "{uri}");
+        assert!(zh.contains("<html lang=\"zh-CN\""), "{uri}");
+        assert!(zh.contains("在线dns工具"), "{uri}");
+
+        let (status, en) = common::call_text(
+            actix_web::test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("Accept-Language", "en")),
+        )
+        .await;
+        assert_eq!(status, 200, "{uri}");
+        assert!(en.contains("<html lang=\"en\""), "{uri}");
+        assert!(en.contains("Online DNS Tool"), "{uri}");
+        assert!(!en.contains("在线dns工具"), "{uri}");
+    }
+}
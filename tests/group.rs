@@ -0,0 +1,146 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//解析器组: 故障转移、竞速与 quorum, 以及每个成员的处理情况; 监控解析器组时不做 dnssec 查询
+mod common;
+
+use actix_web::test;
+use common::MockServer;
+use dns_tool::constants::MonitorRequest;
+use dns_tool::monitor::{self, MonitorStore};
+use serde_json::{Value, json};
+use std::env;
+use tokio::net::UdpSocket;
+
+async fn query(group: &str) -> (u16, Value) {
+    let body = json!({"domain": "www.example.test", "recordType": "A", "dnsServer": group});
+    common::post("/v1/query", body).await
+}
+
+fn statuses(body: &Value) -> Vec<&str> {
+    body["data"]["group"]["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|member| member["status"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn resolver_groups() {
+    common::init();
+    let server = MockServer::start().await;
+    let other = MockServer::start().await;
+    //收到查询但不应答
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let silent = silent.local_addr().unwrap().to_string();
+    let (mock, other) = (server.addr(), other.addr());
+    unsafe {
+        env::set_var(
+            "RESOLVER_GROUPS",
+            format!(
+                "fo=failover:{silent},{mock};fast=race:{silent},{mock};\
+                 agree=quorum/2:{mock},{other},{silent};lonely=quorum/2:{silent},{mock};\
+                 solo=failover:{mock};dead=race:{silent}"
+            ),
+        );
+    }
+
+    let (status, body) = common::get("/v1/resolver_groups").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["groups"].as_array().unwrap().len(), 6);
+    assert_eq!(body["data"]["groups"][2]["quorum"], 2);
+
+    //第一个成员超时后由第二个应答
+    let (status, body) = query("fo").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["group"]["answeredBy"], mock);
+    assert_eq!(statuses(&body), ["FAILED", "ANSWERED"]);
+
+    let (status, body) = query("fast").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["group"]["answeredBy"], mock);
+    assert_eq!(statuses(&body), ["SLOWER", "ANSWERED"]);
+
+    //两个 mock 应答一致即可, 不等待超时的成员
+    let (status, body) = query("agree").await;
+    assert_eq!(status, 200, "{body}");
+    let mut members = statuses(&body);
+    members.sort();
+    assert_eq!(members, ["AGREED", "ANSWERED", "SKIPPED"]);
+    assert_eq!(body["data"]["record"].as_array().unwrap().len(), 2);
+
+    //成员的 reason 按请求语言输出
+    let request = test::TestRequest::post()
+        .uri("/v1/query")
+        .insert_header(("Accept-Language", "en"))
+        .set_json(json!({"domain": "www.example.test", "recordType": "A", "dnsServer": "agree"}));
+    let (status, body) = common::call(request).await;
+    assert_eq!(status, 200, "{body}");
+    let skipped = body["data"]["group"]["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|member| member["status"] == "SKIPPED")
+        .unwrap();
+    assert_eq!(skipped["reason"], "2 members have already agreed");
+
+    //未凑齐 quorum 或所有成员失败时, 错误中附上每个成员的情况
+    let (status, body) = query("lonely").await;
+    assert_eq!(status, 502, "{body}");
+    assert_eq!(body["code"], "DNS_QUORUM_FAILED");
+    let msg = body["msg"].as_str().unwrap();
+    assert!(
+        msg.contains(&format!("{mock}: 应答: A 192.0.2.1, A 192.0.2.2")),
+        "{msg}"
+    );
+    assert!(msg.contains(&format!("{silent}: ")), "{msg}");
+
+    let request = test::TestRequest::post()
+        .uri("/v1/query")
+        .insert_header(("Accept-Language", "en"))
+        .set_json(json!({"domain": "www.example.test", "recordType": "A", "dnsServer": "dead"}));
+    let (status, body) = common::call(request).await;
+    assert_eq!(status, 502, "{body}");
+    assert_eq!(body["code"], "DNS_GROUP_FAILED");
+    let msg = body["msg"].as_str().unwrap();
+    assert!(
+        msg.starts_with(&format!(
+            "Resolver group dead: all members failed: {silent}: "
+        )),
+        "{msg}"
+    );
+
+    //解析器组照常查询记录, dnssec 状态为 Unsupported 而不是 Error
+    let job = MonitorStore::new()
+        .create(MonitorRequest {
+            domain: "www.example.test".to_string(),
+            record_type: "A".to_string(),
+            resolvers: vec!["solo".to_string(), mock.clone()],
+            interval: 300,
+            alerts: vec![],
+        })
+        .unwrap();
+    let answer = monitor::check_resolver(job.clone(), "solo".to_string()).await;
+    assert_eq!(answer.error, None);
+    assert_eq!(answer.dns_record.len(), 2);
+    assert_eq!(answer.dnssec, "Unsupported");
+    //单个解析器仍做 dnssec 查询
+    let answer = monitor::check_resolver(job, mock).await;
+    assert_eq!(answer.error, None);
+    assert!(!["Unsupported", "Error"].contains(&answer.dnssec.as_str()));
+}
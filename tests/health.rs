@@ -0,0 +1,90 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//就绪检查与自检: 自检 dns 服务器为 mock 与一个不应答的地址
+mod common;
+
+use common::MockServer;
+use dns_tool::health;
+use std::env;
+use tera::Tera;
+use tokio::net::UdpSocket;
+
+#[actix_web::test]
+async fn readyz_and_selftest() {
+    common::init();
+    let server = MockServer::start().await;
+    //收到查询但不应答
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let silent = silent.local_addr().unwrap().to_string();
+    let mock = server.addr();
+    unsafe {
+        env::set_var("SELFTEST_SERVERS", format!("{mock},{silent}"));
+        env::set_var("SELFTEST_DOMAIN", "www.example.test");
+        env::set_var("SELFTEST_EXPECTED", "192.0.2.1");
+        env::set_var("READY_CANARY_TIMEOUT_MS", "300");
+    }
+
+    //mock 应答 canary 查询即可
+    let (status, body) = common::get("/readyz").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["ready"], true);
+    let resolver = &body["data"]["checks"][2];
+    assert_eq!(resolver["name"], "resolver");
+    assert!(
+        resolver["detail"].as_str().unwrap().starts_with(&mock),
+        "{body}"
+    );
+
+    let (status, body) = common::get("/v1/selftest").await;
+    assert_eq!(status, 200, "{body}");
+    let data = &body["data"];
+    assert_eq!(data["total"], 2);
+    assert_eq!(data["reachable"], 1);
+    assert_eq!(data["correct"], 1);
+    assert_eq!(data["results"][0]["dnsServer"], mock.as_str());
+    assert_eq!(data["results"][0]["correct"], true);
+    assert_eq!(data["results"][1]["dnsServer"], silent.as_str());
+    assert_eq!(data["results"][1]["reachable"], false);
+    assert_eq!(data["results"][1]["code"], "DNS_TIMEOUT");
+
+    //缺少模板
+    let report = health::ready(&Tera::default()).await;
+    assert!(!report.ready);
+    assert_eq!(health::failed_checks(&report), "templates");
+    assert!(
+        report.checks[0]
+            .detail
+            .as_deref()
+            .unwrap()
+            .contains("缺少模板 index.html"),
+        "{report:?}"
+    );
+
+    //mock 停止后 canary 查询超时
+    drop(server);
+    let (status, body) = common::get("/readyz").await;
+    assert_eq!(status, 503, "{body}");
+    assert_eq!(body["code"], "NOT_READY");
+    assert_eq!(body["msg"], "服务未就绪: resolver");
+    assert_eq!(body["data"]["ready"], false);
+    assert_eq!(body["data"]["checks"][2]["ok"], false);
+    assert_eq!(
+        body["data"]["checks"][2]["detail"],
+        "没有 dns 服务器在 300ms 内应答"
+    );
+}
@@ -0,0 +1,89 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//查询历史: 按域名模糊搜索时 % _ \ 按普通字符匹配, 过期分享链接失效但历史保留
+use dns_tool::constants;
+use dns_tool::constants::{AppError, DnsResult, HistoryQuery};
+use dns_tool::history::{HistoryStore, NewHistory};
+use dns_tool::monitor::now_millis;
+use std::time::Duration;
+
+async fn save(history: &HistoryStore, domain: &str) -> String {
+    let entry = NewHistory {
+        requester: "127.0.0.1".to_string(),
+        kind: "query",
+        domain: domain.to_string(),
+        record_type: "A".to_string(),
+        dns_server: "127.0.0.1".to_string(),
+        client_subnet: None,
+    };
+    let result: Result<DnsResult, AppError> = Err(AppError::internal("test"));
+    history
+        .save(entry, Duration::from_millis(1), &result)
+        .await
+        .unwrap()
+}
+
+async fn search(history: &HistoryStore, domain: &str) -> Vec<String> {
+    let query = HistoryQuery {
+        domain: Some(domain.to_string()),
+        ..Default::default()
+    };
+    let (total, records) = history.search(&query).await.unwrap();
+    assert_eq!(total as usize, records.len());
+    let mut domains: Vec<String> = records.into_iter().map(|record| record.domain).collect();
+    domains.sort();
+    domains
+}
+
+#[actix_web::test]
+async fn domain_search_escapes_like_wildcards() {
+    let history = HistoryStore::open(":memory:").unwrap();
+    for domain in [
+        "a_b.test",
+        "axb.test",
+        "100%.test",
+        "1000.test",
+        "back\\slash.test",
+    ] {
+        save(&history, domain).await;
+    }
+
+    assert_eq!(search(&history, "_").await, vec!["a_b.test"]);
+    assert_eq!(search(&history, "%").await, vec!["100%.test"]);
+    assert_eq!(search(&history, "\\").await, vec!["back\\slash.test"]);
+    assert_eq!(search(&history, "a_b").await, vec!["a_b.test"]);
+    assert_eq!(search(&history, ".test").await.len(), 5);
+}
+
+#[actix_web::test]
+async fn expired_share_links_keep_history() {
+    let history = HistoryStore::open(":memory:").unwrap();
+    let old = save(&history, "old.test").await;
+    save(&history, "new.test").await;
+
+    //创建时按 PERMALINK_TTL_DAYS 写入 expires_at, 未到期不处理
+    assert_eq!(history.expire_share_links(now_millis()).await.unwrap(), 0);
+    assert!(history.find_by_share_id(&old).await.unwrap().is_some());
+
+    //过期后链接失效, 查询历史保留
+    let after_ttl = now_millis() + *constants::PERMALINK_TTL_DAYS * 24 * 60 * 60 * 1000;
+    assert_eq!(history.expire_share_links(after_ttl).await.unwrap(), 2);
+    assert!(history.find_by_share_id(&old).await.unwrap().is_none());
+    assert_eq!(search(&history, ".test").await.len(), 2);
+    assert_eq!(history.expire_share_links(after_ttl).await.unwrap(), 0);
+}
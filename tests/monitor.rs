@@ -0,0 +1,203 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//监控结果对比: RRset 变更、TTL 变更 (含递归解析器的缓存倒计时)、查询失败的状态切换
+use dns_tool::constants::{
+    DnsRecordResult, MONITOR_CHANGE_DNSSEC, MONITOR_CHANGE_ERROR, MONITOR_CHANGE_RRSET,
+    MONITOR_CHANGE_TTL, MonitorAnswer, MonitorJob, MonitorRequest,
+};
+use dns_tool::monitor::MonitorStore;
+
+const RESOLVER: &str = "127.0.0.1";
+
+fn job(store: &MonitorStore) -> MonitorJob {
+    store
+        .create(MonitorRequest {
+            domain: "example.test".to_string(),
+            record_type: "A".to_string(),
+            resolvers: vec![RESOLVER.to_string()],
+            interval: 300,
+            alerts: vec![],
+        })
+        .unwrap()
+}
+
+fn answer(records: &[(&str, u32)], authoritative: bool) -> MonitorAnswer {
+    MonitorAnswer {
+        dns_server: RESOLVER.to_string(),
+        checked_at: 0,
+        dns_record: records
+            .iter()
+            .map(|(value, ttl)| DnsRecordResult {
+                name: "example.test.".to_string(),
+                value: value.to_string(),
+                ttl: *ttl,
+            })
+            .collect(),
+        authoritative,
+        dnssec: "Insecure".to_string(),
+        error: None,
+    }
+}
+
+fn failed(error: &str) -> MonitorAnswer {
+    MonitorAnswer {
+        error: Some(error.to_string()),
+        dnssec: "Error".to_string(),
+        ..answer(&[], false)
+    }
+}
+
+//返回 (kind, before, after)
+fn record(
+    store: &MonitorStore,
+    job: &MonitorJob,
+    answer: MonitorAnswer,
+) -> Vec<(String, String, String)> {
+    store
+        .record(job, answer)
+        .into_iter()
+        .map(|change| (change.kind, change.before, change.after))
+        .collect()
+}
+
+fn change(kind: &str, before: &str, after: &str) -> (String, String, String) {
+    (kind.to_string(), before.to_string(), after.to_string())
+}
+
+#[actix_web::test]
+async fn first_answer_is_baseline_and_unchanged_answer_is_quiet() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    let first = answer(&[("192.0.2.1", 300), ("192.0.2.2", 300)], false);
+    assert!(record(&store, &job, first).is_empty());
+    //顺序不同、重复值都不算变更
+    let same = answer(
+        &[("192.0.2.2", 300), ("192.0.2.1", 300), ("192.0.2.1", 300)],
+        false,
+    );
+    assert!(record(&store, &job, same).is_empty());
+    assert!(store.changes(10).is_empty());
+}
+
+#[actix_web::test]
+async fn rrset_change_is_reported() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, answer(&[("192.0.2.1", 300)], false));
+    assert_eq!(
+        record(
+            &store,
+            &job,
+            answer(&[("192.0.2.9", 300), ("192.0.2.1", 300)], false)
+        ),
+        vec![change(
+            MONITOR_CHANGE_RRSET,
+            "192.0.2.1",
+            "192.0.2.1 192.0.2.9"
+        )]
+    );
+    assert_eq!(store.changes(10).len(), 1);
+}
+
+#[actix_web::test]
+async fn ttl_countdown_of_recursive_answer_is_not_a_change() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, answer(&[("192.0.2.1", 300)], false));
+    //缓存倒计时, 以及缓存过期后重新取回原值
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 120)], false)).is_empty());
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 40)], false)).is_empty());
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 300)], false)).is_empty());
+    //超过观测到的最大值才是 TTL 被调大
+    assert_eq!(
+        record(&store, &job, answer(&[("192.0.2.1", 3600)], false)),
+        vec![change(MONITOR_CHANGE_TTL, "300", "3600")]
+    );
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 3000)], false)).is_empty());
+}
+
+#[actix_web::test]
+async fn authoritative_ttl_is_compared_exactly() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, answer(&[("192.0.2.1", 300)], true));
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 300)], true)).is_empty());
+    assert_eq!(
+        record(&store, &job, answer(&[("192.0.2.1", 60)], true)),
+        vec![change(MONITOR_CHANGE_TTL, "300", "60")]
+    );
+}
+
+#[actix_web::test]
+async fn error_transitions_are_reported_without_rrset_diff() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, answer(&[("192.0.2.1", 300)], false));
+    //失败前后没有可比较的记录, 只报告错误状态的切换
+    assert_eq!(
+        record(&store, &job, failed("timeout")),
+        vec![change(MONITOR_CHANGE_ERROR, "", "timeout")]
+    );
+    assert!(record(&store, &job, failed("timeout")).is_empty());
+    assert_eq!(
+        record(&store, &job, failed("SERVFAIL")),
+        vec![change(MONITOR_CHANGE_ERROR, "timeout", "SERVFAIL")]
+    );
+    assert_eq!(
+        record(&store, &job, answer(&[("192.0.2.1", 300)], false)),
+        vec![change(MONITOR_CHANGE_ERROR, "SERVFAIL", "")]
+    );
+    //恢复后继续比较 RRset 与 dnssec 状态
+    let mut bogus = answer(&[("192.0.2.1", 300)], false);
+    bogus.dnssec = "Bogus".to_string();
+    assert_eq!(
+        record(&store, &job, bogus),
+        vec![change(MONITOR_CHANGE_DNSSEC, "Insecure", "Bogus")]
+    );
+}
+
+#[actix_web::test]
+async fn change_during_outage_is_reported_after_recovery() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, answer(&[("192.0.2.1", 300)], true));
+    record(&store, &job, failed("timeout"));
+    //失败期间记录被修改, 恢复后与失败前的成功应答比较
+    let mut recovered = answer(&[("192.0.2.9", 60)], true);
+    recovered.dnssec = "Secure".to_string();
+    assert_eq!(
+        record(&store, &job, recovered),
+        vec![
+            change(MONITOR_CHANGE_ERROR, "timeout", ""),
+            change(MONITOR_CHANGE_RRSET, "192.0.2.1", "192.0.2.9"),
+            change(MONITOR_CHANGE_DNSSEC, "Insecure", "Secure"),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn first_successful_answer_after_failures_is_baseline() {
+    let store = MonitorStore::new();
+    let job = job(&store);
+    record(&store, &job, failed("timeout"));
+    assert_eq!(
+        record(&store, &job, answer(&[("192.0.2.1", 300)], false)),
+        vec![change(MONITOR_CHANGE_ERROR, "timeout", "")]
+    );
+    assert!(record(&store, &job, answer(&[("192.0.2.1", 200)], false)).is_empty());
+}
@@ -0,0 +1,93 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//NOTIFY: 从服务器的应答码与 SOA serial 同步
+mod common;
+
+use actix_web::test::TestRequest;
+use common::MockServer;
+use serde_json::{Value, json};
+use std::env;
+use tokio::net::UdpSocket;
+
+const TOKEN: &str = "test-token";
+
+async fn notify(body: Value) -> (u16, Value) {
+    common::init();
+    unsafe {
+        env::set_var("UPDATE_TOKEN", TOKEN);
+    }
+    let request = TestRequest::post()
+        .uri("/v1/notify")
+        .insert_header(("Authorization", format!("Bearer {}", TOKEN)))
+        .set_json(body);
+    common::call(request).await
+}
+
+#[actix_web::test]
+async fn notify_polls_secondaries() {
+    let server = MockServer::start().await;
+    //收到 NOTIFY 但不应答
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let silent = silent.local_addr().unwrap().to_string();
+    let body = json!({
+        "zone": "example.test",
+        "primary": server.addr(),
+        "secondaries": [server.addr(), silent],
+        "deadlineMs": 1500,
+        "intervalMs": 200,
+    });
+    let (status, body) = notify(body).await;
+    assert_eq!(status, 200, "{}", body);
+    let report = &body["data"];
+    assert_eq!(report["zone"], "example.test");
+    assert_eq!(report["primarySerial"], 2026101901u32);
+
+    let synced = &report["secondaries"][0];
+    assert_eq!(synced["responseCode"], "NOERROR");
+    assert_eq!(synced["authoritative"], true);
+    assert_eq!(synced["serial"], 2026101901u32);
+    assert_eq!(synced["synced"], true);
+    assert_eq!(synced["polls"], 1);
+
+    let silent = &report["secondaries"][1];
+    assert!(silent["responseCode"].is_null());
+    assert!(silent["error"].is_string());
+    assert_eq!(silent["synced"], false);
+}
+
+#[actix_web::test]
+async fn notify_rejects_invalid_request() {
+    let body = json!({"zone": "example.test", "primary": "127.0.0.1", "secondaries": []});
+    let (status, body) = notify(body).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "NOTIFY_SECONDARIES_EMPTY");
+
+    let body = json!({
+        "zone": "example.test",
+        "primary": "127.0.0.1",
+        "secondaries": ["127.0.0.1"],
+        "intervalMs": 10,
+    });
+    let (status, body) = notify(body).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "QUERY_OPTION_INVALID");
+    assert!(
+        body["msg"].as_str().unwrap().contains("intervalMs"),
+        "{body}"
+    );
+}
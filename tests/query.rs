@@ -0,0 +1,316 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//普通查询: 成功、NXDOMAIN、超时与截断后改用 TCP
+mod common;
+
+use actix_web::body;
+use actix_web::test;
+use common::MockServer;
+use dns_tool::sse;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+async fn query(server: &MockServer, domain: &str, record_type: &str) -> (u16, Value) {
+    common::init();
+    let body = json!({"domain": domain, "recordType": record_type, "dnsServer": server.addr()});
+    common::post("/v1/query", body).await
+}
+
+fn values(body: &Value) -> Vec<&str> {
+    body["data"]["record"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["value"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn query_success() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "www.example.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["code"], "1");
+    let mut records = values(&body);
+    records.sort();
+    assert_eq!(records, ["192.0.2.1", "192.0.2.2"]);
+    assert_eq!(body["data"]["authoritative"], true);
+    assert_eq!(body["data"]["record"][0]["ttl"], 300);
+}
+
+#[actix_web::test]
+async fn query_cname() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "alias.example.test", "CNAME").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(values(&body), ["www.example.test."]);
+}
+
+#[actix_web::test]
+async fn query_nxdomain() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "missing.example.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    assert!(values(&body).is_empty());
+}
+
+#[actix_web::test]
+async fn query_nodata() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "www.example.test", "AAAA").await;
+    assert_eq!(status, 200, "{body}");
+    assert!(values(&body).is_empty());
+}
+
+#[actix_web::test]
+async fn query_timeout() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "timeout.example.test", "A").await;
+    assert_eq!(status, 504, "{body}");
+    assert_eq!(body["code"], "DNS_TIMEOUT");
+}
+
+#[actix_web::test]
+async fn query_retries_report_attempts() {
+    common::init();
+    let server = MockServer::start().await;
+    let body = json!({"domain": "timeout.example.test", "recordType": "A",
+        "dnsServer": server.addr(), "timeoutMs": 200, "retries": 2, "backoffMs": 10});
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 502, "{body}");
+    assert_eq!(body["code"], "DNS_ATTEMPTS_FAILED");
+
+    let body = json!({"domain": "www.example.test", "recordType": "A",
+        "dnsServer": server.addr(), "timeoutMs": 500, "retries": 2});
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["attempts"][0]["outcome"], "ok");
+    assert_eq!(body["data"]["attempts"][0]["timeoutMs"], 500);
+
+    let body = json!({"domain": "www.example.test", "recordType": "A",
+        "dnsServer": server.addr(), "retries": 99});
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "QUERY_OPTION_INVALID");
+}
+
+#[actix_web::test]
+async fn query_truncated_retries_over_tcp() {
+    let server = MockServer::start().await;
+    let (status, body) = query(&server, "big.example.test", "TXT").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(values(&body).len(), 32);
+    let outcomes: Vec<&str> = body["data"]["attempts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|attempt| attempt["outcome"].as_str().unwrap())
+        .collect();
+    assert_eq!(outcomes, ["truncated", "ok"]);
+}
+
+//ECS 选项发送到服务器, 服务器返回的 scope prefix 写入结果
+#[actix_web::test]
+async fn query_client_subnet() {
+    let server = MockServer::start().await;
+    common::init();
+    let body = json!({
+        "domain": "www.example.test",
+        "recordType": "A",
+        "dnsServer": server.addr(),
+        "clientSubnet": "198.51.100.7/24",
+    });
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["data"]["clientSubnet"], "198.51.100.0/24");
+    assert_eq!(body["data"]["scopePrefix"], 24);
+
+    let body = json!({
+        "domain": "www.example.test",
+        "recordType": "A",
+        "dnsServer": server.addr(),
+        "clientSubnet": "not-a-subnet",
+    });
+    let (status, body) = common::post("/v1/query", body).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "CLIENT_SUBNET_INVALID");
+}
+
+#[actix_web::test]
+async fn query_subnets_compare() {
+    let server = MockServer::start().await;
+    common::init();
+    let body = json!({
+        "domain": "www.example.test",
+        "recordType": "A",
+        "dnsServer": server.addr(),
+        "clientSubnets": ["198.51.100.0/24", "2001:db8::/56"],
+    });
+    let (status, body) = common::post("/v1/query_subnets", body).await;
+    assert_eq!(status, 200, "{body}");
+    let results = body["data"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["clientSubnet"], "198.51.100.0/24");
+    assert_eq!(results[0]["result"]["scopePrefix"], 24);
+    assert_eq!(results[1]["result"]["scopePrefix"], 56);
+
+    let body = json!({
+        "domain": "www.example.test",
+        "recordType": "A",
+        "dnsServer": server.addr(),
+        "clientSubnets": ["198.51.100.0/24", "bad"],
+    });
+    let (status, body) = common::post("/v1/query_subnets", body).await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "CLIENT_SUBNET_INVALID");
+}
+
+#[actix_web::test]
+async fn query_idn_warnings_are_codes() {
+    let server = MockServer::start().await;
+    //第一个字母是西里尔字母 а
+    let (status, body) = query(&server, "\u{430}pple.example.test", "A").await;
+    assert_eq!(status, 200, "{body}");
+    let warnings = &body["data"]["idn"]["warnings"];
+    assert_eq!(warnings[0]["code"], "IDN_MIXED_SCRIPTS");
+    assert_eq!(warnings[0]["args"]["label"], "\u{430}pple");
+    assert_eq!(warnings[0]["args"]["scripts"], "Cyrillic, Latin");
+    assert_eq!(warnings[1]["code"], "IDN_CONFUSABLE");
+    assert_eq!(warnings[1]["args"]["prototype"], "apple");
+}
+
+#[actix_web::test]
+async fn query_stream_rejects_bad_request_before_streaming() {
+    common::init();
+    let server = MockServer::start().await;
+    let (status, body) =
+        common::get("/v1/query/stream?domain=www.example.test&recordType=A&dnsServers=").await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "DNS_SERVER_EMPTY");
+
+    let (status, body) = common::get(&format!(
+        "/v1/query_subnets/stream?domain=www.example.test&recordType=A&dnsServer={}&clientSubnets=bad",
+        server.addr()
+    ))
+    .await;
+    assert_eq!(status, 400, "{body}");
+    assert_eq!(body["code"], "CLIENT_SUBNET_INVALID");
+
+    //单个服务器的错误在事件流中推送
+    let (status, events) = common::call_text(test::TestRequest::get().uri(&format!(
+        "/v1/query/stream?domain=timeout.example.test&recordType=A&dnsServers={}",
+        server.addr()
+    )))
+    .await;
+    assert_eq!(status, 200, "{events}");
+    assert!(events.contains("event: result\ndata: "), "{events}");
+    assert!(events.contains("\"code\":\"DNS_TIMEOUT\""), "{events}");
+    assert!(events.contains("event: done\n"), "{events}");
+}
+
+fn counted_tasks(finished: &Arc<AtomicUsize>) -> Vec<impl Future<Output = usize> + use<>> {
+    (0..3)
+        .map(|index| {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                index
+            }
+        })
+        .collect()
+}
+
+#[actix_web::test]
+async fn stream_tasks_stop_with_the_response() {
+    let finished = Arc::new(AtomicUsize::new(0));
+    let response = sse::spawn_all(counted_tasks(&finished));
+    let events = body::to_bytes(response.into_body()).await.unwrap();
+    let events = String::from_utf8_lossy(&events);
+    assert_eq!(events.matches("event: result\n").count(), 3, "{events}");
+    assert!(
+        events.ends_with("event: done\ndata: {\"total\":3}\n\n"),
+        "{events}"
+    );
+    assert_eq!(finished.load(Ordering::SeqCst), 3);
+
+    //客户端断开 (响应被丢弃) 后未完成的任务被取消
+    let finished = Arc::new(AtomicUsize::new(0));
+    drop(sse::spawn_all(counted_tasks(&finished)));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(finished.load(Ordering::SeqCst), 0);
+}
+
+#[actix_web::test]
+async fn query_export_formats() {
+    common::init();
+    let server = MockServer::start().await;
+    let body =
+        json!({"domain": "long.example.test", "recordType": "TXT", "dnsServer": server.addr()});
+
+    //超过 255 字节的 TXT 拆成多个字符串
+    let (status, zone) = common::call_text(
+        test::TestRequest::post()
+            .uri("/v1/query?format=zone")
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{zone}");
+    let rdata = format!(
+        "\"{}{}\" \"{}\"",
+        "a".repeat(200),
+        "b".repeat(55),
+        "b".repeat(45)
+    );
+    assert!(
+        zone.contains(&format!("long.example.test.\t300\tIN\tTXT\t{rdata}\n")),
+        "{zone}"
+    );
+
+    //Accept: text/plain 仍返回 json, dig 只能用 format=dig 指定
+    let (status, json) = common::call(
+        test::TestRequest::post()
+            .uri("/v1/query")
+            .insert_header(("Accept", "text/plain, */*"))
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{json}");
+    assert_eq!(json["data"]["record"].as_array().unwrap().len(), 1);
+
+    let (status, csv) = common::call_text(
+        test::TestRequest::post()
+            .uri("/v1/query")
+            .insert_header(("Accept", "text/csv"))
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{csv}");
+    assert!(csv.starts_with("domain,dnsServer,"), "{csv}");
+
+    let (status, dig) = common::call_text(
+        test::TestRequest::post()
+            .uri("/v1/query?format=dig")
+            .set_json(&body),
+    )
+    .await;
+    assert_eq!(status, 200, "{dig}");
+    assert!(dig.contains(&format!("\tIN\tTXT\t{rdata}\n")), "{dig}");
+}
@@ -0,0 +1,112 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//本地转发解析器: 故障转移、缓存与实时历史
+mod common;
+
+use actix_web::web::Data;
+use common::MockServer;
+use dns_tool::history::HistoryStore;
+use dns_tool::resolver::{self, Resolver};
+use hickory_client::proto::op::{Message, MessageType, Query};
+use hickory_client::proto::rr::{Name, RecordType};
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+async fn query(port: u16, id: u16, domain: &str) -> Message {
+    let mut request = Message::new();
+    request
+        .set_id(id)
+        .set_message_type(MessageType::Query)
+        .set_recursion_desired(true)
+        .add_query(Query::query(Name::from_str(domain).unwrap(), RecordType::A));
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket
+        .send_to(&request.to_vec().unwrap(), ("127.0.0.1", port))
+        .await
+        .unwrap();
+    let mut buf = [0u8; 4096];
+    let len = tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    Message::from_vec(&buf[..len]).unwrap()
+}
+
+fn addresses(response: &Message) -> Vec<String> {
+    let mut values: Vec<String> = response
+        .answers()
+        .iter()
+        .map(|record| record.data().to_string())
+        .collect();
+    values.sort();
+    values
+}
+
+#[actix_web::test]
+async fn resolver_failover_cache_and_history() {
+    common::init();
+    let server = MockServer::start().await;
+    //第一个上游收到查询但不应答, 超时后转到 mock 服务器
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let listen_port = UdpSocket::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    unsafe {
+        env::set_var("RESOLVER_PORT", listen_port.to_string());
+        env::set_var(
+            "RESOLVER_UPSTREAMS",
+            format!("{},{}", silent.local_addr().unwrap(), server.addr()),
+        );
+    }
+
+    let history = Data::new(HistoryStore::open(":memory:").unwrap());
+    let mut live = history.subscribe();
+    let resolver = Data::new(Resolver::new(history));
+    tokio::spawn(resolver::run(resolver.clone()));
+    while resolver.status().listen.is_none() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let response = query(listen_port, 1001, "www.example.test.").await;
+    assert_eq!(response.id(), 1001);
+    assert_eq!(addresses(&response), ["192.0.2.1", "192.0.2.2"]);
+    let record = live.recv().await.unwrap();
+    assert_eq!(record.kind, "resolver");
+    assert_eq!(record.domain, "www.example.test");
+    assert_eq!(record.dns_server, server.addr());
+
+    //第二次由缓存应答
+    let response = query(listen_port, 1002, "www.example.test.").await;
+    assert_eq!(response.id(), 1002);
+    assert_eq!(addresses(&response), ["192.0.2.1", "192.0.2.2"]);
+    assert_eq!(live.recv().await.unwrap().dns_server, "cache");
+
+    let status = resolver.status();
+    assert!(status.enabled);
+    assert_eq!(status.cache_hits, 1);
+    assert_eq!(status.cache_misses, 1);
+    assert_eq!(status.upstreams[0].queries, 1);
+    assert_eq!(status.upstreams[0].failures, 1);
+    assert_eq!(status.upstreams[1].queries, 1);
+    assert_eq!(status.upstreams[1].failures, 0);
+}
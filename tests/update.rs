@@ -0,0 +1,126 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//DNS UPDATE: 令牌、请求校验与审计日志
+mod common;
+
+use actix_web::test::TestRequest;
+use common::MockServer;
+use dns_tool::constants::{DnsUpdateRequest, UpdateAuditQuery};
+use dns_tool::history::HistoryStore;
+use dns_tool::update;
+use serde_json::{Value, json};
+use std::env;
+
+const TOKEN: &str = "test-token";
+
+fn setup() {
+    common::init();
+    unsafe {
+        env::set_var("UPDATE_TOKEN", TOKEN);
+        env::set_var(
+            "TSIG_KEYS",
+            "update-key:hmac-sha256:c2VjcmV0LXNlY3JldC1zZWNyZXQtc2VjcmV0",
+        );
+    }
+}
+
+async fn post(token: Option<&str>, body: Value) -> (u16, Value) {
+    let mut request = TestRequest::post().uri("/v1/update").set_json(body);
+    if let Some(token) = token {
+        request = request.insert_header(("Authorization", format!("Bearer {}", token)));
+    }
+    common::call(request).await
+}
+
+fn request(dns_server: &str) -> Value {
+    json!({
+        "zone": "example.test",
+        "dnsServer": dns_server,
+        "keyName": "update-key",
+        "action": "add",
+        "name": "new",
+        "recordType": "A",
+        "values": ["192.0.2.10"],
+    })
+}
+
+#[actix_web::test]
+async fn update_checks_token_and_request() {
+    setup();
+    let (status, body) = post(None, request("127.0.0.1:53")).await;
+    assert_eq!(status, 401);
+    assert_eq!(body["code"], "UNAUTHORIZED");
+    let (status, _) = post(Some("wrong"), request("127.0.0.1:53")).await;
+    assert_eq!(status, 401);
+
+    let mut body = request("127.0.0.1:53");
+    body["keyName"] = json!("missing");
+    let (status, body) = post(Some(TOKEN), body).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "TSIG_KEY_NOT_FOUND");
+
+    //zone 外的名称与没有记录值的 add
+    let mut body = request("127.0.0.1:53");
+    body["name"] = json!("www.example.org.");
+    let (_, body) = post(Some(TOKEN), body).await;
+    assert_eq!(body["code"], "UPDATE_NAME_OUTSIDE_ZONE");
+    let mut body = request("127.0.0.1:53");
+    body["values"] = json!([]);
+    let (_, body) = post(Some(TOKEN), body).await;
+    assert_eq!(body["code"], "UPDATE_VALUES_REQUIRED");
+    let mut body = request("127.0.0.1:53");
+    body["values"] = json!(["not-an-ip"]);
+    let (_, body) = post(Some(TOKEN), body).await;
+    assert_eq!(body["code"], "UPDATE_RECORD_INVALID");
+    let mut body = request("127.0.0.1:53");
+    body["action"] = json!("upsert");
+    let (status, body) = post(Some(TOKEN), body).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "UPDATE_ACTION_UNSUPPORTED");
+    assert!(body["msg"].as_str().unwrap().contains("upsert"), "{body}");
+
+    let keys = TestRequest::get()
+        .uri("/v1/update/keys")
+        .insert_header(("Authorization", format!("Bearer {}", TOKEN)));
+    let (status, body) = common::call(keys).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"], json!(["update-key (hmac-sha256)"]));
+}
+
+//测试服务器不支持 UPDATE, 应答没有 TSIG, 失败同样写入审计日志
+#[actix_web::test]
+async fn update_writes_audit() {
+    setup();
+    let server = MockServer::start().await;
+    let history = HistoryStore::open(":memory:").unwrap();
+    let request: DnsUpdateRequest = serde_json::from_value(request(&server.addr())).unwrap();
+    assert!(update::update(&history, "tester", request).await.is_err());
+
+    let query = UpdateAuditQuery {
+        zone: Some("Example.Test.".to_string()),
+        ..Default::default()
+    };
+    let (total, records) = history.audits(&query).await.unwrap();
+    assert_eq!(total, 1);
+    let record = &records[0];
+    assert_eq!(record.requester, "tester");
+    assert_eq!(record.name, "new.example.test.");
+    assert_eq!(record.record_type, "A");
+    assert_ne!(record.code, "1");
+    assert_ne!(record.code, "PENDING");
+}
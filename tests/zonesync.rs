@@ -0,0 +1,118 @@
+// Apache License
+// Version 2.0, January 2004
+//
+// Copyright (c) 2025 wchar.net
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//区域同步: 每个 NS 地址的 SOA serial, 落后与没有应答的服务器
+mod common;
+
+use common::MockServer;
+use hickory_client::proto::op::{Message, MessageType};
+use hickory_client::proto::rr::rdata::SOA;
+use hickory_client::proto::rr::{Name, RData, Record};
+use serde_json::json;
+use std::str::FromStr;
+use tokio::net::UdpSocket;
+
+const LAGGING_SERIAL: u32 = 2026101899;
+
+//只应答 SOA 的服务器, serial 比 example.test 旧
+async fn serve_lagging(socket: UdpSocket) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let Ok((len, peer)) = socket.recv_from(&mut buf).await else {
+            return;
+        };
+        let Ok(request) = Message::from_vec(&buf[..len]) else {
+            continue;
+        };
+        let Some(query) = request.queries().first() else {
+            continue;
+        };
+        let soa = SOA::new(
+            Name::from_str("ns1.example.test.").unwrap(),
+            Name::from_str("hostmaster.example.test.").unwrap(),
+            LAGGING_SERIAL,
+            3600,
+            600,
+            86400,
+            300,
+        );
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_authoritative(true)
+            .add_query(query.clone())
+            .add_answer(Record::from_rdata(
+                query.name().clone(),
+                300,
+                RData::SOA(soa),
+            ));
+        let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+    }
+}
+
+#[actix_web::test]
+async fn zone_sync_marks_lagging_servers() {
+    common::init();
+    let server = MockServer::start().await;
+    let lagging = UdpSocket::bind(("127.0.0.2", server.port)).await.unwrap();
+    let lagging = tokio::spawn(serve_lagging(lagging));
+
+    let body = json!({"zone": "example.test", "dnsServer": server.addr(), "port": server.port});
+    let (status, body) = common::post("/v1/zone_sync", body).await;
+    lagging.abort();
+    assert_eq!(status, 200, "{}", body);
+    let report = &body["data"];
+    assert_eq!(report["maxSerial"], 2026101901u32);
+    assert_eq!(report["consistent"], false);
+
+    let servers = report["servers"].as_array().unwrap();
+    let find = |ns: &str| {
+        servers
+            .iter()
+            .find(|server| server["ns"] == ns)
+            .unwrap_or_else(|| panic!("{} {:?}", ns, servers))
+    };
+    //192.0.2.53 不可达
+    let unreachable = find("ns1.example.test.");
+    assert_eq!(unreachable["family"], "ipv4");
+    assert!(unreachable["serial"].is_null());
+    assert!(unreachable["error"].is_string());
+
+    let synced = find("ns2.example.test.");
+    assert_eq!(synced["address"], "127.0.0.1");
+    assert_eq!(synced["serial"], 2026101901u32);
+    assert_eq!(synced["refresh"], 3600);
+    assert_eq!(synced["expire"], 86400);
+    assert_eq!(synced["authoritative"], true);
+    assert_eq!(synced["behind"], false);
+    assert_eq!(synced["lag"], 0);
+
+    let behind = find("ns3.example.test.");
+    assert_eq!(behind["serial"], LAGGING_SERIAL);
+    assert_eq!(behind["behind"], true);
+    assert_eq!(behind["lag"], 2);
+
+    let missing = find("ns4.example.test.");
+    assert_eq!(
+        missing["error"],
+        format!(
+            "dns 服务器 {} 没有返回 ns4.example.test. 的 A / AAAA 记录",
+            server.addr()
+        )
+    );
+}